@@ -654,6 +654,78 @@ impl std::fmt::Display for NodeDiff {
     }
 }
 
+/// Paths of added, removed and changed nodes between two trees.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiffSummary {
+    added: Vec<Opath>,
+    removed: Vec<Opath>,
+    changed: Vec<Opath>,
+}
+
+impl DiffSummary {
+    pub fn diff(a: &NodeRef, b: &NodeRef) -> DiffSummary {
+        let mut cache = NodePathCache::new();
+        let mut changes = Vec::new();
+        diff_node(a, b, &mut changes, &mut cache);
+
+        let mut summary = DiffSummary::default();
+        let mut it = changes.into_iter().peekable();
+        while let Some(c) = it.next() {
+            match c.kind {
+                ChangeKind::Added => summary.added.push(c.new_path.unwrap()),
+                ChangeKind::Removed => {
+                    let old_path = c.old_path.unwrap();
+                    // node replaced with a node of incompatible kind is reported as removed and added
+                    let replaced = match it.peek() {
+                        Some(n) => n.kind == ChangeKind::Added && n.new_path.as_ref() == Some(&old_path),
+                        None => false,
+                    };
+                    if replaced {
+                        it.next();
+                        summary.changed.push(old_path);
+                    } else {
+                        summary.removed.push(old_path);
+                    }
+                }
+                ChangeKind::Updated => summary.changed.push(c.new_path.unwrap()),
+                ChangeKind::Moved => unreachable!(),
+            }
+        }
+        summary
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    pub fn added(&self) -> &Vec<Opath> {
+        &self.added
+    }
+
+    pub fn removed(&self) -> &Vec<Opath> {
+        &self.removed
+    }
+
+    pub fn changed(&self) -> &Vec<Opath> {
+        &self.changed
+    }
+}
+
+impl std::fmt::Display for DiffSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for p in self.added.iter() {
+            writeln!(f, "{} {}", ChangeKind::Added, p)?;
+        }
+        for p in self.removed.iter() {
+            writeln!(f, "{} {}", ChangeKind::Removed, p)?;
+        }
+        for p in self.changed.iter() {
+            writeln!(f, "{} {}", ChangeKind::Updated, p)?;
+        }
+        Ok(())
+    }
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -858,4 +930,81 @@ mod tests {
         assert_eq!(d.changes()[8].new_path().unwrap().to_string(), "$.bb");
         assert_eq!(d.changes()[8].kind(), ChangeKind::Added);
     }
+
+    mod diff_summary {
+        use super::*;
+
+        fn paths(p: &Vec<Opath>) -> Vec<String> {
+            p.iter().map(|p| p.to_string()).collect()
+        }
+
+        #[test]
+        fn objects() {
+            let a = NodeRef::from_json(r#"{"keep": 1, "removed": 2, "changed": 3, "nested": {"a": true}}"#).unwrap();
+            let b = NodeRef::from_json(r#"{"keep": 1, "changed": 4, "nested": {"a": true}, "added": 5}"#).unwrap();
+
+            let s = a.diff_summary(&b);
+
+            assert_eq!(paths(s.added()), vec!["$.added"]);
+            assert_eq!(paths(s.removed()), vec!["$.removed"]);
+            assert_eq!(paths(s.changed()), vec!["$.changed"]);
+        }
+
+        #[test]
+        fn arrays() {
+            let a = NodeRef::from_json(r#"{"arr": [1, 2, 3]}"#).unwrap();
+            let b = NodeRef::from_json(r#"{"arr": [1, 5]}"#).unwrap();
+
+            let s = a.diff_summary(&b);
+
+            assert!(s.added().is_empty());
+            assert_eq!(paths(s.removed()), vec!["$.arr[2]"]);
+            assert_eq!(paths(s.changed()), vec!["$.arr[1]"]);
+        }
+
+        #[test]
+        fn replaced_kind() {
+            let a = NodeRef::from_json(r#"{"p": 1}"#).unwrap();
+            let b = NodeRef::from_json(r#"{"p": {"a": 1}}"#).unwrap();
+
+            let s = a.diff_summary(&b);
+
+            assert!(s.added().is_empty());
+            assert!(s.removed().is_empty());
+            assert_eq!(paths(s.changed()), vec!["$.p"]);
+        }
+
+        #[test]
+        fn equal() {
+            let a = NodeRef::from_json(r#"{"p": [1, {"a": null}]}"#).unwrap();
+            let b = a.deep_copy();
+
+            assert!(a.diff_summary(&b).is_empty());
+        }
+    }
+
+    mod diff_string {
+        use super::*;
+
+        #[test]
+        fn change_kinds() {
+            let a = NodeRef::from_json(r#"{"keep": 1, "removed": "x", "changed": 3, "arr": [1, 2]}"#).unwrap();
+            let b = NodeRef::from_json(r#"{"keep": 1, "changed": 4, "arr": [1, 2, {"a": true}], "added": null}"#).unwrap();
+
+            let s = a.diff_string(&b);
+            let lines: Vec<&str> = s.lines().collect();
+
+            assert_eq!(lines.len(), 4);
+            assert!(lines.contains(&"+ $.added: null"));
+            assert!(lines.contains(&r#"+ $.arr[2]: {"a":true}"#));
+            assert!(lines.contains(&r#"- $.removed: "x""#));
+            assert!(lines.contains(&"~ $.changed: 3 -> 4"));
+        }
+
+        #[test]
+        fn equal() {
+            let a = NodeRef::from_json(r#"{"p": [1, {"a": null}]}"#).unwrap();
+            assert_eq!(a.diff_string(&a.deep_copy()), "");
+        }
+    }
 }
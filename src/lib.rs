@@ -8,7 +8,7 @@ extern crate kg_display_derive;
 extern crate serde_derive;
 
 use std::borrow::Cow;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
@@ -16,19 +16,37 @@ use heapsize::HeapSizeOf;
 
 use kg_diag::io::fs;
 use kg_diag::*;
-use kg_symbol::{Symbol, SymbolMap};
-
-pub use tree::convert::Primitive;
+/// Object keys are `Symbol`s, strings interned in a global, thread-safe table. Creating a
+/// symbol (e.g. with `Symbol::from(&str)`) for a string that already has a live symbol
+/// returns the existing one without allocating, so repeated keys are stored only once and
+/// compared by pointer. Interned strings are freed when their last symbol is dropped.
+pub use kg_symbol::{Symbol, SymbolMap};
+
+pub use tree::convert::{convert_file, Primitive};
+pub use tree::frozen::{FrozenNode, FrozenValue};
 use tree::metadata::Metadata;
 pub use tree::metadata::{FileFormat, FileInfo};
 pub use tree::node::{Kind, KindMask, Node, Value};
-pub use tree::{NodeRef, TreeErrorDetail};
+pub use tree::schema::{validate, ValidationError, ValidationErrorDetail};
+pub use tree::source_map::SourceMap;
+pub use tree::{MergeStrategy, NodeRef, TreeErrorDetail};
 
 mod tree;
 
 pub type Properties = SymbolMap<NodeRef>;
 pub type Elements = Vec<NodeRef>;
 
+/// Adds string access to [`Symbol`], which otherwise only exposes `as_ref()` and `Deref`.
+pub trait SymbolExt {
+    fn as_str(&self) -> &str;
+}
+
+impl SymbolExt for Symbol {
+    fn as_str(&self) -> &str {
+        self.as_ref()
+    }
+}
+
 pub mod diff;
 pub mod opath;
 
@@ -37,6 +55,51 @@ pub mod serial;
 thread_local! {
     static BASE_PATH: RefCell<PathBuf> = RefCell::new(std::env::current_dir().unwrap());
     static BASE_PATH_STACK: RefCell<Vec<PathBuf>> = RefCell::new(Vec::new());
+    static FLOAT_FORMAT: Cell<FloatFormat> = Cell::new(FloatFormat::default());
+}
+
+/// Formatting policy for float values in node serialization and `Display` of nodes and expressions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatFormat {
+    /// Shortest representation that parses back to the same value, always with a decimal point
+    /// (or an exponent), so that `1.0` is not written as `1`.
+    Shortest,
+    /// Value rounded to the given number of significant digits.
+    Significant(u32),
+}
+
+impl FloatFormat {
+    pub fn round(&self, n: f64) -> f64 {
+        match *self {
+            FloatFormat::Shortest => n,
+            FloatFormat::Significant(digits) => {
+                if n.is_finite() {
+                    let digits = digits.max(1) as usize;
+                    format!("{:.*e}", digits - 1, n).parse().unwrap_or(n)
+                } else {
+                    n
+                }
+            }
+        }
+    }
+
+    pub fn format(&self, n: f64) -> String {
+        format!("{:?}", self.round(n))
+    }
+}
+
+impl Default for FloatFormat {
+    fn default() -> Self {
+        FloatFormat::Shortest
+    }
+}
+
+pub fn set_float_format(format: FloatFormat) {
+    FLOAT_FORMAT.with(|f| f.set(format));
+}
+
+pub fn float_format() -> FloatFormat {
+    FLOAT_FORMAT.with(|f| f.get())
 }
 
 pub fn set_base_path<P: AsRef<Path> + Into<PathBuf>>(base_path: P) {
@@ -4,11 +4,20 @@ use kg_utils::collections::LruCache;
 
 use super::*;
 
+/// Cache of node paths, as computed by [`Opath::from`], keyed by node identity.
+///
+/// Implementations are expected to compute and store the path on cache miss, so
+/// [`get`](OpathCache::get) always returns a path for given node. Entries are not invalidated
+/// when the tree is modified, so a cache should only be used while the tree structure
+/// does not change.
 pub trait OpathCache {
+    /// Returns path for node `n`, computing and caching it if not yet cached.
     fn get(&mut self, n: &NodeRef) -> &Opath;
 
+    /// Checks if path for node `n` is cached.
     fn contains(&mut self, n: &NodeRef) -> bool;
 
+    /// Returns the number of cached paths.
     fn len(&self) -> usize;
 }
 
@@ -1,5 +1,8 @@
 use super::*;
 use crate::opath::expr::func::FuncCallErrorDetail::{NonBinaryNode, RegexParse};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use std::path::{Component, Path, PathBuf};
 pub type FuncCallError = BasicDiag;
 
 pub type FuncCallResult = Result<(), FuncCallError>;
@@ -85,6 +88,42 @@ pub enum FuncCallErrorDetail {
     #[display(fmt = "cannot parse expression")]
     NodeParse,
 
+    #[display(fmt = "cannot parse date from '{input}'")]
+    InvalidDate { input: String },
+
+    #[display(fmt = "invalid date format '{format}'")]
+    InvalidDateFormat { format: String },
+
+    #[display(fmt = "unsupported uuid version '{version}'")]
+    UnsupportedUuidVersion { version: String },
+
+    #[display(fmt = "unknown hash algorithm '{name}'")]
+    UnknownHashAlgorithm { name: String },
+
+    #[display(fmt = "function '{id}' does not accept arguments of type '{kind}'")]
+    FuncCallInvalidArgType { id: FuncId, kind: Kind },
+
+    #[display(fmt = "unknown case style '{style}'")]
+    UnknownCaseStyle { style: String },
+
+    #[display(fmt = "invalid clamp bounds, min {min} and max {max} must be ordered numbers")]
+    InvalidClampBounds { min: f64, max: f64 },
+
+    #[display(fmt = "range step cannot be zero")]
+    ZeroRangeStep,
+
+    #[display(fmt = "range bounds must be finite, got start {start} and stop {stop}")]
+    NonFiniteRangeBounds { start: f64, stop: f64 },
+
+    #[display(fmt = "range step must be finite, got {step}")]
+    NonFiniteRangeStep { step: f64 },
+
+    #[display(fmt = "function '{id}' would generate more than {max} elements")]
+    TooManyElements { id: FuncId, max: usize },
+
+    #[display(fmt = "method '{id}' for type '{kind}' requires argument to be a single value")]
+    MethodCallSingleArgRequired { id: MethodId, kind: Kind },
+
     #[display(fmt = "error while calling method '{id}' for type '{kind}'")]
     MethodCallCustom {
         id: MethodId,
@@ -127,6 +166,39 @@ pub enum FuncId {
     Stringify,
     FindNew,
     FindOld,
+    Zip,
+    Entries,
+    ObjectFromEntries,
+    Date,
+    Now,
+    Uuid,
+    Hash,
+    Merge,
+    CamelCase,
+    SnakeCase,
+    KebabCase,
+    Concat,
+    ZipObject,
+    ToFixed,
+    Clamp,
+    Range,
+    Repeat,
+    AbsPath,
+    RelPath,
+    Basename,
+    Dirname,
+    Extname,
+    Exists,
+    IsString,
+    IsNumber,
+    IsArray,
+    IsObject,
+    IsBool,
+    IsNull,
+    Union,
+    Intersection,
+    Difference,
+    EntriesSorted,
     Custom(String),
 }
 
@@ -146,6 +218,40 @@ impl FuncId {
             "stringify" => FuncId::Stringify,
             "findNew" => FuncId::FindNew,
             "findOld" => FuncId::FindOld,
+            "zip" => FuncId::Zip,
+            "entries" => FuncId::Entries,
+            // camelCase name is kept as an alias, for consistency with older functions
+            "object_from_entries" | "objectFromEntries" => FuncId::ObjectFromEntries,
+            "date" => FuncId::Date,
+            "now" => FuncId::Now,
+            "uuid" => FuncId::Uuid,
+            "hash" => FuncId::Hash,
+            "merge" => FuncId::Merge,
+            "camel_case" => FuncId::CamelCase,
+            "snake_case" => FuncId::SnakeCase,
+            "kebab_case" => FuncId::KebabCase,
+            "concat" => FuncId::Concat,
+            "zip_object" => FuncId::ZipObject,
+            "to_fixed" => FuncId::ToFixed,
+            "clamp" => FuncId::Clamp,
+            "range" => FuncId::Range,
+            "repeat" => FuncId::Repeat,
+            "abs_path" => FuncId::AbsPath,
+            "rel_path" => FuncId::RelPath,
+            "basename" => FuncId::Basename,
+            "dirname" => FuncId::Dirname,
+            "extname" => FuncId::Extname,
+            "exists" => FuncId::Exists,
+            "is_string" => FuncId::IsString,
+            "is_number" => FuncId::IsNumber,
+            "is_array" => FuncId::IsArray,
+            "is_object" => FuncId::IsObject,
+            "is_bool" => FuncId::IsBool,
+            "is_null" => FuncId::IsNull,
+            "union" => FuncId::Union,
+            "intersection" => FuncId::Intersection,
+            "difference" => FuncId::Difference,
+            "entries_sorted" => FuncId::EntriesSorted,
             _ => FuncId::Custom(f.to_string()),
         }
     }
@@ -165,6 +271,39 @@ impl FuncId {
             FuncId::Stringify => "stringify",
             FuncId::FindNew => "findNew",
             FuncId::FindOld => "findOld",
+            FuncId::Zip => "zip",
+            FuncId::Entries => "entries",
+            FuncId::ObjectFromEntries => "object_from_entries",
+            FuncId::Date => "date",
+            FuncId::Now => "now",
+            FuncId::Uuid => "uuid",
+            FuncId::Hash => "hash",
+            FuncId::Merge => "merge",
+            FuncId::CamelCase => "camel_case",
+            FuncId::SnakeCase => "snake_case",
+            FuncId::KebabCase => "kebab_case",
+            FuncId::Concat => "concat",
+            FuncId::ZipObject => "zip_object",
+            FuncId::ToFixed => "to_fixed",
+            FuncId::Clamp => "clamp",
+            FuncId::Range => "range",
+            FuncId::Repeat => "repeat",
+            FuncId::AbsPath => "abs_path",
+            FuncId::RelPath => "rel_path",
+            FuncId::Basename => "basename",
+            FuncId::Dirname => "dirname",
+            FuncId::Extname => "extname",
+            FuncId::Exists => "exists",
+            FuncId::IsString => "is_string",
+            FuncId::IsNumber => "is_number",
+            FuncId::IsArray => "is_array",
+            FuncId::IsObject => "is_object",
+            FuncId::IsBool => "is_bool",
+            FuncId::IsNull => "is_null",
+            FuncId::Union => "union",
+            FuncId::Intersection => "intersection",
+            FuncId::Difference => "difference",
+            FuncId::EntriesSorted => "entries_sorted",
             FuncId::Custom(ref s) => s,
         }
     }
@@ -191,6 +330,35 @@ pub enum MethodId {
     Join,
     Replace,
     Split,
+    First,
+    Last,
+    Nth,
+    Count,
+    Any,
+    All,
+    Map,
+    Filter,
+    Reduce,
+    Closest,
+    Next,
+    Prev,
+    ToJsonString,
+    ParseJson,
+    ParseYaml,
+    ParseToml,
+    KeysMatching,
+    Pick,
+    Omit,
+    Rekey,
+    Reject,
+    KeysWhere,
+    Slugify,
+    Lines,
+    Words,
+    DepthFromRoot,
+    FlatMap,
+    Partition,
+    MergeDeep,
     Custom(String),
 }
 
@@ -210,6 +378,35 @@ impl MethodId {
             "join" => MethodId::Join,
             "replace" => MethodId::Replace,
             "split" => MethodId::Split,
+            "first" => MethodId::First,
+            "last" => MethodId::Last,
+            "nth" => MethodId::Nth,
+            "count" => MethodId::Count,
+            "any" => MethodId::Any,
+            "all" => MethodId::All,
+            "map" => MethodId::Map,
+            "filter" => MethodId::Filter,
+            "reduce" => MethodId::Reduce,
+            "closest" => MethodId::Closest,
+            "next" => MethodId::Next,
+            "prev" => MethodId::Prev,
+            "to_json_string" => MethodId::ToJsonString,
+            "parse_json" => MethodId::ParseJson,
+            "parse_yaml" => MethodId::ParseYaml,
+            "parse_toml" => MethodId::ParseToml,
+            "keys_matching" => MethodId::KeysMatching,
+            "pick" => MethodId::Pick,
+            "omit" => MethodId::Omit,
+            "rekey" => MethodId::Rekey,
+            "reject" => MethodId::Reject,
+            "keys_where" => MethodId::KeysWhere,
+            "slugify" => MethodId::Slugify,
+            "lines" => MethodId::Lines,
+            "words" => MethodId::Words,
+            "depth_from_root" => MethodId::DepthFromRoot,
+            "flat_map" => MethodId::FlatMap,
+            "partition" => MethodId::Partition,
+            "merge_deep" => MethodId::MergeDeep,
             _ => MethodId::Custom(f.to_string()),
         }
     }
@@ -229,9 +426,58 @@ impl MethodId {
             MethodId::Join => "join",
             MethodId::Replace => "replace",
             MethodId::Split => "split",
+            MethodId::First => "first",
+            MethodId::Last => "last",
+            MethodId::Nth => "nth",
+            MethodId::Count => "count",
+            MethodId::Any => "any",
+            MethodId::All => "all",
+            MethodId::Map => "map",
+            MethodId::Filter => "filter",
+            MethodId::Reduce => "reduce",
+            MethodId::Closest => "closest",
+            MethodId::Next => "next",
+            MethodId::Prev => "prev",
+            MethodId::ToJsonString => "to_json_string",
+            MethodId::ParseJson => "parse_json",
+            MethodId::ParseYaml => "parse_yaml",
+            MethodId::ParseToml => "parse_toml",
+            MethodId::KeysMatching => "keys_matching",
+            MethodId::Pick => "pick",
+            MethodId::Omit => "omit",
+            MethodId::Rekey => "rekey",
+            MethodId::Reject => "reject",
+            MethodId::KeysWhere => "keys_where",
+            MethodId::Slugify => "slugify",
+            MethodId::Lines => "lines",
+            MethodId::Words => "words",
+            MethodId::DepthFromRoot => "depth_from_root",
+            MethodId::FlatMap => "flat_map",
+            MethodId::Partition => "partition",
+            MethodId::MergeDeep => "merge_deep",
             MethodId::Custom(ref s) => s,
         }
     }
+
+    /// Returns `true` for methods applied once to the whole current node set
+    /// rather than to each node separately.
+    pub fn is_set_method(&self) -> bool {
+        match *self {
+            MethodId::First
+            | MethodId::Last
+            | MethodId::Nth
+            | MethodId::Count
+            | MethodId::Any
+            | MethodId::All
+            | MethodId::Map
+            | MethodId::Filter
+            | MethodId::Reduce
+            | MethodId::Reject
+            | MethodId::FlatMap
+            | MethodId::Partition => true,
+            _ => false,
+        }
+    }
 }
 
 impl std::fmt::Display for MethodId {
@@ -560,6 +806,12 @@ impl std::fmt::Debug for Method {
     }
 }
 
+/// Maximum number of decimals written by `to_fixed()`, more than enough for any `f64`.
+const MAX_FIXED_DECIMALS: usize = 20;
+
+/// Maximum length of arrays generated by functions like `range()`.
+const MAX_GENERATED_LEN: usize = 1_000_000;
+
 pub(super) fn apply_func_to(
     id: &FuncId,
     args: Args,
@@ -824,6 +1076,421 @@ pub(super) fn apply_func_to(
                 }))
             }
         }
+        FuncId::Zip => {
+            args.check_count_func(id, 2, 0)?;
+            let columns = args.resolve(true, env)?;
+            let len = columns.iter().map(|c| c.len()).min().unwrap_or(0);
+            let mut columns: Vec<_> = columns.into_iter().map(|c| c.into_iter()).collect();
+            let mut rows = Vec::with_capacity(len);
+            for _ in 0..len {
+                let row = columns.iter_mut().map(|c| c.next().unwrap()).collect();
+                rows.push(NodeRef::array(row));
+            }
+            out.add(NodeRef::array(rows));
+            Ok(())
+        }
+        // `entries_sorted()` orders entries lexicographically by key instead of insertion order
+        FuncId::Entries | FuncId::EntriesSorted => {
+            args.check_count_func(id, 1, 1)?;
+            let objects = args.resolve_column(false, 0, env)?;
+            for o in objects.into_iter() {
+                if let Value::Object(ref props) = *o.data().value() {
+                    let mut props: Vec<_> = props.iter().collect();
+                    if *id == FuncId::EntriesSorted {
+                        props.sort_by(|a, b| a.0.as_ref().cmp(b.0.as_ref()));
+                    }
+                    let entries = props
+                        .into_iter()
+                        .map(|(k, v)| NodeRef::array(vec![NodeRef::string(k.as_ref()), v.deep_copy()]))
+                        .collect();
+                    out.add(NodeRef::array(entries));
+                }
+            }
+            Ok(())
+        }
+        FuncId::ObjectFromEntries => {
+            args.check_count_func(id, 1, 1)?;
+            let arrays = args.resolve_column(false, 0, env)?;
+            for a in arrays.into_iter() {
+                if let Value::Array(ref entries) = *a.data().value() {
+                    let mut map = Properties::with_capacity(entries.len());
+                    for e in entries.iter() {
+                        // entries without a key are skipped, missing values become null
+                        if let Value::Array(ref pair) = *e.data().value() {
+                            if let Some(k) = pair.get(0) {
+                                let v = match pair.get(1) {
+                                    Some(v) => v.deep_copy(),
+                                    None => NodeRef::null(),
+                                };
+                                map.insert(k.as_string().into(), v);
+                            }
+                        }
+                    }
+                    out.add(NodeRef::object(map));
+                }
+            }
+            Ok(())
+        }
+        FuncId::Date => {
+            args.check_count_func(id, 1, 2)?;
+            let rows = args.resolve_rows_null(false, None, env)?;
+            for r in rows {
+                let date = parse_date(&r[0])?;
+                let s = match r.get(1) {
+                    Some(f) if !f.is_null() => format_date(&date, &f.as_string())?,
+                    _ => date.to_rfc3339(),
+                };
+                out.add(NodeRef::string(s));
+            }
+            Ok(())
+        }
+        FuncId::Now => {
+            // Nondeterministic: every call reads the system clock.
+            args.check_count_func(id, 0, 1)?;
+            let now = Utc::now();
+            if args.count() == 0 {
+                out.add(NodeRef::integer(now.timestamp()));
+            } else {
+                for f in args.resolve_column(false, 0, env)? {
+                    out.add(NodeRef::string(format_date(&now, &f.as_string())?));
+                }
+            }
+            Ok(())
+        }
+        FuncId::Uuid => {
+            args.check_count_func(id, 0, 1)?;
+            if args.count() == 0 {
+                out.add(NodeRef::string(uuid::Uuid::new_v4().to_string()));
+            } else {
+                for v in args.resolve_column(false, 0, env)? {
+                    let version = v.as_string();
+                    let u = match version.trim_start_matches('v') {
+                        "4" => uuid::Uuid::new_v4(),
+                        "nil" | "0" => uuid::Uuid::nil(),
+                        _ => return Err(FuncCallErrorDetail::UnsupportedUuidVersion { version }.into()),
+                    };
+                    out.add(NodeRef::string(u.to_string()));
+                }
+            }
+            Ok(())
+        }
+        FuncId::Hash => {
+            args.check_count_func(id, 2, 2)?;
+            let rows = args.resolve_rows_null(false, None, env)?;
+            for r in rows {
+                let name = r[1].as_string();
+                let digest = {
+                    let n = r[0].data();
+                    match n.as_binary() {
+                        Some(bytes) => hash_hex(&name, bytes),
+                        None => hash_hex(&name, n.as_string().as_bytes()),
+                    }
+                };
+                match digest {
+                    Some(d) => out.add(NodeRef::string(d)),
+                    None => return Err(FuncCallErrorDetail::UnknownHashAlgorithm { name }.into()),
+                }
+            }
+            Ok(())
+        }
+        FuncId::Merge => {
+            let res = NodeRef::object(Properties::new());
+            for set in args.resolve(false, env)? {
+                for n in set.into_vec() {
+                    let kind = n.data().kind();
+                    if kind != Kind::Object {
+                        return Err(basic_diag!(FuncCallErrorDetail::FuncCallInvalidArgType {
+                            id: id.clone(),
+                            kind,
+                        }));
+                    }
+                    res.merge(n.deep_copy(), MergeStrategy::Replace)
+                        .map_err(|err| FuncCallErrorDetail::custom_func(id, err))?;
+                }
+            }
+            out.add(res);
+            Ok(())
+        }
+        FuncId::CamelCase | FuncId::SnakeCase | FuncId::KebabCase => {
+            args.check_count_func(id, 1, 1)?;
+            let style = match *id {
+                FuncId::CamelCase => CaseStyle::Camel,
+                FuncId::SnakeCase => CaseStyle::Snake,
+                _ => CaseStyle::Kebab,
+            };
+            for n in args.resolve_flat(false, env)?.into_vec() {
+                out.add(NodeRef::string(style.convert(&n.as_string())));
+            }
+            Ok(())
+        }
+        // unlike `+`, always concatenates string representations, regardless of argument types
+        FuncId::Concat => {
+            let mut res = String::new();
+            for n in args.resolve_flat(false, env)?.into_iter() {
+                res.push_str(&n.as_string());
+            }
+            out.add(NodeRef::string(res));
+            Ok(())
+        }
+        FuncId::ZipObject => {
+            args.check_count_func(id, 2, 2)?;
+            let keys = args.resolve_column(false, 0, env)?.into_vec();
+            let values = args.resolve_column(true, 1, env)?.into_vec();
+            let mut props = Properties::with_capacity(keys.len().min(values.len()));
+            // extra keys or values are ignored, duplicate keys keep the last value
+            for (k, v) in keys.iter().zip(values.into_iter()) {
+                props.insert(k.as_string().into(), v);
+            }
+            out.add(NodeRef::object(props));
+            Ok(())
+        }
+        FuncId::ToFixed => {
+            args.check_count_func(id, 1, 2)?;
+            let rows = args.resolve_rows_null(false, None, env)?;
+            for r in rows {
+                let decimals = match r.get(1) {
+                    Some(d) => {
+                        let d = d.as_integer().unwrap_or(0);
+                        d.clamp(0, MAX_FIXED_DECIMALS as i64) as usize
+                    }
+                    None => 0,
+                };
+                out.add(NodeRef::string(format!("{:.*}", decimals, r[0].as_float())));
+            }
+            Ok(())
+        }
+        FuncId::Clamp => {
+            args.check_count_func(id, 3, 3)?;
+            let rows = args.resolve_rows_null(false, None, env)?;
+            for r in rows {
+                let (min, max) = (r[1].as_float(), r[2].as_float());
+                // negated to also reject NaN bounds, e.g. from non-numeric strings
+                #[allow(clippy::neg_cmp_op_on_partial_ord)]
+                let invalid = !(min <= max);
+                if invalid {
+                    return Err(basic_diag!(FuncCallErrorDetail::InvalidClampBounds { min, max }));
+                }
+                // integer type is preserved only if all arguments are integers
+                let n = if r.iter().all(|n| n.is_integer()) {
+                    let v = r[0].as_integer().unwrap();
+                    let (imin, imax) = (r[1].as_integer().unwrap(), r[2].as_integer().unwrap());
+                    // large integers may be equal when converted to floats
+                    if imin > imax {
+                        return Err(basic_diag!(FuncCallErrorDetail::InvalidClampBounds { min, max }));
+                    }
+                    NodeRef::integer(v.clamp(imin, imax))
+                } else {
+                    NodeRef::float(r[0].as_float().clamp(min, max))
+                };
+                out.add(n);
+            }
+            Ok(())
+        }
+        // like `start..stop` ranges, `stop` is inclusive
+        FuncId::Range => {
+            args.check_count_func(id, 2, 3)?;
+            let rows = args.resolve_rows_null(false, None, env)?;
+            for r in rows {
+                let (start, stop) = (&r[0], &r[1]);
+                let step = r.get(2);
+                let elems = if r.iter().all(|n| n.is_integer()) {
+                    let (start, stop) = (start.as_integer().unwrap(), stop.as_integer().unwrap());
+                    let step = match step {
+                        Some(s) => s.as_integer().unwrap(),
+                        None if start <= stop => 1,
+                        None => -1,
+                    };
+                    if step == 0 {
+                        return Err(basic_diag!(FuncCallErrorDetail::ZeroRangeStep));
+                    }
+                    // computed in i128, so that neither the length nor the elements can overflow
+                    let (start, stop, step) = (start as i128, stop as i128, step as i128);
+                    let len = if (step > 0 && start <= stop) || (step < 0 && start >= stop) {
+                        (stop - start) / step + 1
+                    } else {
+                        0
+                    };
+                    if len > MAX_GENERATED_LEN as i128 {
+                        return Err(basic_diag!(FuncCallErrorDetail::TooManyElements {
+                            id: id.clone(),
+                            max: MAX_GENERATED_LEN,
+                        }));
+                    }
+                    (0..len).map(|i| NodeRef::integer((start + i * step) as i64)).collect()
+                } else {
+                    let (start, stop) = (start.as_float(), stop.as_float());
+                    let step = match step {
+                        Some(s) => s.as_float(),
+                        None if start <= stop => 1.,
+                        None => -1.,
+                    };
+                    if !step.is_finite() {
+                        return Err(basic_diag!(FuncCallErrorDetail::NonFiniteRangeStep { step }));
+                    }
+                    if step == 0. {
+                        return Err(basic_diag!(FuncCallErrorDetail::ZeroRangeStep));
+                    }
+                    if !start.is_finite() || !stop.is_finite() {
+                        return Err(basic_diag!(FuncCallErrorDetail::NonFiniteRangeBounds { start, stop }));
+                    }
+                    // infinite if the difference overflows, negative if the step has the wrong sign
+                    let len = ((stop - start) / step).floor() + 1.;
+                    if len > MAX_GENERATED_LEN as f64 {
+                        return Err(basic_diag!(FuncCallErrorDetail::TooManyElements {
+                            id: id.clone(),
+                            max: MAX_GENERATED_LEN,
+                        }));
+                    }
+                    let mut elems = Vec::new();
+                    let mut i = 0;
+                    loop {
+                        // computed from the index to avoid accumulating rounding errors
+                        let v = start + i as f64 * step;
+                        if !((step > 0. && v <= stop) || (step < 0. && v >= stop)) {
+                            break;
+                        }
+                        elems.push(NodeRef::float(v));
+                        i += 1;
+                    }
+                    elems
+                };
+                out.add(NodeRef::array(elems));
+            }
+            Ok(())
+        }
+        FuncId::Repeat => {
+            args.check_count_func(id, 2, 2)?;
+            // consumable, so that tree nodes are copied before being wrapped in an array
+            let value = match args.resolve_column(true, 0, env)? {
+                NodeSet::Empty => NodeRef::null(),
+                NodeSet::One(n) => n,
+                NodeSet::Many(elems) => NodeRef::array(elems),
+            };
+            let count = match args.resolve_column(false, 1, env)?.into_one() {
+                Some(n) => n.as_integer().unwrap_or(0).max(0) as u64,
+                None => 0,
+            };
+            if count > MAX_GENERATED_LEN as u64 {
+                return Err(basic_diag!(FuncCallErrorDetail::TooManyElements {
+                    id: id.clone(),
+                    max: MAX_GENERATED_LEN,
+                }));
+            }
+            let elems = (0..count).map(|_| value.deep_copy()).collect();
+            out.add(NodeRef::array(elems));
+            Ok(())
+        }
+        // relative paths are resolved against current base path, see `kg_tree::set_base_path()`
+        FuncId::AbsPath => {
+            args.check_count_func(id, 1, 1)?;
+            let rows = args.resolve_rows_null(false, None, env)?;
+            for r in rows {
+                let path = normalize_path(&resolve_path_str(&r[0].as_string()));
+                out.add(NodeRef::string(path.display().to_string()));
+            }
+            Ok(())
+        }
+        // without `base` argument the path is made relative to current base path
+        FuncId::RelPath => {
+            args.check_count_func(id, 1, 2)?;
+            let rows = args.resolve_rows_null(false, None, env)?;
+            for r in rows {
+                let path = normalize_path(&resolve_path_str(&r[0].as_string()));
+                let rel = match r.get(1) {
+                    Some(base) => relative_path_to(&path, &normalize_path(&resolve_path_str(&base.as_string()))),
+                    None => crate::relative_path(&path).to_path_buf(),
+                };
+                out.add(NodeRef::string(rel.display().to_string()));
+            }
+            Ok(())
+        }
+        // same as `@file_name`, `@file_ext` and the directory of `@file_path`, but for arbitrary
+        // path strings; extension is returned without leading dot
+        FuncId::Basename | FuncId::Dirname | FuncId::Extname => {
+            args.check_count_func(id, 1, 1)?;
+            let rows = args.resolve_rows_null(false, None, env)?;
+            for r in rows {
+                let s = r[0].as_string();
+                let path = Path::new(&s);
+                let res = match *id {
+                    FuncId::Basename => path.file_name().map(|n| n.to_string_lossy().into_owned()),
+                    FuncId::Extname => path.extension().map(|e| e.to_string_lossy().into_owned()),
+                    FuncId::Dirname => match path.parent() {
+                        Some(p) if p.as_os_str().is_empty() => Some(".".to_string()),
+                        Some(p) => Some(p.display().to_string()),
+                        None => Some(s.clone()),
+                    },
+                    _ => unreachable!(),
+                };
+                out.add(NodeRef::string(res.unwrap_or_default()));
+            }
+            Ok(())
+        }
+        FuncId::Exists => {
+            args.check_count_func(id, 1, 1)?;
+            let res = args.resolve_column(false, 0, env)?;
+            out.add(NodeRef::boolean(!res.is_empty()));
+            Ok(())
+        }
+        // missing argument value matches none of the predicates
+        FuncId::IsString
+        | FuncId::IsNumber
+        | FuncId::IsArray
+        | FuncId::IsObject
+        | FuncId::IsBool
+        | FuncId::IsNull => {
+            args.check_count_func(id, 1, 1)?;
+            let check = |n: &NodeRef| match *id {
+                FuncId::IsString => n.is_string(),
+                FuncId::IsNumber => n.is_number(),
+                FuncId::IsArray => n.is_array(),
+                FuncId::IsObject => n.is_object(),
+                FuncId::IsBool => n.is_boolean(),
+                FuncId::IsNull => n.is_null(),
+                _ => unreachable!(),
+            };
+            match args.resolve_column(false, 0, env)? {
+                NodeSet::Empty => out.add(NodeRef::boolean(false)),
+                NodeSet::One(n) => out.add(NodeRef::boolean(check(&n))),
+                NodeSet::Many(elems) => {
+                    for e in elems.iter() {
+                        out.add(NodeRef::boolean(check(e)));
+                    }
+                }
+            }
+            Ok(())
+        }
+        // elements are compared with `NodeRef::is_equal()`, union and intersection keep only
+        // the first occurrence of equal elements, difference keeps duplicates from the first set
+        FuncId::Union | FuncId::Intersection | FuncId::Difference => {
+            args.check_count_func(id, 2, 2)?;
+            let a = args.resolve_column(true, 0, env)?.into_vec();
+            let b = args.resolve_column(true, 1, env)?.into_vec();
+            let contains = |elems: &[NodeRef], n: &NodeRef| elems.iter().any(|e| e.is_equal(n));
+            let mut res: Vec<NodeRef> = Vec::new();
+            match *id {
+                FuncId::Union => {
+                    for n in a.into_iter().chain(b.into_iter()) {
+                        if !contains(&res, &n) {
+                            res.push(n);
+                        }
+                    }
+                }
+                FuncId::Intersection => {
+                    for n in a.into_iter() {
+                        if contains(&b, &n) && !contains(&res, &n) {
+                            res.push(n);
+                        }
+                    }
+                }
+                FuncId::Difference => {
+                    res.extend(a.into_iter().filter(|n| !contains(&b, n)));
+                }
+                _ => unreachable!(),
+            }
+            out.add(NodeRef::array(res));
+            Ok(())
+        }
         FuncId::Custom(ref name) => {
             if let Some(e) = env.scope() {
                 if let Some(func) = e.get_func(name) {
@@ -900,6 +1567,10 @@ pub(super) fn apply_method_to(
         }
     }
 
+    if id.is_set_method() {
+        return apply_set_method_to(id, args, env, std::slice::from_ref(env.current()), out);
+    }
+
     let kind = env.current().data().kind();
 
     match *id {
@@ -1184,448 +1855,2848 @@ pub(super) fn apply_method_to(
                     kind,
                 }))
             }
-        } //_ => unimplemented!()
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    static TEST_JSON: &str = r#"
-        {
-            "one": 1,
-            "empty_object": {},
-            "empty_array": [],
-            "array": ["a","b"],
-            "null_value": null,
-            "nested": {
-                "two": 2,
-                "three_string": "3",
-                "four": 4
-            }
-        }
-    "#;
-
-    fn test_node<'a>() -> NodeRef {
-        NodeRef::from_json(TEST_JSON).unwrap()
-    }
-
-    mod args {
-        use super::*;
-
-        fn expr(e: &str) -> Expr {
-            Opath::parse(e).unwrap().into_expr()
         }
-
-        #[test]
-        fn resolve() {
-            let n = test_node();
-            let a = vec![expr("$.**"), expr("'str'"), expr("1..10")];
-            let args = Args::new(&a);
-
-            let values = args.resolve(false, Env::new(&n, &n, None)).unwrap();
-
-            assert_eq!(values.len(), 3);
-            assert_eq!(values[0].len(), 11);
-            assert_eq!(values[1].len(), 1);
-            assert_eq!(values[2].len(), 10);
-
-            let mut consumable = true;
-            for vals in values.iter() {
-                for v in vals.iter() {
-                    consumable = consumable && v.is_consumable();
+        // like DOM `Element.closest()`, the current node itself is tested before its ancestors
+        MethodId::Closest => {
+            args.check_count_method(id, kind, 1, 1)?;
+            let mut n = Some(env.current().clone());
+            while let Some(c) = n {
+                if args.resolve_column(false, 0, env.with_current(&c))?.is_truthy() {
+                    out.add(c);
+                    break;
                 }
+                n = c.data().parent();
             }
-            assert_eq!(consumable, false);
+            Ok(())
         }
-
-        #[test]
-        fn resolve_consumable() {
-            let n = test_node();
-            let a = vec![expr("$.**"), expr("'str'"), expr("1..10")];
-            let args = Args::new(&a);
-
-            let values = args.resolve(true, Env::new(&n, &n, None)).unwrap();
-
-            assert_eq!(values.len(), 3);
-            assert_eq!(values[0].len(), 11);
-            assert_eq!(values[1].len(), 1);
-            assert_eq!(values[2].len(), 10);
-            for vals in values.iter() {
-                for v in vals.iter() {
-                    assert!(v.is_consumable());
+        MethodId::Next | MethodId::Prev => {
+            args.check_count_method(id, kind, 0, 0)?;
+            let (parent, index) = {
+                let d = env.current().data();
+                (d.parent(), d.index())
+            };
+            if let Some(p) = parent {
+                let sibling = if *id == MethodId::Next {
+                    p.get_child_index(index + 1)
+                } else if index > 0 {
+                    p.get_child_index(index - 1)
+                } else {
+                    None
+                };
+                if let Some(n) = sibling {
+                    out.add(n);
                 }
             }
+            Ok(())
         }
-
-        #[test]
-        fn resolve_flat() {
-            let n = test_node();
-            let a = vec![expr("$.**"), expr("'str'"), expr("1..10")];
-            let args = Args::new(&a);
-
+        MethodId::ToJsonString => {
+            args.check_count_method(id, kind, 0, 1)?;
+            let pretty = if args.count() > 0 {
+                match args.resolve_column(false, 0, env)?.into_one() {
+                    Some(p) => p.as_boolean(),
+                    None => false,
+                }
+            } else {
+                false
+            };
+            let s = if pretty {
+                env.current().to_json_pretty()
+            } else {
+                env.current().to_json()
+            };
+            out.add(NodeRef::string(s));
+            Ok(())
+        }
+        MethodId::ParseJson | MethodId::ParseYaml | MethodId::ParseToml => {
+            if kind == Kind::String {
+                args.check_count_method(id, kind, 0, 0)?;
+                let s = env.current().as_string();
+                let n = match *id {
+                    MethodId::ParseJson => NodeRef::from_json(&s),
+                    MethodId::ParseYaml => NodeRef::from_yaml(&s),
+                    _ => NodeRef::from_toml(&s),
+                }
+                .map_err_as_cause(|| FuncCallErrorDetail::NodeParse)?;
+                out.add(n);
+                Ok(())
+            } else {
+                Err(basic_diag!(FuncCallErrorDetail::UnknownMethod {
+                    name: id.name().to_string(),
+                    kind,
+                }))
+            }
+        }
+        MethodId::KeysMatching => {
+            use regex::Regex;
+
+            if kind == Kind::Object {
+                args.check_count_method(id, kind, 1, 1)?;
+                let re = args.resolve_column(true, 0, env)?.into_one().ok_or_else(|| {
+                    basic_diag!(FuncCallErrorDetail::MethodCallSingleArgRequired {
+                        id: id.clone(),
+                        kind,
+                    })
+                })?;
+                let regex =
+                    Regex::new(&re.data().as_string()).map_err(|err| RegexParse { err })?;
+
+                if let Value::Object(ref props) = *env.current().data().value() {
+                    for (k, v) in props.iter() {
+                        if regex.is_match(k.as_ref()) {
+                            out.add(v.clone());
+                        }
+                    }
+                }
+                Ok(())
+            } else {
+                Err(basic_diag!(FuncCallErrorDetail::UnknownMethod {
+                    name: id.name().to_string(),
+                    kind,
+                }))
+            }
+        }
+        MethodId::Pick | MethodId::Omit => {
+            if kind == Kind::Object {
+                args.check_count_method(id, kind, 1, 0)?;
+                let mut keys = Vec::new();
+                for k in args.resolve_flat(false, env)?.into_vec() {
+                    match *k.data().value() {
+                        Value::Array(ref elems) => keys.extend(elems.iter().map(|e| e.as_string())),
+                        _ => keys.push(k.as_string()),
+                    }
+                }
+
+                let pick = *id == MethodId::Pick;
+                let mut res = Properties::new();
+                if let Value::Object(ref props) = *env.current().data().value() {
+                    for (k, v) in props.iter() {
+                        if keys.iter().any(|key| key == k.as_ref()) == pick {
+                            res.insert(k.clone(), v.deep_copy());
+                        }
+                    }
+                }
+                out.add(NodeRef::object(res));
+                Ok(())
+            } else {
+                Err(basic_diag!(FuncCallErrorDetail::UnknownMethod {
+                    name: id.name().to_string(),
+                    kind,
+                }))
+            }
+        }
+        MethodId::Rekey => {
+            args.check_count_method(id, kind, 1, 1)?;
+            let style = args.resolve_column(false, 0, env)?.into_one().map(|s| s.as_string());
+            let style = match style.as_ref().map(|s| s.as_str()) {
+                Some("camel") => CaseStyle::Camel,
+                Some("snake") => CaseStyle::Snake,
+                Some("kebab") => CaseStyle::Kebab,
+                _ => {
+                    return Err(basic_diag!(FuncCallErrorDetail::UnknownCaseStyle {
+                        style: style.unwrap_or_default(),
+                    }))
+                }
+            };
+            let n = env.current().deep_copy();
+            n.rename_keys(|k| style.convert(k));
+            out.add(n);
+            Ok(())
+        }
+        MethodId::KeysWhere => {
+            if kind == Kind::Object {
+                args.check_count_method(id, kind, 1, 1)?;
+                let entries: Vec<(String, NodeRef)> = match *env.current().data().value() {
+                    Value::Object(ref props) => props
+                        .iter()
+                        .map(|(k, v)| (k.as_ref().to_string(), v.clone()))
+                        .collect(),
+                    _ => unreachable!(),
+                };
+                for (k, v) in entries {
+                    if args.resolve_column(false, 0, env.with_current(&v))?.is_truthy() {
+                        out.add(NodeRef::string(k));
+                    }
+                }
+                Ok(())
+            } else {
+                Err(basic_diag!(FuncCallErrorDetail::UnknownMethod {
+                    name: id.name().to_string(),
+                    kind,
+                }))
+            }
+        }
+        MethodId::Slugify => {
+            if kind == Kind::String {
+                args.check_count_method(id, kind, 0, 0)?;
+                out.add(NodeRef::string(slugify(&env.current().as_string())));
+                Ok(())
+            } else {
+                Err(basic_diag!(FuncCallErrorDetail::UnknownMethod {
+                    name: id.name().to_string(),
+                    kind,
+                }))
+            }
+        }
+        // `lines()` accepts both `\n` and `\r\n` line endings, a trailing newline does not
+        // produce an empty line
+        MethodId::Lines | MethodId::Words => {
+            if kind == Kind::String {
+                args.check_count_method(id, kind, 0, 0)?;
+                let s = env.current().as_string();
+                let elems = if *id == MethodId::Lines {
+                    s.lines().map(NodeRef::string).collect()
+                } else {
+                    s.split_whitespace().map(NodeRef::string).collect()
+                };
+                out.add(NodeRef::array(elems));
+                Ok(())
+            } else {
+                Err(basic_diag!(FuncCallErrorDetail::UnknownMethod {
+                    name: id.name().to_string(),
+                    kind,
+                }))
+            }
+        }
+        // same as `@level`, counts parent hops up to the node with no parent
+        MethodId::DepthFromRoot => {
+            args.check_count_method(id, kind, 0, 0)?;
+            out.add(NodeRef::integer(env.current().data().level() as i64));
+            Ok(())
+        }
+        // merges deep copy of current node, the source tree is left intact
+        MethodId::MergeDeep => {
+            args.check_count_method(id, kind, 2, 2)?;
+            let others = args.resolve_column(false, 0, env)?.into_vec();
+            let key_field = match args.resolve_column(false, 1, env)?.into_one() {
+                Some(k) => k.as_string(),
+                None => String::new(),
+            };
+            let n = env.current().deep_copy();
+            for o in others {
+                n.merge_by_key(o.deep_copy(), &key_field)
+                    .map_err(|err| FuncCallErrorDetail::custom_method(id, kind, err))?;
+            }
+            out.add(n);
+            Ok(())
+        }
+        MethodId::First
+        | MethodId::Last
+        | MethodId::Nth
+        | MethodId::Count
+        | MethodId::Any
+        | MethodId::All
+        | MethodId::Map
+        | MethodId::Filter
+        | MethodId::Reduce
+        | MethodId::Reject
+        | MethodId::FlatMap
+        | MethodId::Partition => unreachable!(),
+    }
+}
+
+/// Lexically normalizes absolute `path`, removing `.` components and resolving `..` components.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut res = PathBuf::new();
+    for c in path.components() {
+        match c {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                res.pop();
+            }
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+/// Returns `path` relative to `base`, both paths are expected to be absolute and normalized.
+fn relative_path_to(path: &Path, base: &Path) -> PathBuf {
+    let path: Vec<Component> = path.components().collect();
+    let base: Vec<Component> = base.components().collect();
+    let common = path.iter().zip(base.iter()).take_while(|(a, b)| a == b).count();
+    let mut res = PathBuf::new();
+    for _ in common..base.len() {
+        res.push(Component::ParentDir);
+    }
+    for c in path[common..].iter() {
+        res.push(c);
+    }
+    if res.as_os_str().is_empty() {
+        res.push(Component::CurDir);
+    }
+    res
+}
+
+/// Lowercases `s`, replaces runs of non-alphanumeric characters with a single `-` and trims
+/// dashes from both ends. Non-ASCII letters and digits are kept, no transliteration is done.
+fn slugify(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    let mut sep = false;
+    for c in s.chars() {
+        if c.is_alphanumeric() {
+            if sep && !res.is_empty() {
+                res.push('-');
+            }
+            sep = false;
+            res.extend(c.to_lowercase());
+        } else {
+            sep = true;
+        }
+    }
+    res
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CaseStyle {
+    Camel,
+    Snake,
+    Kebab,
+}
+
+impl CaseStyle {
+    /// Splits identifier into lowercase words on separators (`_`, `-`, whitespace, `.`)
+    /// and case changes, keeping acronyms together (`HTTPServer` -> `http`, `server`).
+    fn words(s: &str) -> Vec<String> {
+        let chars: Vec<char> = s.chars().collect();
+        let mut words = Vec::new();
+        let mut word = String::new();
+        for (i, &c) in chars.iter().enumerate() {
+            if c == '_' || c == '-' || c == '.' || c.is_whitespace() {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+                continue;
+            }
+            if c.is_uppercase() && !word.is_empty() {
+                let prev = chars[i - 1];
+                let next_lower = chars.get(i + 1).map_or(false, |n| n.is_lowercase());
+                if !prev.is_uppercase() || next_lower {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            word.extend(c.to_lowercase());
+        }
+        if !word.is_empty() {
+            words.push(word);
+        }
+        words
+    }
+
+    fn convert(self, s: &str) -> String {
+        let words = CaseStyle::words(s);
+        match self {
+            CaseStyle::Camel => {
+                let mut res = String::with_capacity(s.len());
+                for (i, w) in words.iter().enumerate() {
+                    let mut chars = w.chars();
+                    match chars.next() {
+                        Some(c) if i > 0 => {
+                            res.extend(c.to_uppercase());
+                            res.push_str(chars.as_str());
+                        }
+                        _ => res.push_str(w),
+                    }
+                }
+                res
+            }
+            CaseStyle::Snake => words.join("_"),
+            CaseStyle::Kebab => words.join("-"),
+        }
+    }
+}
+
+/// Parses a date from a unix timestamp (in seconds) or an ISO 8601 string.
+fn parse_date(n: &NodeRef) -> Result<DateTime<Utc>, FuncCallError> {
+    let date = match *n.data().value() {
+        Value::Integer(secs) => Utc.timestamp_opt(secs, 0).single(),
+        Value::Float(secs) => Utc.timestamp_opt(secs.floor() as i64, 0).single(),
+        Value::String(ref s) => {
+            let s = s.trim();
+            DateTime::parse_from_rfc3339(s)
+                .map(|d| d.with_timezone(&Utc))
+                .ok()
+                .or_else(|| {
+                    NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S")
+                        .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S"))
+                        .ok()
+                        .or_else(|| {
+                            NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                                .ok()
+                                .and_then(|d| d.and_hms_opt(0, 0, 0))
+                        })
+                        .map(|d| Utc.from_utc_datetime(&d))
+                })
+        }
+        _ => None,
+    };
+    date.ok_or_else(|| {
+        FuncCallErrorDetail::InvalidDate {
+            input: n.as_string(),
+        }
+        .into()
+    })
+}
+
+/// Returns lowercase hex digest of `bytes`, or `None` if the algorithm is not supported.
+fn hash_hex(algorithm: &str, bytes: &[u8]) -> Option<String> {
+    use sha2::Digest;
+
+    match algorithm.to_ascii_lowercase().as_str() {
+        "md5" => Some(format!("{:x}", md5::compute(bytes))),
+        "sha1" => Some(format!("{:x}", sha1::Sha1::digest(bytes))),
+        "sha256" => Some(format!("{:x}", sha2::Sha256::digest(bytes))),
+        _ => None,
+    }
+}
+
+fn format_date(date: &DateTime<Utc>, format: &str) -> Result<String, FuncCallError> {
+    let items: Vec<Item> = StrftimeItems::new(format).collect();
+    if items.iter().any(|i| *i == Item::Error) {
+        return Err(FuncCallErrorDetail::InvalidDateFormat {
+            format: format.to_string(),
+        }
+        .into());
+    }
+    Ok(date.format_with_items(items.into_iter()).to_string())
+}
+
+pub(super) fn apply_set_method_to(
+    id: &MethodId,
+    args: Args,
+    env: Env,
+    nodes: &[NodeRef],
+    out: &mut NodeBuf,
+) -> FuncCallResult {
+    let kind = env.current().data().kind();
+
+    match *id {
+        MethodId::First => {
+            args.check_count_method(id, kind, 0, 0)?;
+            if let Some(n) = nodes.first() {
+                out.add(n.clone());
+            }
+            Ok(())
+        }
+        MethodId::Last => {
+            args.check_count_method(id, kind, 0, 0)?;
+            if let Some(n) = nodes.last() {
+                out.add(n.clone());
+            }
+            Ok(())
+        }
+        MethodId::Nth => {
+            args.check_count_method(id, kind, 1, 1)?;
+            let index = args.resolve_column(false, 0, env)?;
+            if let Some(index) = index.into_one().and_then(|i| i.as_integer()) {
+                if let Some(n) = nodes.get(to_abs_index(index, nodes.len())) {
+                    out.add(n.clone());
+                }
+            }
+            Ok(())
+        }
+        MethodId::Count => {
+            args.check_count_method(id, kind, 0, 0)?;
+            out.add(NodeRef::integer(nodes.len() as i64));
+            Ok(())
+        }
+        // `any` over an empty set is false, `all` over an empty set is true
+        MethodId::Any => {
+            args.check_count_method(id, kind, 1, 1)?;
+            let mut res = false;
+            for n in nodes {
+                if args.resolve_column(false, 0, env.with_current(n))?.is_truthy() {
+                    res = true;
+                    break;
+                }
+            }
+            out.add(NodeRef::boolean(res));
+            Ok(())
+        }
+        MethodId::All => {
+            args.check_count_method(id, kind, 1, 1)?;
+            let mut res = true;
+            for n in nodes {
+                if !args.resolve_column(false, 0, env.with_current(n))?.is_truthy() {
+                    res = false;
+                    break;
+                }
+            }
+            out.add(NodeRef::boolean(res));
+            Ok(())
+        }
+        // every node maps to exactly one array element: multi-node results are nested
+        // as arrays and empty results become null, so elements stay aligned with the input
+        MethodId::Map => {
+            args.check_count_method(id, kind, 1, 1)?;
+            let mut elems = Vec::with_capacity(nodes.len());
+            for n in nodes {
+                let e = match args.resolve_column(true, 0, env.with_current(n))? {
+                    NodeSet::Empty => NodeRef::null(),
+                    NodeSet::One(e) => e,
+                    NodeSet::Many(es) => NodeRef::array(es),
+                };
+                elems.push(e);
+            }
+            out.add(NodeRef::array(elems));
+            Ok(())
+        }
+        // `reject()` keeps nodes for which the predicate is falsy or empty
+        MethodId::Filter | MethodId::Reject => {
+            args.check_count_method(id, kind, 1, 1)?;
+            let keep = *id == MethodId::Filter;
+            let mut kept = Vec::with_capacity(nodes.len());
+            for n in nodes {
+                if args.resolve_column(false, 0, env.with_current(n))?.is_truthy() == keep {
+                    kept.push(n.clone());
+                }
+            }
+            out.append(kept.iter());
+            Ok(())
+        }
+        // the accumulator is exposed to the fold expression as `$acc`
+        MethodId::Reduce => {
+            args.check_count_method(id, kind, 2, 2)?;
+            let mut acc = args.resolve_column(true, 0, env)?;
+            let scope = match env.scope() {
+                Some(s) => s.new_child(),
+                None => ScopeMut::new(),
+            };
+            for n in nodes {
+                scope.set_var("acc".into(), acc);
+                let e = env.with_current(n);
+                acc = args.resolve_column(true, 1, e.with_scope(Some(scope.as_ref())))?;
+            }
+            out.add_all(&acc);
+            Ok(())
+        }
+        // unlike `map()`, results of all nodes are concatenated into a single flat array
+        MethodId::FlatMap => {
+            args.check_count_method(id, kind, 1, 1)?;
+            let mut elems = Vec::new();
+            for n in nodes {
+                elems.extend(args.resolve_column(true, 0, env.with_current(n))?.into_vec());
+            }
+            out.add(NodeRef::array(elems));
+            Ok(())
+        }
+        // nodes are copied into the result arrays, so the source tree is left intact
+        MethodId::Partition => {
+            args.check_count_method(id, kind, 1, 1)?;
+            let mut matching = Vec::new();
+            let mut non_matching = Vec::new();
+            for n in nodes {
+                let e = if n.is_consumable() { n.clone() } else { n.deep_copy() };
+                if args.resolve_column(false, 0, env.with_current(n))?.is_truthy() {
+                    matching.push(e);
+                } else {
+                    non_matching.push(e);
+                }
+            }
+            out.add(NodeRef::array(vec![NodeRef::array(matching), NodeRef::array(non_matching)]));
+            Ok(())
+        }
+        _ => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static TEST_JSON: &str = r#"
+        {
+            "one": 1,
+            "empty_object": {},
+            "empty_array": [],
+            "array": ["a","b"],
+            "null_value": null,
+            "nested": {
+                "two": 2,
+                "three_string": "3",
+                "four": 4
+            }
+        }
+    "#;
+
+    fn test_node<'a>() -> NodeRef {
+        NodeRef::from_json(TEST_JSON).unwrap()
+    }
+
+    mod args {
+        use super::*;
+
+        fn expr(e: &str) -> Expr {
+            Opath::parse(e).unwrap().into_expr()
+        }
+
+        #[test]
+        fn resolve() {
+            let n = test_node();
+            let a = vec![expr("$.**"), expr("'str'"), expr("1..10")];
+            let args = Args::new(&a);
+
+            let values = args.resolve(false, Env::new(&n, &n, None)).unwrap();
+
+            assert_eq!(values.len(), 3);
+            assert_eq!(values[0].len(), 11);
+            assert_eq!(values[1].len(), 1);
+            assert_eq!(values[2].len(), 10);
+
+            let mut consumable = true;
+            for vals in values.iter() {
+                for v in vals.iter() {
+                    consumable = consumable && v.is_consumable();
+                }
+            }
+            assert_eq!(consumable, false);
+        }
+
+        #[test]
+        fn resolve_consumable() {
+            let n = test_node();
+            let a = vec![expr("$.**"), expr("'str'"), expr("1..10")];
+            let args = Args::new(&a);
+
+            let values = args.resolve(true, Env::new(&n, &n, None)).unwrap();
+
+            assert_eq!(values.len(), 3);
+            assert_eq!(values[0].len(), 11);
+            assert_eq!(values[1].len(), 1);
+            assert_eq!(values[2].len(), 10);
+            for vals in values.iter() {
+                for v in vals.iter() {
+                    assert!(v.is_consumable());
+                }
+            }
+        }
+
+        #[test]
+        fn resolve_flat() {
+            let n = test_node();
+            let a = vec![expr("$.**"), expr("'str'"), expr("1..10")];
+            let args = Args::new(&a);
+
             let values = args.resolve_flat(false, Env::new(&n, &n, None)).unwrap();
 
-            assert_eq!(values.len(), 11 + 1 + 10);
-            let mut consumable = true;
-            for v in values.iter() {
-                consumable = consumable && v.is_consumable();
+            assert_eq!(values.len(), 11 + 1 + 10);
+            let mut consumable = true;
+            for v in values.iter() {
+                consumable = consumable && v.is_consumable();
+            }
+            assert_eq!(consumable, false);
+        }
+
+        #[test]
+        fn resolve_flat_consumable() {
+            let n = test_node();
+            let a = vec![expr("$.**"), expr("'str'"), expr("1..10")];
+            let args = Args::new(&a);
+
+            let values = args.resolve_flat(true, Env::new(&n, &n, None)).unwrap();
+
+            assert_eq!(values.len(), 11 + 1 + 10);
+            for v in values.iter() {
+                assert!(v.is_consumable());
+            }
+        }
+
+        #[test]
+        fn resolve_rows() {
+            let n = test_node();
+            let a = vec![expr("$.**"), expr("'str'"), expr("1..10")];
+            let args = Args::new(&a);
+
+            let values = args
+                .resolve_rows_null(false, None, Env::new(&n, &n, None))
+                .unwrap();
+            assert_eq!(values.len(), 10);
+            assert_eq!(values[0].len(), 3);
+            assert_eq!(values[1].len(), 3);
+            assert_eq!(values[2].len(), 3);
+            assert_eq!(values[3].len(), 3);
+            assert_eq!(values[4].len(), 3);
+            assert_eq!(values[5].len(), 3);
+            assert_eq!(values[6].len(), 3);
+            assert_eq!(values[7].len(), 3);
+            assert_eq!(values[8].len(), 3);
+            assert_eq!(values[9].len(), 3);
+
+            let mut consumable = true;
+            for vals in values.iter() {
+                for v in vals.iter() {
+                    consumable = consumable && v.is_consumable();
+                }
+            }
+            assert_eq!(consumable, false);
+        }
+
+        #[test]
+        fn resolve_rows_consumable() {
+            let n = test_node();
+            let a = vec![expr("$.**"), expr("'str'"), expr("1..10")];
+            let args = Args::new(&a);
+
+            let values = args
+                .resolve_rows_null(true, None, Env::new(&n, &n, None))
+                .unwrap();
+
+            assert_eq!(values.len(), 10);
+            assert_eq!(values[0].len(), 3);
+            assert_eq!(values[1].len(), 3);
+            assert_eq!(values[2].len(), 3);
+            assert_eq!(values[3].len(), 3);
+            assert_eq!(values[4].len(), 3);
+            assert_eq!(values[5].len(), 3);
+            assert_eq!(values[6].len(), 3);
+            assert_eq!(values[7].len(), 3);
+            assert_eq!(values[8].len(), 3);
+            assert_eq!(values[9].len(), 3);
+
+            for vals in values.iter() {
+                for v in vals.iter() {
+                    assert!(v.is_consumable());
+                }
+            }
+        }
+    }
+
+    mod func {
+        use super::*;
+
+        #[test]
+        fn array() {
+            let n = test_node();
+
+            let expr = Opath::parse("array(@.*.@key)").unwrap();
+
+            let res = expr.apply(&n, &n).unwrap();
+
+            assert_eq!(res.len(), 1);
+
+            let ref a = res.into_vec()[0];
+            assert!(a.is_array());
+            assert_eq!(a.data().children_count(), Some(6));
+        }
+
+        #[test]
+        fn map() {
+            let n = test_node();
+
+            let expr = Opath::parse("map(@.*.@key, @.*.@index)").unwrap();
+
+            let res = expr.apply(&n, &n).unwrap();
+
+            assert_eq!(res.len(), 1);
+
+            let ref o = res.into_vec()[0];
+            assert!(o.is_object());
+            assert_eq!(o.data().children_count(), Some(6));
+        }
+
+        mod zip {
+            use super::*;
+
+            fn zip(expr: &str) -> Vec<Vec<NodeRef>> {
+                let n = NodeRef::from_json(r#"{
+                    "keys": ["a", "b", "c"],
+                    "values": [1, 2, 3],
+                    "short": [true]
+                }"#)
+                .unwrap();
+                let res = Opath::parse(expr).unwrap().apply(&n, &n).unwrap().into_vec();
+                assert_eq!(res.len(), 1);
+                let rows = match *res[0].data().value() {
+                    Value::Array(ref elems) => elems
+                        .iter()
+                        .map(|r| match *r.data().value() {
+                            Value::Array(ref row) => row.clone(),
+                            _ => panic!("array expected"),
+                        })
+                        .collect(),
+                    _ => panic!("array expected"),
+                };
+                rows
+            }
+
+            #[test]
+            fn equal_length() {
+                let rows = zip("zip(@.keys.*, @.values.*)");
+                assert_eq!(rows.len(), 3);
+                assert_eq!(rows[0][0].as_string(), "a");
+                assert_eq!(rows[0][1].as_integer(), Some(1));
+                assert_eq!(rows[2][0].as_string(), "c");
+                assert_eq!(rows[2][1].as_integer(), Some(3));
+            }
+
+            #[test]
+            fn unequal_length() {
+                let rows = zip("zip(@.keys.*, @.short.*)");
+                assert_eq!(rows.len(), 1);
+                assert_eq!(rows[0].len(), 2);
+                assert_eq!(rows[0][0].as_string(), "a");
+                assert_eq!(rows[0][1].as_boolean(), true);
+            }
+
+            #[test]
+            fn three_way() {
+                let rows = zip("zip(@.keys.*, @.values.*, @.keys.*.@index)");
+                assert_eq!(rows.len(), 3);
+                for (i, row) in rows.iter().enumerate() {
+                    assert_eq!(row.len(), 3);
+                    assert_eq!(row[1].as_integer(), Some(i as i64 + 1));
+                    assert_eq!(row[2].as_integer(), Some(i as i64));
+                }
+            }
+        }
+
+        mod entries_sorted {
+            use super::*;
+
+            #[test]
+            fn sorted_by_key() {
+                let n = NodeRef::from_json(r#"{"map": {"b": 2, "c": [3], "a": {"x": 1}, "B": 0}}"#).unwrap();
+
+                let entries = Opath::parse("entries(@.map)").unwrap().apply_one(&n, &n).unwrap();
+                assert_eq!(entries.to_json(), r#"[["b",2],["c",[3]],["a",{"x":1}],["B",0]]"#);
+
+                let sorted = Opath::parse("entries_sorted(@.map)").unwrap().apply_one(&n, &n).unwrap();
+                assert_eq!(sorted.to_json(), r#"[["B",0],["a",{"x":1}],["b",2],["c",[3]]]"#);
+            }
+
+            #[test]
+            fn round_trip() {
+                let n = test_node();
+                let expr = Opath::parse("object_from_entries(entries_sorted(@.nested))").unwrap();
+                let res = expr.apply_one(&n, &n).unwrap();
+
+                let nested = Opath::parse("@.nested").unwrap().apply_one(&n, &n).unwrap();
+                assert!(res.is_equal_deep(&nested));
+                assert_eq!(res.to_json(), r#"{"four":4,"three_string":"3","two":2}"#);
+            }
+        }
+
+        mod object_from_entries {
+            use super::*;
+
+            #[test]
+            fn camel_case_alias() {
+                let n = NodeRef::from_json(r#"[["a", 1]]"#).unwrap();
+                let expr = Opath::parse("objectFromEntries(@)").unwrap();
+                assert_eq!(expr.to_string(), "object_from_entries(@)");
+                assert_eq!(expr.apply_one(&n, &n).unwrap().to_json(), r#"{"a":1}"#);
+            }
+
+            #[test]
+            fn round_trip() {
+                let n = test_node();
+                let expr = Opath::parse("object_from_entries(entries(@.nested))").unwrap();
+                let res = expr.apply(&n, &n).unwrap().into_vec();
+
+                assert_eq!(res.len(), 1);
+                let nested = Opath::parse("@.nested").unwrap().apply_one(&n, &n).unwrap();
+                assert!(res[0].is_identical_deep(&nested));
+            }
+
+            #[test]
+            fn duplicate_keys() {
+                let n = NodeRef::from_json(r#"[["a", 1], ["b", 2], ["a", 3]]"#).unwrap();
+                let expr = Opath::parse("object_from_entries(@)").unwrap();
+                let res = expr.apply(&n, &n).unwrap().into_vec();
+
+                assert_eq!(res.len(), 1);
+                let expected = NodeRef::from_json(r#"{"a": 3, "b": 2}"#).unwrap();
+                assert!(res[0].is_identical_deep(&expected));
+            }
+
+            #[test]
+            fn malformed_entries() {
+                let n = NodeRef::from_json(r#"[["a"], [], "x", ["b", 2]]"#).unwrap();
+                let expr = Opath::parse("object_from_entries(@)").unwrap();
+                let res = expr.apply(&n, &n).unwrap().into_vec();
+
+                assert_eq!(res.len(), 1);
+                let expected = NodeRef::from_json(r#"{"a": null, "b": 2}"#).unwrap();
+                assert!(res[0].is_identical_deep(&expected));
+            }
+        }
+
+        mod find_new {
+            use super::*;
+
+            #[test]
+            fn should_return_error_without_diff_env() {
+                let expr = Opath::parse("findNew($.some)").unwrap();
+                let root = NodeRef::object(Properties::new());
+                let err = expr.apply(&root, &root).unwrap_err();
+                let detail: &FuncCallErrorDetail = err.detail().downcast_ref().unwrap();
+                assert_eq!(detail, &FuncCallErrorDetail::UnknownFunc { name: "findNew".into() });
+            }
+
+            //FIXME (jc) add further tests
+        }
+
+        mod find_old {
+            use super::*;
+
+            #[test]
+            fn should_return_error_without_diff_env() {
+                let expr = Opath::parse("findOld($.some)").unwrap();
+                let root = NodeRef::object(Properties::new());
+                let err = expr.apply(&root, &root).unwrap_err();
+                let detail: &FuncCallErrorDetail = err.detail().downcast_ref().unwrap();
+                assert_eq!(detail, &FuncCallErrorDetail::UnknownFunc { name: "findOld".into() });
+            }
+
+            //FIXME (jc) add further tests
+        }
+
+        mod parse_int {
+            use super::*;
+
+            #[test]
+            fn multiple_args() {
+                let n = NodeRef::from_json(r#"{
+                                                        "nums":["10", "-12aaa", "FF", "fE", "101", "1A"],
+                                                        "basis":[10, 10, 16, 16, 2, "aaa"]
+                                                    }"#)
+                    .unwrap();
+
+                let expr = Opath::parse("parseInt(@.nums.*, @.basis.*)").unwrap();
+                let mut res = expr.apply(&n, &n).unwrap().into_iter();
+
+                assert_eq!(res.len(), 6);
+                assert_eq!(res.next().unwrap().as_integer().unwrap(), 10);
+                assert_eq!(res.next().unwrap().as_integer().unwrap(), -12);
+                assert_eq!(res.next().unwrap().as_integer().unwrap(), 255);
+                assert_eq!(res.next().unwrap().as_integer().unwrap(), 254);
+                assert_eq!(res.next().unwrap().as_integer().unwrap(), 5);
+                assert_eq!(res.next().unwrap().as_integer().unwrap(), 1);
+            }
+
+            #[test]
+            fn integer_str() {
+                let n = NodeRef::null();
+                let expr = Opath::parse("parseInt('10')").unwrap();
+                let res = expr.apply(&n, &n).unwrap().into_vec();
+
+                assert_eq!(res.len(), 1);
+
+                let res = res.get(0).unwrap();
+
+                assert!(res.is_integer());
+                assert_eq!(res.as_integer().unwrap(), 10)
+            }
+
+            #[test]
+            fn radix() {
+                let n = NodeRef::from_json(r#"{}"#).unwrap();
+                let expr = Opath::parse("parseInt('10', 2)").unwrap();
+                let res = expr.apply(&n, &n).unwrap().into_vec();
+
+                assert_eq!(res.len(), 1);
+
+                let res = res.get(0).unwrap();
+
+                assert!(res.is_integer());
+                assert_eq!(res.as_integer().unwrap(), 2)
+            }
+
+            #[test]
+            fn radix_str() {
+                let n = NodeRef::null();
+                let expr = Opath::parse("parseInt('10', '2')").unwrap();
+                let res = expr.apply(&n, &n).unwrap().into_vec();
+
+                assert_eq!(res.len(), 1);
+
+                let res = res.get(0).unwrap();
+
+                assert!(res.is_integer());
+                assert_eq!(res.as_integer().unwrap(), 2)
+            }
+
+            #[test]
+            fn float() {
+                let n = NodeRef::from_json(r#"{}"#).unwrap();
+                let expr = Opath::parse("parseInt('10.33')").unwrap();
+                let res = expr.apply(&n, &n).unwrap().into_vec();
+
+                assert_eq!(res.len(), 1);
+
+                let res = res.get(0).unwrap();
+
+                assert!(res.is_integer());
+                assert_eq!(res.as_integer().unwrap(), 10)
+            }
+
+            #[test]
+            fn nan() {
+                let n = NodeRef::from_json(r#"{}"#).unwrap();
+                let expr = Opath::parse("parseInt('blaa')").unwrap();
+                let res = expr.apply(&n, &n).unwrap().into_vec();
+
+                assert_eq!(res.len(), 1);
+
+                let res = res.get(0).unwrap();
+
+                assert!(res.is_float());
+                assert!(res.as_float().is_nan());
+            }
+
+            #[test]
+            fn neg() {
+                let n = NodeRef::from_json(r#"{}"#).unwrap();
+                let expr = Opath::parse("parseInt('-10')").unwrap();
+                let res = expr.apply(&n, &n).unwrap().into_vec();
+
+                assert_eq!(res.len(), 1);
+
+                let res = res.get(0).unwrap();
+
+                assert!(res.is_integer());
+                assert_eq!(res.as_integer().unwrap(), -10);
+            }
+
+            #[test]
+            fn num_first() {
+                let n = NodeRef::from_json(r#"{}"#).unwrap();
+                let expr = Opath::parse("parseInt('10ab')").unwrap();
+                let res = expr.apply(&n, &n).unwrap().into_vec();
+
+                assert_eq!(res.len(), 1);
+
+                let res = res.get(0).unwrap();
+
+                assert!(res.is_integer());
+                assert_eq!(res.as_integer().unwrap(), 10);
+            }
+
+            #[test]
+            fn mul_tokens() {
+                let n = NodeRef::from_json(r#"{}"#).unwrap();
+                let expr = Opath::parse("parseInt('10 aaa')").unwrap();
+                let res = expr.apply(&n, &n).unwrap().into_vec();
+
+                assert_eq!(res.len(), 1);
+
+                let res = res.get(0).unwrap();
+
+                assert!(res.is_integer());
+                assert_eq!(res.as_integer().unwrap(), 10)
+            }
+
+            #[test]
+            fn whitespaces() {
+                let n = NodeRef::from_json(r#"{}"#).unwrap();
+                let expr = Opath::parse("parseInt('  10  ')").unwrap();
+                let res = expr.apply(&n, &n).unwrap().into_vec();
+
+                assert_eq!(res.len(), 1);
+
+                let res = res.get(0).unwrap();
+
+                assert!(res.is_integer());
+                assert_eq!(res.as_integer().unwrap(), 10)
+            }
+        }
+
+        mod date {
+            use super::*;
+
+            fn date(expr: &str) -> Result<NodeRef, FuncCallError> {
+                let n = NodeRef::from_json(r#"{"ts": 1600000000, "iso": "2020-09-13T12:26:40Z"}"#)
+                    .unwrap();
+                Opath::parse(expr).unwrap().apply(&n, &n).map(|res| {
+                    let mut res = res.into_vec();
+                    assert_eq!(res.len(), 1);
+                    res.remove(0)
+                })
+            }
+
+            #[test]
+            fn format_timestamp() {
+                let res = date("date(@.ts, '%Y-%m-%d %H:%M')").unwrap();
+                assert_eq!(res.as_string(), "2020-09-13 12:26");
+            }
+
+            #[test]
+            fn reformat_iso_string() {
+                let res = date("date(@.iso, '%d/%m/%Y')").unwrap();
+                assert_eq!(res.as_string(), "13/09/2020");
+
+                let res = date("date('2020-09-13', '%Y%m%d')").unwrap();
+                assert_eq!(res.as_string(), "20200913");
+            }
+
+            #[test]
+            fn default_format() {
+                let res = date("date(@.ts)").unwrap();
+                assert_eq!(res.as_string(), "2020-09-13T12:26:40+00:00");
+            }
+
+            #[test]
+            fn invalid_input() {
+                let err = date("date('yesterday', '%Y')").unwrap_err();
+                let detail: &FuncCallErrorDetail = err.detail().downcast_ref().unwrap();
+                assert_eq!(
+                    detail,
+                    &FuncCallErrorDetail::InvalidDate {
+                        input: "yesterday".into()
+                    }
+                );
+            }
+        }
+
+        mod now {
+            use super::*;
+
+            fn now(expr: &str) -> NodeRef {
+                let n = NodeRef::null();
+                let mut res = Opath::parse(expr).unwrap().apply(&n, &n).unwrap().into_vec();
+                assert_eq!(res.len(), 1);
+                res.remove(0)
+            }
+
+            #[test]
+            fn timestamp() {
+                let res = now("now()");
+                assert!(res.is_integer());
+                // 2020-09-13T12:26:40Z
+                assert!(res.as_integer().unwrap() > 1_600_000_000);
+            }
+
+            #[test]
+            fn formatted() {
+                let res = now("now('%Y-%m-%d')");
+                assert!(res.is_string());
+                let s = res.as_string();
+                assert_eq!(s.len(), 10);
+                assert!(s.chars().enumerate().all(|(i, c)| if i == 4 || i == 7 {
+                    c == '-'
+                } else {
+                    c.is_ascii_digit()
+                }));
+            }
+        }
+
+        mod uuid {
+            use super::*;
+
+            fn uuid(expr: &str) -> Result<NodeRef, FuncCallError> {
+                let n = NodeRef::null();
+                Opath::parse(expr).unwrap().apply(&n, &n).map(|res| {
+                    let mut res = res.into_vec();
+                    assert_eq!(res.len(), 1);
+                    res.remove(0)
+                })
+            }
+
+            fn assert_uuid_v4(s: &str) {
+                assert_eq!(s.len(), 36);
+                for (i, c) in s.chars().enumerate() {
+                    match i {
+                        8 | 13 | 18 | 23 => assert_eq!(c, '-'),
+                        14 => assert_eq!(c, '4'),
+                        _ => assert!(c.is_ascii_hexdigit() && !c.is_ascii_uppercase()),
+                    }
+                }
+            }
+
+            #[test]
+            fn v4() {
+                let a = uuid("uuid()").unwrap().as_string();
+                let b = uuid("uuid('v4')").unwrap().as_string();
+                assert_uuid_v4(&a);
+                assert_uuid_v4(&b);
+                assert_ne!(a, b);
+            }
+
+            #[test]
+            fn nil() {
+                let res = uuid("uuid('nil')").unwrap();
+                assert_eq!(res.as_string(), "00000000-0000-0000-0000-000000000000");
+            }
+
+            #[test]
+            fn unsupported_version() {
+                let err = uuid("uuid('v9')").unwrap_err();
+                let detail: &FuncCallErrorDetail = err.detail().downcast_ref().unwrap();
+                assert_eq!(
+                    detail,
+                    &FuncCallErrorDetail::UnsupportedUuidVersion {
+                        version: "v9".into()
+                    }
+                );
+            }
+        }
+
+        mod hash {
+            use super::*;
+
+            fn hash(expr: &str) -> Result<NodeRef, FuncCallError> {
+                let n = NodeRef::from_json(r#"{"content": "hello world"}"#).unwrap();
+                Opath::parse(expr).unwrap().apply(&n, &n).map(|res| {
+                    let mut res = res.into_vec();
+                    assert_eq!(res.len(), 1);
+                    res.remove(0)
+                })
+            }
+
+            #[test]
+            fn md5() {
+                let res = hash("hash(@.content, 'md5')").unwrap();
+                assert_eq!(res.as_string(), "5eb63bbbe01eeed093cb22bb8f5acdc3");
+            }
+
+            #[test]
+            fn sha1() {
+                let res = hash("hash(@.content, 'sha1')").unwrap();
+                assert_eq!(res.as_string(), "2aae6c35c94fcfb415dbe95f408b9ce91ee846ed");
+            }
+
+            #[test]
+            fn sha256() {
+                let res = hash("hash(@.content, 'sha256')").unwrap();
+                assert_eq!(
+                    res.as_string(),
+                    "b94d27b9934d3e08a52e52d7da7dabfac484efe37a5380ee9088f7ace2efcde9"
+                );
+            }
+
+            #[test]
+            fn binary_input() {
+                let n = NodeRef::binary(&b"hello world"[..]);
+                let expr = Opath::parse("hash(@, 'md5')").unwrap();
+                let res = expr.apply(&n, &n).unwrap().into_vec();
+                assert_eq!(res[0].as_string(), "5eb63bbbe01eeed093cb22bb8f5acdc3");
+            }
+
+            #[test]
+            fn unknown_algorithm() {
+                let err = hash("hash(@.content, 'crc32')").unwrap_err();
+                let detail: &FuncCallErrorDetail = err.detail().downcast_ref().unwrap();
+                assert_eq!(
+                    detail,
+                    &FuncCallErrorDetail::UnknownHashAlgorithm {
+                        name: "crc32".into()
+                    }
+                );
+            }
+        }
+
+        mod merge {
+            use super::*;
+
+            static PARTS_JSON: &str = r#"
+                {
+                    "base": {"name": "app", "db": {"host": "localhost", "port": 5432}, "tags": ["a"]},
+                    "override": {"db": {"host": "db.prod"}, "tags": ["b", "c"]},
+                    "local": {"debug": true, "db": {"port": 6543}},
+                    "list": [1, 2]
+                }
+            "#;
+
+            fn merge(expr: &str) -> Result<NodeRef, FuncCallError> {
+                let n = NodeRef::from_json(PARTS_JSON).unwrap();
+                let res = Opath::parse(expr).unwrap().apply(&n, &n)?;
+                // inputs are never modified
+                assert!(n.is_equal_deep(&NodeRef::from_json(PARTS_JSON).unwrap()));
+                Ok(res.into_one().unwrap())
+            }
+
+            #[test]
+            fn two_objects() {
+                let res = merge("merge($.base, $.override)").unwrap();
+                assert_eq!(
+                    res.to_json(),
+                    r#"{"name":"app","db":{"host":"db.prod","port":5432},"tags":["b","c"]}"#
+                );
+            }
+
+            #[test]
+            fn three_way() {
+                let res = merge("merge($.base, $.override, $.local)").unwrap();
+                assert_eq!(
+                    res.to_json(),
+                    r#"{"name":"app","db":{"host":"db.prod","port":6543},"tags":["b","c"],"debug":true}"#
+                );
+            }
+
+            #[test]
+            fn non_object() {
+                let err = merge("merge($.base, $.list)").unwrap_err();
+                let detail: &FuncCallErrorDetail = err.detail().downcast_ref().unwrap();
+                assert_eq!(
+                    detail,
+                    &FuncCallErrorDetail::FuncCallInvalidArgType {
+                        id: FuncId::Merge,
+                        kind: Kind::Array,
+                    }
+                );
+            }
+        }
+
+        mod case_conversion {
+            use super::*;
+
+            fn convert(func: &str, input: &str) -> String {
+                let n = NodeRef::null();
+                let expr = Opath::parse(&format!("{}('{}')", func, input)).unwrap();
+                expr.apply(&n, &n).unwrap().into_one().unwrap().as_string()
+            }
+
+            #[test]
+            fn camel_case() {
+                assert_eq!(convert("camel_case", "max_pool_size"), "maxPoolSize");
+                assert_eq!(convert("camel_case", "max-pool-size"), "maxPoolSize");
+                assert_eq!(convert("camel_case", "MaxPoolSize"), "maxPoolSize");
+                assert_eq!(convert("camel_case", "HTTPServer"), "httpServer");
+            }
+
+            #[test]
+            fn snake_case() {
+                assert_eq!(convert("snake_case", "maxPoolSize"), "max_pool_size");
+                assert_eq!(convert("snake_case", "max-pool size"), "max_pool_size");
+                assert_eq!(convert("snake_case", "parseHTTPResponse"), "parse_http_response");
+                assert_eq!(convert("snake_case", "version2Name"), "version2_name");
+            }
+
+            #[test]
+            fn kebab_case() {
+                assert_eq!(convert("kebab_case", "maxPoolSize"), "max-pool-size");
+                assert_eq!(convert("kebab_case", "MAX_POOL_SIZE"), "max-pool-size");
+            }
+        }
+
+        mod concat {
+            use super::*;
+
+            fn eval(expr: &str) -> NodeRef {
+                let n = NodeRef::null();
+                Opath::parse(expr).unwrap().apply(&n, &n).unwrap().into_one().unwrap()
+            }
+
+            #[test]
+            fn numeric_args() {
+                let res = eval("concat(1, 2)");
+                assert!(res.data().is_string());
+                assert_eq!(res.as_string(), "12");
+                assert_eq!(eval("1 + 2").as_integer(), Some(3));
+            }
+
+            #[test]
+            fn mixed_args() {
+                assert_eq!(eval("concat('v', 1.5, '-', true, null)").as_string(), "v1.5-truenull");
+                assert_eq!(eval("concat()").as_string(), "");
+            }
+        }
+
+        mod zip_object {
+            use super::*;
+
+            fn zip(json: &str) -> String {
+                let n = NodeRef::from_json(json).unwrap();
+                let expr = Opath::parse("zip_object($.names.*, $.values.*)").unwrap();
+                expr.apply(&n, &n).unwrap().into_one().unwrap().to_json()
+            }
+
+            #[test]
+            fn equal_lengths() {
+                assert_eq!(
+                    zip(r#"{"names": ["a", "b", "c"], "values": [1, "x", {"y": true}]}"#),
+                    r#"{"a":1,"b":"x","c":{"y":true}}"#
+                );
+            }
+
+            #[test]
+            fn unequal_lengths() {
+                assert_eq!(zip(r#"{"names": ["a", "b", "c"], "values": [1, 2]}"#), r#"{"a":1,"b":2}"#);
+                assert_eq!(zip(r#"{"names": ["a"], "values": [1, 2]}"#), r#"{"a":1}"#);
+                assert_eq!(zip(r#"{"names": [], "values": [1, 2]}"#), r#"{}"#);
+            }
+
+            #[test]
+            fn duplicate_keys() {
+                assert_eq!(zip(r#"{"names": ["a", "b", "a"], "values": [1, 2, 3]}"#), r#"{"a":3,"b":2}"#);
+            }
+        }
+
+        mod to_fixed {
+            use super::*;
+
+            fn fixed(expr: &str) -> String {
+                let n = NodeRef::null();
+                let res = Opath::parse(expr).unwrap().apply(&n, &n).unwrap().into_one().unwrap();
+                assert!(res.data().is_string());
+                res.as_string()
+            }
+
+            #[test]
+            fn rounding() {
+                assert_eq!(fixed("to_fixed(2.346, 2)"), "2.35");
+                assert_eq!(fixed("to_fixed(2.344, 2)"), "2.34");
+                assert_eq!(fixed("to_fixed(0.1 + 0.2, 3)"), "0.300");
+            }
+
+            #[test]
+            fn zero_decimals() {
+                assert_eq!(fixed("to_fixed(2.7, 0)"), "3");
+                assert_eq!(fixed("to_fixed(2.2)"), "2");
+            }
+
+            #[test]
+            fn integers() {
+                assert_eq!(fixed("to_fixed(5, 2)"), "5.00");
+            }
+
+            #[test]
+            fn negative_numbers() {
+                assert_eq!(fixed("to_fixed(-1.236, 2)"), "-1.24");
+                assert_eq!(fixed("to_fixed(-3, 1)"), "-3.0");
+            }
+
+            #[test]
+            fn decimals_capped() {
+                assert_eq!(fixed("to_fixed(1, 2000000000)"), format!("1.{}", "0".repeat(20)));
+            }
+        }
+
+        mod clamp {
+            use super::*;
+
+            fn clamp(expr: &str) -> Result<NodeRef, FuncCallError> {
+                let n = NodeRef::null();
+                Ok(Opath::parse(expr).unwrap().apply(&n, &n)?.into_one().unwrap())
+            }
+
+            #[test]
+            fn below_range() {
+                let res = clamp("clamp(0, 1, 16)").unwrap();
+                assert_eq!(res.as_integer(), Some(1));
+            }
+
+            #[test]
+            fn in_range() {
+                let res = clamp("clamp(8, 1, 16)").unwrap();
+                assert_eq!(res.as_integer(), Some(8));
+                let res = clamp("clamp(2.5, 1, 16)").unwrap();
+                assert!(res.is_float());
+                assert_eq!(res.as_float(), 2.5);
+            }
+
+            #[test]
+            fn above_range() {
+                let res = clamp("clamp(32, 1, 16)").unwrap();
+                assert_eq!(res.as_integer(), Some(16));
+                let res = clamp("clamp(32, 1, 16.5)").unwrap();
+                assert!(res.is_float());
+                assert_eq!(res.as_float(), 16.5);
+            }
+
+            #[test]
+            fn invalid_bounds() {
+                let err = clamp("clamp(5, 10, 1)").unwrap_err();
+                let detail: &FuncCallErrorDetail = err.detail().downcast_ref().unwrap();
+                assert_eq!(detail, &FuncCallErrorDetail::InvalidClampBounds { min: 10.0, max: 1.0 });
+            }
+
+            #[test]
+            fn non_numeric_bound() {
+                let err = clamp("clamp(5, 'a', 10)").unwrap_err();
+                let detail: &FuncCallErrorDetail = err.detail().downcast_ref().unwrap();
+                match *detail {
+                    FuncCallErrorDetail::InvalidClampBounds { min, max } => {
+                        assert!(min.is_nan());
+                        assert_eq!(max, 10.0);
+                    }
+                    _ => panic!("InvalidClampBounds expected"),
+                }
+            }
+
+            #[test]
+            fn large_integer_bounds() {
+                // both bounds convert to the same float
+                let err = clamp("clamp(5, 9223372036854775807, 9223372036854775806)").unwrap_err();
+                let detail: &FuncCallErrorDetail = err.detail().downcast_ref().unwrap();
+                match *detail {
+                    FuncCallErrorDetail::InvalidClampBounds { .. } => {}
+                    _ => panic!("InvalidClampBounds expected"),
+                }
+
+                let res = clamp("clamp(5, 9223372036854775806, 9223372036854775807)").unwrap();
+                assert_eq!(res.as_integer(), Some(9223372036854775806));
+            }
+        }
+
+        mod range {
+            use super::*;
+
+            fn range(expr: &str) -> Result<String, FuncCallError> {
+                let n = NodeRef::null();
+                Ok(Opath::parse(expr).unwrap().apply(&n, &n)?.into_one().unwrap().to_json())
+            }
+
+            #[test]
+            fn ascending() {
+                assert_eq!(range("range(0, 10, 2)").unwrap(), "[0,2,4,6,8,10]");
+                assert_eq!(range("range(1, 4)").unwrap(), "[1,2,3,4]");
+                assert_eq!(range("range(0, 5, -1)").unwrap(), "[]");
+            }
+
+            #[test]
+            fn descending() {
+                assert_eq!(range("range(5, 1, -2)").unwrap(), "[5,3,1]");
+                assert_eq!(range("range(3, 1)").unwrap(), "[3,2,1]");
+            }
+
+            #[test]
+            fn float_step() {
+                assert_eq!(range("range(0, 1, 0.25)").unwrap(), "[0.0,0.25,0.5,0.75,1.0]");
+                assert_eq!(range("range(0.5, 2)").unwrap(), "[0.5,1.5]");
+            }
+
+            #[test]
+            fn zero_step() {
+                let err = range("range(0, 10, 0)").unwrap_err();
+                let detail: &FuncCallErrorDetail = err.detail().downcast_ref().unwrap();
+                assert_eq!(detail, &FuncCallErrorDetail::ZeroRangeStep);
+            }
+
+            #[test]
+            fn near_integer_limits() {
+                assert_eq!(
+                    range("range(9223372036854775806, 9223372036854775807)").unwrap(),
+                    "[9223372036854775806,9223372036854775807]"
+                );
+                assert_eq!(range("range(9223372036854775800, 9223372036854775807, 5)").unwrap(), "[9223372036854775800,9223372036854775805]");
+            }
+
+            #[test]
+            fn non_finite_bounds() {
+                let err = range("range(0, 1e400)").unwrap_err();
+                let detail: &FuncCallErrorDetail = err.detail().downcast_ref().unwrap();
+                match *detail {
+                    FuncCallErrorDetail::NonFiniteRangeBounds { start, stop } => {
+                        assert_eq!(start, 0.0);
+                        assert!(stop.is_infinite());
+                    }
+                    _ => panic!("NonFiniteRangeBounds expected"),
+                }
+            }
+
+            #[test]
+            fn non_finite_step() {
+                for expr in &["range(0, 1, 1 / 0)", "range(0, 1, 'x')"] {
+                    let err = range(expr).unwrap_err();
+                    let detail: &FuncCallErrorDetail = err.detail().downcast_ref().unwrap();
+                    match *detail {
+                        FuncCallErrorDetail::NonFiniteRangeStep { .. } => {}
+                        _ => panic!("NonFiniteRangeStep expected"),
+                    }
+                }
+            }
+
+            #[test]
+            fn too_many_elements() {
+                for expr in &["range(0, 9223372036854775807)", "range(0, 1, 1e-300)", "range(-1e308, 1e308)"] {
+                    let err = range(expr).unwrap_err();
+                    let detail: &FuncCallErrorDetail = err.detail().downcast_ref().unwrap();
+                    match *detail {
+                        FuncCallErrorDetail::TooManyElements { .. } => {}
+                        _ => panic!("TooManyElements expected"),
+                    }
+                }
+            }
+
+            #[test]
+            fn large_step() {
+                assert_eq!(
+                    range("range(-9223372036854775807, 9223372036854775807, 9223372036854775807)").unwrap(),
+                    "[-9223372036854775807,0,9223372036854775807]"
+                );
+            }
+        }
+
+        mod repeat {
+            use super::*;
+
+            #[test]
+            fn scalar() {
+                let n = NodeRef::null();
+                let res = Opath::parse("repeat('x', 3)").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().to_json(), r#"["x","x","x"]"#);
+            }
+
+            #[test]
+            fn independent_copies() {
+                let n = NodeRef::from_json(r#"{"item": {"a": 1}}"#).unwrap();
+                let res = Opath::parse("repeat($.item, 2)").unwrap().apply(&n, &n).unwrap();
+                let res = res.into_one().unwrap();
+                assert_eq!(res.to_json(), r#"[{"a":1},{"a":1}]"#);
+
+                let first = res.get_child_index(0).unwrap();
+                first.get_child_key("a").unwrap().replace_with(NodeRef::integer(2)).unwrap();
+                assert_eq!(res.to_json(), r#"[{"a":2},{"a":1}]"#);
+                assert_eq!(n.to_json(), r#"{"item":{"a":1}}"#);
+            }
+
+            #[test]
+            fn zero_count() {
+                let n = NodeRef::null();
+                for expr in &["repeat('x', 0)", "repeat('x', -2)"] {
+                    let res = Opath::parse(expr).unwrap().apply(&n, &n).unwrap();
+                    assert_eq!(res.into_one().unwrap().to_json(), "[]");
+                }
+            }
+
+            #[test]
+            fn source_tree_untouched() {
+                let n = NodeRef::from_json(r#"{"obj": {"x": "a", "y": "b"}}"#).unwrap();
+                let res = Opath::parse("repeat($.obj.*, 2)").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().to_json(), r#"[["a","b"],["a","b"]]"#);
+
+                let obj = n.get_child_key("obj").unwrap();
+                for (i, key) in ["x", "y"].iter().enumerate() {
+                    let e = obj.get_child_key(key).unwrap();
+                    assert!(e.data().parent().unwrap().is_ref_eq(&obj));
+                    assert_eq!(e.data().index(), i);
+                    assert_eq!(e.data().key(), *key);
+                }
+            }
+
+            #[test]
+            fn too_many_elements() {
+                let n = NodeRef::null();
+                let err = Opath::parse("repeat('x', 9223372036854775807)").unwrap().apply(&n, &n).unwrap_err();
+                let detail: &FuncCallErrorDetail = err.detail().downcast_ref().unwrap();
+                match *detail {
+                    FuncCallErrorDetail::TooManyElements { .. } => {}
+                    _ => panic!("TooManyElements expected"),
+                }
+            }
+        }
+
+        mod abs_rel_path {
+            use super::*;
+
+            fn path(expr: &str) -> String {
+                let n = NodeRef::null();
+                let res = Opath::parse(expr).unwrap().apply(&n, &n).unwrap().into_one().unwrap();
+                res.as_string()
+            }
+
+            #[test]
+            fn abs_path_normalize() {
+                assert_eq!(path("abs_path('/x/./a/../b')"), "/x/b");
+                assert_eq!(path("abs_path('/x/a/b/../../c/')"), "/x/c");
+                assert_eq!(path("abs_path('/..')"), "/");
+            }
+
+            #[test]
+            fn abs_path_relative_input() {
+                let base = std::env::current_dir().unwrap();
+                crate::set_base_path(base.clone());
+                assert_eq!(path("abs_path('./a/../b')"), base.join("b").display().to_string());
+            }
+
+            #[test]
+            fn rel_path_between_absolute_paths() {
+                assert_eq!(path("rel_path('/x/a/b/c.txt', '/x/a')"), "b/c.txt");
+                assert_eq!(path("rel_path('/x/a/c.txt', '/x/b/d')"), "../../a/c.txt");
+                assert_eq!(path("rel_path('/x/a', '/x/a')"), ".");
+            }
+
+            #[test]
+            fn rel_path_relative_input() {
+                crate::set_base_path("/base");
+                assert_eq!(path("rel_path('a/b/../c')"), "a/c");
+                assert_eq!(path("rel_path('a/c', 'a/b')"), "../c");
+                assert_eq!(path("rel_path('/other/a')"), "/other/a");
+            }
+        }
+
+        mod path_parts {
+            use super::*;
+
+            fn part(expr: &str) -> String {
+                let n = NodeRef::null();
+                let res = Opath::parse(expr).unwrap().apply(&n, &n).unwrap().into_one().unwrap();
+                res.as_string()
+            }
+
+            #[test]
+            fn with_extension() {
+                assert_eq!(part("basename('/etc/app/config.yaml')"), "config.yaml");
+                assert_eq!(part("dirname('/etc/app/config.yaml')"), "/etc/app");
+                assert_eq!(part("extname('/etc/app/config.yaml')"), "yaml");
+                assert_eq!(part("extname('archive.tar.gz')"), "gz");
+            }
+
+            #[test]
+            fn without_extension() {
+                assert_eq!(part("basename('bin/run')"), "run");
+                assert_eq!(part("dirname('bin/run')"), "bin");
+                assert_eq!(part("extname('bin/run')"), "");
+                assert_eq!(part("extname('.gitignore')"), "");
+                assert_eq!(part("dirname('run')"), ".");
+            }
+
+            #[test]
+            fn trailing_slash() {
+                assert_eq!(part("basename('/etc/app/')"), "app");
+                assert_eq!(part("dirname('/etc/app/')"), "/etc");
+            }
+
+            #[test]
+            fn root_path() {
+                assert_eq!(part("basename('/')"), "");
+                assert_eq!(part("dirname('/')"), "/");
+                assert_eq!(part("extname('/')"), "");
+                assert_eq!(part("dirname('/etc')"), "/");
+            }
+
+            #[test]
+            fn multiple_paths() {
+                let n = NodeRef::from_json(r#"{"a": "a/b.txt", "c": "c/d.json"}"#).unwrap();
+                let res = Opath::parse("basename($.*)").unwrap().apply(&n, &n).unwrap();
+                let names: Vec<String> = res.into_vec().iter().map(|n| n.as_string()).collect();
+                assert_eq!(names, vec!["b.txt", "d.json"]);
+            }
+        }
+
+        mod exists {
+            use super::*;
+
+            fn exists(expr: &str) -> bool {
+                let n = NodeRef::from_json(r#"{"a": {"b": {"c": null}}, "d": 1}"#).unwrap();
+                let res = Opath::parse(expr).unwrap().apply(&n, &n).unwrap().into_one().unwrap();
+                res.as_boolean()
+            }
+
+            #[test]
+            fn existing_deep_path() {
+                assert!(exists("exists($.a.b.c)"));
+                assert!(exists("exists($.a.b)"));
+            }
+
+            #[test]
+            fn missing_leaf() {
+                assert!(!exists("exists($.a.b.x)"));
+            }
+
+            #[test]
+            fn missing_intermediate() {
+                assert!(!exists("exists($.x.y.z)"));
+                assert!(!exists("exists($.d.y.z)"));
+            }
+        }
+
+        mod type_guards {
+            use super::*;
+
+            const PREDICATES: [&str; 6] = ["is_string", "is_number", "is_array", "is_object", "is_bool", "is_null"];
+
+            fn matching(json: &str) -> Vec<&'static str> {
+                let n = NodeRef::from_json(&format!(r#"{{"x": {}}}"#, json)).unwrap();
+                PREDICATES
+                    .iter()
+                    .cloned()
+                    .filter(|p| {
+                        let expr = Opath::parse(&format!("{}(@.x)", p)).unwrap();
+                        expr.apply(&n, &n).unwrap().into_one().unwrap().as_boolean()
+                    })
+                    .collect()
+            }
+
+            #[test]
+            fn exactly_one_matches() {
+                assert_eq!(matching(r#""text""#), vec!["is_string"]);
+                assert_eq!(matching("12"), vec!["is_number"]);
+                assert_eq!(matching("1.5"), vec!["is_number"]);
+                assert_eq!(matching("[1, 2]"), vec!["is_array"]);
+                assert_eq!(matching(r#"{"a": 1}"#), vec!["is_object"]);
+                assert_eq!(matching("false"), vec!["is_bool"]);
+                assert_eq!(matching("null"), vec!["is_null"]);
+            }
+
+            #[test]
+            fn missing_value() {
+                let n = NodeRef::from_json("{}").unwrap();
+                for p in PREDICATES.iter() {
+                    let expr = Opath::parse(&format!("{}(@.x)", p)).unwrap();
+                    assert!(!expr.apply(&n, &n).unwrap().into_one().unwrap().as_boolean());
+                }
+            }
+        }
+
+        mod set_operations {
+            use super::*;
+
+            static JSON: &str = r#"{
+                "a": [1, 2, 2, 3],
+                "b": [3, 4, 2],
+                "c": [5, 6],
+                "s": ["x", "y"],
+                "t": ["y", "z", "y"]
+            }"#;
+
+            fn eval(expr: &str) -> String {
+                let n = NodeRef::from_json(JSON).unwrap();
+                let res = Opath::parse(expr).unwrap().apply(&n, &n).unwrap().into_one().unwrap();
+                res.to_json()
+            }
+
+            #[test]
+            fn union() {
+                assert_eq!(eval("union($.a.*, $.b.*)"), "[1,2,3,4]");
+                assert_eq!(eval("union($.a.*, $.c.*)"), "[1,2,3,5,6]");
+                assert_eq!(eval("union($.s.*, $.t.*)"), r#"["x","y","z"]"#);
+            }
+
+            #[test]
+            fn intersection() {
+                assert_eq!(eval("intersection($.a.*, $.b.*)"), "[2,3]");
+                assert_eq!(eval("intersection($.a.*, $.c.*)"), "[]");
+                assert_eq!(eval("intersection($.t.*, $.s.*)"), r#"["y"]"#);
+            }
+
+            #[test]
+            fn difference() {
+                assert_eq!(eval("difference($.a.*, $.b.*)"), "[1]");
+                assert_eq!(eval("difference($.a.*, $.c.*)"), "[1,2,2,3]");
+                assert_eq!(eval("difference($.t.*, $.s.*)"), r#"["z"]"#);
+            }
+
+            #[test]
+            fn source_not_modified() {
+                let n = NodeRef::from_json(JSON).unwrap();
+                Opath::parse("union($.a.*, $.b.*)").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(n.get_child_key("a").unwrap().to_json(), "[1,2,2,3]");
+            }
+        }
+
+        mod custom {
+            use super::*;
+
+            #[test]
+            fn filter_numbers() {
+                fn filter_numbers(
+                    _name: &str,
+                    args: Args,
+                    env: Env<'_>,
+                    out: &mut NodeBuf,
+                ) -> FuncCallResult {
+                    let res = args.resolve_flat(false, env).unwrap();
+                    for r in res.into_iter() {
+                        if r.is_number() {
+                            out.add(r);
+                        }
+                    }
+                    Ok(())
+                }
+
+                let scope = ScopeMut::new()
+                    .with_func("filter_numbers".into(), box Func::new(filter_numbers));
+                let n = test_node();
+                let expr = Opath::parse("filter_numbers($.**).@path").unwrap();
+                let res = expr.apply_ext(&n, &n, scope.as_ref()).unwrap().into_vec();
+
+                assert_eq!(res.len(), 3);
+                assert_eq!(res[0].as_string(), "$.one");
+                assert_eq!(res[1].as_string(), "$.nested.two");
+                assert_eq!(res[2].as_string(), "$.nested.four");
+            }
+        }
+    }
+
+    mod method {
+        use super::*;
+
+        mod find {
+            use super::*;
+
+            #[test]
+            fn nested_prop() {
+                let n = test_node();
+
+                let expr = Opath::parse("@.find('nested.two')").unwrap();
+
+                let res = expr.apply(&n, &n).unwrap().into_vec();
+
+                assert_eq!(res.len(), 1);
+                assert_eq!(Opath::from(&res[0]).to_string(), "$.nested.two");
+            }
+
+            #[test]
+            fn wildcard() {
+                let n = test_node();
+
+                let expr = Opath::parse("@.find('nested.*')").unwrap();
+
+                let res = expr.apply(&n, &n).unwrap().into_vec();
+
+                assert_eq!(res.len(), 3);
+                assert_eq!(Opath::from(&res[0]).to_string(), "$.nested.two");
+                assert_eq!(Opath::from(&res[1]).to_string(), "$.nested.three_string");
+                assert_eq!(Opath::from(&res[2]).to_string(), "$.nested.four");
+            }
+        }
+
+        mod first_last {
+            use super::*;
+
+            #[test]
+            fn many() {
+                let n = test_node();
+
+                let res = Opath::parse("@.nested.*.first()").unwrap().apply(&n, &n).unwrap();
+                assert!(res.is_one());
+                let first = res.into_one().unwrap();
+                assert_eq!(Opath::from(&first).to_string(), "$.nested.two");
+
+                let res = Opath::parse("@.nested.*.last()").unwrap().apply(&n, &n).unwrap();
+                assert!(res.is_one());
+                let last = res.into_one().unwrap();
+                assert_eq!(Opath::from(&last).to_string(), "$.nested.four");
+                assert!(last.is_ref_eq(&n.get_child_key("nested").unwrap().get_child_key("four").unwrap()));
+            }
+
+            #[test]
+            fn single() {
+                let n = test_node();
+
+                let res = Opath::parse("@.one.first()").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().as_integer(), Some(1));
+
+                let res = Opath::parse("@.array.*.last()").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().as_string(), "b");
+            }
+
+            #[test]
+            fn empty() {
+                let n = test_node();
+
+                let res = Opath::parse("@.empty_array.*.first()").unwrap().apply(&n, &n).unwrap();
+                assert!(res.is_empty());
+
+                let res = Opath::parse("@.missing.last()").unwrap().apply(&n, &n).unwrap();
+                assert!(res.is_empty());
+            }
+        }
+
+        mod nth {
+            use super::*;
+
+            #[test]
+            fn positive_index() {
+                let n = test_node();
+
+                let res = Opath::parse("@.nested.*.nth(1)").unwrap().apply(&n, &n).unwrap();
+                assert!(res.is_one());
+                let e = res.into_one().unwrap();
+                assert_eq!(Opath::from(&e).to_string(), "$.nested.three_string");
+
+                let res = Opath::parse("@.nested.*.nth(0)").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().as_integer(), Some(2));
+            }
+
+            #[test]
+            fn negative_index() {
+                let n = test_node();
+
+                let res = Opath::parse("@.nested.*.nth(-1)").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().as_integer(), Some(4));
+
+                let res = Opath::parse("@.nested.*.nth(-3)").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().as_integer(), Some(2));
+            }
+
+            #[test]
+            fn out_of_range() {
+                let n = test_node();
+
+                let res = Opath::parse("@.nested.*.nth(3)").unwrap().apply(&n, &n).unwrap();
+                assert!(res.is_empty());
+
+                let res = Opath::parse("@.nested.*.nth(-4)").unwrap().apply(&n, &n).unwrap();
+                assert!(res.is_empty());
+
+                let res = Opath::parse("@.empty_array.*.nth(0)").unwrap().apply(&n, &n).unwrap();
+                assert!(res.is_empty());
             }
-            assert_eq!(consumable, false);
         }
 
-        #[test]
-        fn resolve_flat_consumable() {
-            let n = test_node();
-            let a = vec![expr("$.**"), expr("'str'"), expr("1..10")];
-            let args = Args::new(&a);
+        mod count {
+            use super::*;
 
-            let values = args.resolve_flat(true, Env::new(&n, &n, None)).unwrap();
+            #[test]
+            fn array_elements() {
+                let n = test_node();
 
-            assert_eq!(values.len(), 11 + 1 + 10);
-            for v in values.iter() {
-                assert!(v.is_consumable());
+                let res = Opath::parse("@.array.*.count()").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().as_integer(), Some(2));
+            }
+
+            #[test]
+            fn object_properties() {
+                let n = test_node();
+
+                let res = Opath::parse("@.nested.*.count()").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().as_integer(), Some(3));
+
+                let res = Opath::parse("@.nested.*.count() > 2").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().as_boolean(), true);
+            }
+
+            #[test]
+            fn empty() {
+                let n = test_node();
+
+                let res = Opath::parse("@.empty_array.*.count()").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().as_integer(), Some(0));
+
+                let res = Opath::parse("@.missing.count()").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().as_integer(), Some(0));
             }
         }
 
-        #[test]
-        fn resolve_rows() {
-            let n = test_node();
-            let a = vec![expr("$.**"), expr("'str'"), expr("1..10")];
-            let args = Args::new(&a);
+        mod any_all {
+            use super::*;
 
-            let values = args
-                .resolve_rows_null(false, None, Env::new(&n, &n, None))
-                .unwrap();
-            assert_eq!(values.len(), 10);
-            assert_eq!(values[0].len(), 3);
-            assert_eq!(values[1].len(), 3);
-            assert_eq!(values[2].len(), 3);
-            assert_eq!(values[3].len(), 3);
-            assert_eq!(values[4].len(), 3);
-            assert_eq!(values[5].len(), 3);
-            assert_eq!(values[6].len(), 3);
-            assert_eq!(values[7].len(), 3);
-            assert_eq!(values[8].len(), 3);
-            assert_eq!(values[9].len(), 3);
+            static ITEMS_JSON: &str = r#"
+                {
+                    "valid": [{"valid": true, "error": null}, {"valid": true, "error": null}],
+                    "mixed": [{"valid": true, "error": null}, {"valid": false, "error": "bad"}],
+                    "empty": []
+                }
+            "#;
 
-            let mut consumable = true;
-            for vals in values.iter() {
-                for v in vals.iter() {
-                    consumable = consumable && v.is_consumable();
+            fn eval(n: &NodeRef, expr: &str) -> bool {
+                let res = Opath::parse(expr).unwrap().apply(n, n).unwrap();
+                assert!(res.is_one());
+                res.into_one().unwrap().as_boolean()
+            }
+
+            #[test]
+            fn all_true() {
+                let n = NodeRef::from_json(ITEMS_JSON).unwrap();
+
+                assert!(eval(&n, "$.valid.*.all(@.valid)"));
+                assert!(!eval(&n, "$.valid.*.any(@.error)"));
+            }
+
+            #[test]
+            fn some_false() {
+                let n = NodeRef::from_json(ITEMS_JSON).unwrap();
+
+                assert!(!eval(&n, "$.mixed.*.all(@.valid)"));
+                assert!(eval(&n, "$.mixed.*.any(@.error)"));
+                assert!(eval(&n, "$.mixed.*.any(@.valid == false)"));
+            }
+
+            #[test]
+            fn empty_set() {
+                let n = NodeRef::from_json(ITEMS_JSON).unwrap();
+
+                assert!(eval(&n, "$.empty.*.all(@.valid)"));
+                assert!(!eval(&n, "$.empty.*.any(@.valid)"));
+            }
+        }
+
+        mod map {
+            use super::*;
+
+            static USERS_JSON: &str = r#"
+                {
+                    "users": [
+                        {"name": "alice", "age": 30, "tags": ["a", "b"]},
+                        {"name": "bob", "age": 25, "tags": []}
+                    ]
                 }
+            "#;
+
+            #[test]
+            fn project_field() {
+                let n = NodeRef::from_json(USERS_JSON).unwrap();
+
+                let res = Opath::parse("$.users.*.map(@.name)").unwrap().apply(&n, &n).unwrap();
+                assert!(res.is_one());
+                assert_eq!(res.into_one().unwrap().to_json(), r#"["alice","bob"]"#);
+            }
+
+            #[test]
+            fn project_expression() {
+                let n = NodeRef::from_json(USERS_JSON).unwrap();
+
+                let res = Opath::parse("$.users.*.map(@.age * 2)").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().to_json(), "[60,50]");
+            }
+
+            #[test]
+            fn nested_and_missing_results() {
+                let n = NodeRef::from_json(USERS_JSON).unwrap();
+
+                let res = Opath::parse("$.users.*.map(@.tags.*)").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().to_json(), r#"[["a","b"],null]"#);
+            }
+
+            #[test]
+            fn empty_set() {
+                let n = NodeRef::from_json(USERS_JSON).unwrap();
+
+                let res = Opath::parse("$.missing.*.map(@.name)").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().to_json(), "[]");
             }
-            assert_eq!(consumable, false);
         }
 
-        #[test]
-        fn resolve_rows_consumable() {
-            let n = test_node();
-            let a = vec![expr("$.**"), expr("'str'"), expr("1..10")];
-            let args = Args::new(&a);
+        mod filter {
+            use super::*;
 
-            let values = args
-                .resolve_rows_null(true, None, Env::new(&n, &n, None))
-                .unwrap();
+            static ITEMS_JSON: &str = r#"
+                {
+                    "items": [
+                        {"id": 1, "active": true, "price": 10},
+                        {"id": 2, "active": false, "price": 25},
+                        {"id": 3, "active": true, "price": 40}
+                    ]
+                }
+            "#;
 
-            assert_eq!(values.len(), 10);
-            assert_eq!(values[0].len(), 3);
-            assert_eq!(values[1].len(), 3);
-            assert_eq!(values[2].len(), 3);
-            assert_eq!(values[3].len(), 3);
-            assert_eq!(values[4].len(), 3);
-            assert_eq!(values[5].len(), 3);
-            assert_eq!(values[6].len(), 3);
-            assert_eq!(values[7].len(), 3);
-            assert_eq!(values[8].len(), 3);
-            assert_eq!(values[9].len(), 3);
+            #[test]
+            fn boolean_field() {
+                let n = NodeRef::from_json(ITEMS_JSON).unwrap();
+
+                let res = Opath::parse("$.items.*.filter(@.active)").unwrap().apply(&n, &n).unwrap();
+                let res = res.into_vec();
+                assert_eq!(res.len(), 2);
+                assert_eq!(Opath::from(&res[0]).to_string(), "$.items[0]");
+                assert_eq!(Opath::from(&res[1]).to_string(), "$.items[2]");
+            }
 
-            for vals in values.iter() {
-                for v in vals.iter() {
-                    assert!(v.is_consumable());
+            #[test]
+            fn comparison() {
+                let n = NodeRef::from_json(ITEMS_JSON).unwrap();
+
+                let res = Opath::parse("$.items.*.filter(@.price > 20)").unwrap().apply(&n, &n).unwrap();
+                let res = res.into_vec();
+                assert_eq!(res.len(), 2);
+                assert_eq!(res[0].get_child_key("id").unwrap().as_integer(), Some(2));
+                assert_eq!(res[1].get_child_key("id").unwrap().as_integer(), Some(3));
+
+                let res = Opath::parse("$.items.*.filter(@.price > 100)").unwrap().apply(&n, &n).unwrap();
+                assert!(res.is_empty());
+            }
+
+            #[test]
+            fn chaining() {
+                let n = NodeRef::from_json(ITEMS_JSON).unwrap();
+
+                let res = Opath::parse("$.items.*.filter(@.active).map(@.id)").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().to_json(), "[1,3]");
+
+                let res = Opath::parse("$.items.*.filter(@.active).count()").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().as_integer(), Some(2));
+
+                let res = Opath::parse("$.items.*.filter(@.active).last().price").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().as_integer(), Some(40));
+            }
+        }
+
+        mod reject {
+            use super::*;
+
+            static ITEMS_JSON: &str = r#"
+                {
+                    "items": [
+                        {"id": 1, "hidden": false},
+                        {"id": 2, "hidden": true},
+                        {"id": 3},
+                        {"id": 4, "hidden": true}
+                    ]
                 }
+            "#;
+
+            fn ids(expr: &str, n: &NodeRef) -> Vec<i64> {
+                let res = Opath::parse(expr).unwrap().apply(n, n).unwrap();
+                res.into_vec().iter().map(|e| e.get_child_key("id").unwrap().as_integer().unwrap()).collect()
+            }
+
+            #[test]
+            fn boolean_field() {
+                let n = NodeRef::from_json(ITEMS_JSON).unwrap();
+                assert_eq!(ids("$.items.*.reject(@.hidden)", &n), vec![1, 3]);
+            }
+
+            #[test]
+            fn complement_of_filter() {
+                let n = NodeRef::from_json(ITEMS_JSON).unwrap();
+                let kept = ids("$.items.*.filter(@.id > 2)", &n);
+                let rejected = ids("$.items.*.reject(@.id > 2)", &n);
+                assert_eq!(kept, vec![3, 4]);
+                assert_eq!(rejected, vec![1, 2]);
+
+                let mut all = kept;
+                all.extend(rejected);
+                all.sort();
+                assert_eq!(all, ids("$.items.*", &n));
             }
         }
-    }
 
-    mod func {
-        use super::*;
+        mod reduce {
+            use super::*;
 
-        #[test]
-        fn array() {
-            let n = test_node();
+            #[test]
+            fn sum() {
+                let n = NodeRef::from_json(r#"{"nums": [1, 2, 3, 4]}"#).unwrap();
 
-            let expr = Opath::parse("array(@.*.@key)").unwrap();
+                let res = Opath::parse("$.nums.*.reduce(0, $acc + @)").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().as_integer(), Some(10));
+            }
 
-            let res = expr.apply(&n, &n).unwrap();
+            #[test]
+            fn concat_strings() {
+                let n = NodeRef::from_json(r#"{"words": ["a", "b", "c"]}"#).unwrap();
 
-            assert_eq!(res.len(), 1);
+                let res = Opath::parse("$.words.*.reduce('', $acc + @)").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().as_string(), "abc");
+            }
+
+            #[test]
+            fn empty_set_returns_init() {
+                let n = NodeRef::from_json(r#"{"nums": []}"#).unwrap();
+
+                let res = Opath::parse("$.nums.*.reduce(5, $acc + @)").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().as_integer(), Some(5));
+            }
+        }
+
+        mod closest {
+            use super::*;
+
+            static SERVICES_JSON: &str = r#"
+                {
+                    "type": "root",
+                    "services": {
+                        "web": {
+                            "type": "service",
+                            "config": {"ports": [80, 443]}
+                        }
+                    }
+                }
+            "#;
+
+            #[test]
+            fn matching_ancestor() {
+                let n = NodeRef::from_json(SERVICES_JSON).unwrap();
+
+                let res = Opath::parse("$.services.web.config.ports[0].closest(@.type == 'service')")
+                    .unwrap()
+                    .apply(&n, &n)
+                    .unwrap();
+                let res = res.into_one().unwrap();
+                assert_eq!(Opath::from(&res).to_string(), "$.services.web");
+            }
+
+            #[test]
+            fn no_match() {
+                let n = NodeRef::from_json(SERVICES_JSON).unwrap();
+
+                let res = Opath::parse("$.services.web.config.closest(@.type == 'database')")
+                    .unwrap()
+                    .apply(&n, &n)
+                    .unwrap();
+                assert!(res.is_empty());
+            }
+
+            #[test]
+            fn matches_self() {
+                let n = NodeRef::from_json(SERVICES_JSON).unwrap();
+
+                let res = Opath::parse("$.services.web.closest(@.type == 'service')")
+                    .unwrap()
+                    .apply(&n, &n)
+                    .unwrap();
+                let res = res.into_one().unwrap();
+                assert_eq!(Opath::from(&res).to_string(), "$.services.web");
+            }
+        }
+
+        mod siblings {
+            use super::*;
+
+            #[test]
+            fn array() {
+                let n = NodeRef::from_json(r#"{"list": [1, 2, 3]}"#).unwrap();
+
+                let res = Opath::parse("$.list[1].next()").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().as_integer(), Some(3));
+
+                let res = Opath::parse("$.list[1].prev()").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().as_integer(), Some(1));
+            }
+
+            #[test]
+            fn array_boundaries() {
+                let n = NodeRef::from_json(r#"{"list": [1, 2, 3]}"#).unwrap();
+
+                let res = Opath::parse("$.list[0].prev()").unwrap().apply(&n, &n).unwrap();
+                assert!(res.is_empty());
+
+                let res = Opath::parse("$.list[2].next()").unwrap().apply(&n, &n).unwrap();
+                assert!(res.is_empty());
+
+                let res = Opath::parse("$.next()").unwrap().apply(&n, &n).unwrap();
+                assert!(res.is_empty());
+            }
+
+            #[test]
+            fn object_property_order() {
+                let n = NodeRef::from_json(r#"{"a": 1, "b": 2, "c": 3}"#).unwrap();
+
+                let res = Opath::parse("$.b.next()").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().data().key(), "c");
+
+                let res = Opath::parse("$.b.prev()").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().data().key(), "a");
+
+                let res = Opath::parse("$.a.prev()").unwrap().apply(&n, &n).unwrap();
+                assert!(res.is_empty());
+
+                let res = Opath::parse("$.c.next()").unwrap().apply(&n, &n).unwrap();
+                assert!(res.is_empty());
+            }
+        }
+
+        mod to_json_string {
+            use super::*;
+
+            static CONFIG_JSON: &str = r#"
+                {
+                    "config": {"name": "web", "port": 80},
+                    "list": [1, "two", null]
+                }
+            "#;
+
+            #[test]
+            fn object() {
+                let n = NodeRef::from_json(CONFIG_JSON).unwrap();
+                let config = n.get_child_key("config").unwrap();
+
+                let res = Opath::parse("@.config.to_json_string()").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().as_string(), r#"{"name":"web","port":80}"#);
+
+                let res = Opath::parse("@.config.to_json_string(true)").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().as_string(), config.to_json_pretty());
+            }
+
+            #[test]
+            fn array() {
+                let n = NodeRef::from_json(CONFIG_JSON).unwrap();
+                let list = n.get_child_key("list").unwrap();
+
+                let res = Opath::parse("@.list.to_json_string(false)").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().as_string(), r#"[1,"two",null]"#);
+
+                let res = Opath::parse("@.list.to_json_string(true)").unwrap().apply(&n, &n).unwrap();
+                let res = res.into_one().unwrap().as_string();
+                assert_eq!(res, list.to_json_pretty());
+                assert!(res.contains('\n'));
+            }
+        }
+
+        mod parse_json {
+            use super::*;
+
+            static EMBEDDED_JSON: &str = r#"
+                {
+                    "embedded": "{\"name\": \"web\", \"ports\": [80, 443]}",
+                    "scalar": "42",
+                    "malformed": "{\"name\": "
+                }
+            "#;
+
+            #[test]
+            fn object() {
+                let n = NodeRef::from_json(EMBEDDED_JSON).unwrap();
+
+                let res = Opath::parse("@.embedded.parse_json()").unwrap().apply(&n, &n).unwrap();
+                let res = res.into_one().unwrap();
+                assert!(res.is_object());
+                assert_eq!(res.to_json(), r#"{"name":"web","ports":[80,443]}"#);
+
+                let res = Opath::parse("@.embedded.parse_json().ports[1]").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().as_integer(), Some(443));
+            }
+
+            #[test]
+            fn scalar() {
+                let n = NodeRef::from_json(EMBEDDED_JSON).unwrap();
+
+                let res = Opath::parse("@.scalar.parse_json()").unwrap().apply(&n, &n).unwrap();
+                let res = res.into_one().unwrap();
+                assert!(res.is_integer());
+                assert_eq!(res.as_integer(), Some(42));
+            }
+
+            #[test]
+            fn malformed() {
+                let n = NodeRef::from_json(EMBEDDED_JSON).unwrap();
+
+                let err = Opath::parse("@.malformed.parse_json()").unwrap().apply(&n, &n).unwrap_err();
+                let detail: &FuncCallErrorDetail = err.detail().downcast_ref().unwrap();
+                assert_eq!(detail, &FuncCallErrorDetail::NodeParse);
+            }
+        }
+
+        mod parse_yaml_toml {
+            use super::*;
+
+            static EMBEDDED_JSON: &str = r#"
+                {
+                    "yaml": "name: web\nports:\n  - 80\n  - 443\n",
+                    "toml": "name = \"web\"\nports = [80, 443]\n",
+                    "malformed_yaml": "name: [web",
+                    "malformed_toml": "name = "
+                }
+            "#;
 
-            let ref a = res.into_vec()[0];
-            assert!(a.is_array());
-            assert_eq!(a.data().children_count(), Some(6));
-        }
+            #[test]
+            fn yaml() {
+                let n = NodeRef::from_json(EMBEDDED_JSON).unwrap();
 
-        #[test]
-        fn map() {
-            let n = test_node();
+                let res = Opath::parse("@.yaml.parse_yaml()").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().to_json(), r#"{"name":"web","ports":[80,443]}"#);
+            }
 
-            let expr = Opath::parse("map(@.*.@key, @.*.@index)").unwrap();
+            #[test]
+            fn toml() {
+                let n = NodeRef::from_json(EMBEDDED_JSON).unwrap();
 
-            let res = expr.apply(&n, &n).unwrap();
+                let res = Opath::parse("@.toml.parse_toml()").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().to_json(), r#"{"name":"web","ports":[80,443]}"#);
+            }
 
-            assert_eq!(res.len(), 1);
+            #[test]
+            fn malformed() {
+                let n = NodeRef::from_json(EMBEDDED_JSON).unwrap();
 
-            let ref o = res.into_vec()[0];
-            assert!(o.is_object());
-            assert_eq!(o.data().children_count(), Some(6));
+                for expr in &["@.malformed_yaml.parse_yaml()", "@.malformed_toml.parse_toml()"] {
+                    let err = Opath::parse(expr).unwrap().apply(&n, &n).unwrap_err();
+                    let detail: &FuncCallErrorDetail = err.detail().downcast_ref().unwrap();
+                    assert_eq!(detail, &FuncCallErrorDetail::NodeParse);
+                }
+            }
         }
 
-        mod find_new {
+        mod keys_matching {
             use super::*;
 
-            #[test]
-            fn should_return_error_without_diff_env() {
-                let expr = Opath::parse("findNew($.some)").unwrap();
-                let root = NodeRef::object(Properties::new());
-                let err = expr.apply(&root, &root).unwrap_err();
-                let detail: &FuncCallErrorDetail = err.detail().downcast_ref().unwrap();
-                assert_eq!(detail, &FuncCallErrorDetail::UnknownFunc { name: "findNew".into() });
+            static CONFIG_JSON: &str = r#"
+                {
+                    "db_host": "localhost",
+                    "db_port": 5432,
+                    "cache_host": "redis",
+                    "debug_db": true
+                }
+            "#;
+
+            fn keys(expr: &str) -> Result<Vec<String>, FuncCallError> {
+                let n = NodeRef::from_json(CONFIG_JSON).unwrap();
+                let res = Opath::parse(expr).unwrap().apply(&n, &n)?;
+                Ok(res.into_vec().iter().map(|n| n.data().key().to_string()).collect())
             }
 
-            //FIXME (jc) add further tests
-        }
+            #[test]
+            fn prefix() {
+                assert_eq!(keys("@.keys_matching('^db_')").unwrap(), vec!["db_host", "db_port"]);
+                assert_eq!(keys("@.keys_matching('_host$').@key").unwrap().len(), 2);
+            }
 
-        mod find_old {
-            use super::*;
+            #[test]
+            fn no_matches() {
+                assert!(keys("@.keys_matching('^api_')").unwrap().is_empty());
+            }
 
             #[test]
-            fn should_return_error_without_diff_env() {
-                let expr = Opath::parse("findOld($.some)").unwrap();
-                let root = NodeRef::object(Properties::new());
-                let err = expr.apply(&root, &root).unwrap_err();
+            fn invalid_regex() {
+                let err = keys("@.keys_matching('db_(')").unwrap_err();
                 let detail: &FuncCallErrorDetail = err.detail().downcast_ref().unwrap();
-                assert_eq!(detail, &FuncCallErrorDetail::UnknownFunc { name: "findOld".into() });
+                match *detail {
+                    FuncCallErrorDetail::RegexParse { .. } => {}
+                    _ => panic!("RegexParse expected"),
+                }
             }
 
-            //FIXME (jc) add further tests
+            #[test]
+            fn invalid_pattern_arg() {
+                for e in &["@.keys_matching($.missing)", "@.keys_matching(@.*)"] {
+                    let err = keys(e).unwrap_err();
+                    let detail: &FuncCallErrorDetail = err.detail().downcast_ref().unwrap();
+                    match *detail {
+                        FuncCallErrorDetail::MethodCallSingleArgRequired { .. } => {}
+                        _ => panic!("MethodCallSingleArgRequired expected"),
+                    }
+                }
+            }
         }
 
-        mod parse_int {
+        mod pick_omit {
             use super::*;
 
-            #[test]
-            fn multiple_args() {
-                let n = NodeRef::from_json(r#"{
-                                                        "nums":["10", "-12aaa", "FF", "fE", "101", "1A"],
-                                                        "basis":[10, 10, 16, 16, 2, "aaa"]
-                                                    }"#)
-                    .unwrap();
+            static USER_JSON: &str = r#"
+                {"id": 1, "name": "john", "email": "john@example.com", "secret": "xyz"}
+            "#;
 
-                let expr = Opath::parse("parseInt(@.nums.*, @.basis.*)").unwrap();
-                let mut res = expr.apply(&n, &n).unwrap().into_iter();
+            fn apply(expr: &str) -> String {
+                let n = NodeRef::from_json(USER_JSON).unwrap();
+                let res = Opath::parse(expr).unwrap().apply(&n, &n).unwrap();
+                res.into_one().unwrap().to_json()
+            }
 
-                assert_eq!(res.len(), 6);
-                assert_eq!(res.next().unwrap().as_integer().unwrap(), 10);
-                assert_eq!(res.next().unwrap().as_integer().unwrap(), -12);
-                assert_eq!(res.next().unwrap().as_integer().unwrap(), 255);
-                assert_eq!(res.next().unwrap().as_integer().unwrap(), 254);
-                assert_eq!(res.next().unwrap().as_integer().unwrap(), 5);
-                assert_eq!(res.next().unwrap().as_integer().unwrap(), 1);
+            #[test]
+            fn pick() {
+                assert_eq!(apply("@.pick('name', 'id')"), r#"{"id":1,"name":"john"}"#);
+                assert_eq!(apply("@.pick(['email', 'id'])"), r#"{"id":1,"email":"john@example.com"}"#);
             }
 
             #[test]
-            fn integer_str() {
-                let n = NodeRef::null();
-                let expr = Opath::parse("parseInt('10')").unwrap();
-                let res = expr.apply(&n, &n).unwrap().into_vec();
+            fn omit() {
+                assert_eq!(
+                    apply("@.omit('secret')"),
+                    r#"{"id":1,"name":"john","email":"john@example.com"}"#
+                );
+                assert_eq!(apply("@.omit(['secret', 'email'], 'id')"), r#"{"name":"john"}"#);
+            }
 
-                assert_eq!(res.len(), 1);
+            #[test]
+            fn nonexistent_key() {
+                assert_eq!(apply("@.pick('id', 'missing')"), r#"{"id":1}"#);
+                assert_eq!(apply("@.omit('missing').pick('secret')"), r#"{"secret":"xyz"}"#);
+            }
+        }
 
-                let res = res.get(0).unwrap();
+        mod rekey {
+            use super::*;
 
-                assert!(res.is_integer());
-                assert_eq!(res.as_integer().unwrap(), 10)
+            #[test]
+            fn nested_object() {
+                let n = NodeRef::from_json(
+                    r#"{"dbConfig": {"maxPoolSize": 10, "hostNames": [{"primaryHost": "a"}]}, "logLevel": "info"}"#,
+                )
+                .unwrap();
+                let res = Opath::parse("@.rekey('snake')").unwrap().apply(&n, &n).unwrap();
+                let res = res.into_one().unwrap();
+                assert_eq!(
+                    res.to_json(),
+                    r#"{"db_config":{"max_pool_size":10,"host_names":[{"primary_host":"a"}]},"log_level":"info"}"#
+                );
+                let c = res.get_child_key("db_config").unwrap().get_child_key("max_pool_size").unwrap();
+                assert_eq!(c.data().key(), "max_pool_size");
+                assert_eq!(c.path().to_string(), "$.db_config.max_pool_size");
+                // the source is not modified
+                assert!(n.get_child_key("dbConfig").is_some());
             }
 
             #[test]
-            fn radix() {
-                let n = NodeRef::from_json(r#"{}"#).unwrap();
-                let expr = Opath::parse("parseInt('10', 2)").unwrap();
-                let res = expr.apply(&n, &n).unwrap().into_vec();
+            fn unknown_style() {
+                let n = NodeRef::from_json(r#"{"a": 1}"#).unwrap();
+                let err = Opath::parse("@.rekey('pascal')").unwrap().apply(&n, &n).unwrap_err();
+                let detail: &FuncCallErrorDetail = err.detail().downcast_ref().unwrap();
+                assert_eq!(
+                    detail,
+                    &FuncCallErrorDetail::UnknownCaseStyle {
+                        style: "pascal".into()
+                    }
+                );
+            }
+        }
 
-                assert_eq!(res.len(), 1);
+        mod keys_where {
+            use super::*;
 
-                let res = res.get(0).unwrap();
+            static PORTS_JSON: &str = r#"
+                {
+                    "http": 80,
+                    "app": 8080,
+                    "https": 443,
+                    "debug": 5005
+                }
+            "#;
 
-                assert!(res.is_integer());
-                assert_eq!(res.as_integer().unwrap(), 2)
+            fn keys(expr: &str) -> Vec<String> {
+                let n = NodeRef::from_json(PORTS_JSON).unwrap();
+                let res = Opath::parse(expr).unwrap().apply(&n, &n).unwrap();
+                res.into_vec().iter().map(|n| n.as_string()).collect()
             }
 
             #[test]
-            fn radix_str() {
-                let n = NodeRef::null();
-                let expr = Opath::parse("parseInt('10', '2')").unwrap();
-                let res = expr.apply(&n, &n).unwrap().into_vec();
-
-                assert_eq!(res.len(), 1);
-
-                let res = res.get(0).unwrap();
-
-                assert!(res.is_integer());
-                assert_eq!(res.as_integer().unwrap(), 2)
+            fn numeric_threshold() {
+                assert_eq!(keys("@.keys_where(@ > 1024)"), vec!["app", "debug"]);
             }
 
             #[test]
-            fn float() {
-                let n = NodeRef::from_json(r#"{}"#).unwrap();
-                let expr = Opath::parse("parseInt('10.33')").unwrap();
-                let res = expr.apply(&n, &n).unwrap().into_vec();
+            fn string_match() {
+                assert_eq!(keys("@.keys_where(@.@key ^= 'http')"), vec!["http", "https"]);
+            }
 
-                assert_eq!(res.len(), 1);
+            #[test]
+            fn empty_result() {
+                assert!(keys("@.keys_where(@ > 65535)").is_empty());
+            }
+        }
 
-                let res = res.get(0).unwrap();
+        mod slugify {
+            use super::*;
 
-                assert!(res.is_integer());
-                assert_eq!(res.as_integer().unwrap(), 10)
+            fn slug(s: &str) -> String {
+                let n = NodeRef::string(s);
+                let res = Opath::parse("@.slugify()").unwrap().apply(&n, &n).unwrap();
+                res.into_one().unwrap().as_string()
             }
 
             #[test]
-            fn nan() {
-                let n = NodeRef::from_json(r#"{}"#).unwrap();
-                let expr = Opath::parse("parseInt('blaa')").unwrap();
-                let res = expr.apply(&n, &n).unwrap().into_vec();
-
-                assert_eq!(res.len(), 1);
+            fn spaces() {
+                assert_eq!(slug("Hello World"), "hello-world");
+                assert_eq!(slug("  Many   spaces  here "), "many-spaces-here");
+            }
 
-                let res = res.get(0).unwrap();
+            #[test]
+            fn punctuation() {
+                assert_eq!(slug("What's new? (v2.0)"), "what-s-new-v2-0");
+                assert_eq!(slug("--a__b--"), "a-b");
+                assert_eq!(slug("!!!"), "");
+            }
 
-                assert!(res.is_float());
-                assert!(res.as_float().is_nan());
+            #[test]
+            fn accented_characters() {
+                // no transliteration, non-ASCII letters are kept
+                assert_eq!(slug("Café Crème"), "café-crème");
             }
 
             #[test]
-            fn neg() {
-                let n = NodeRef::from_json(r#"{}"#).unwrap();
-                let expr = Opath::parse("parseInt('-10')").unwrap();
-                let res = expr.apply(&n, &n).unwrap().into_vec();
+            fn already_slug() {
+                assert_eq!(slug("already-a-slug-42"), "already-a-slug-42");
+            }
+        }
 
-                assert_eq!(res.len(), 1);
+        mod lines_words {
+            use super::*;
 
-                let res = res.get(0).unwrap();
+            fn apply(s: &str, expr: &str) -> String {
+                let n = NodeRef::string(s);
+                let res = Opath::parse(expr).unwrap().apply(&n, &n).unwrap();
+                res.into_one().unwrap().to_json()
+            }
 
-                assert!(res.is_integer());
-                assert_eq!(res.as_integer().unwrap(), -10);
+            #[test]
+            fn multi_line() {
+                assert_eq!(apply("a\nb c\r\nd", "@.lines()"), r#"["a","b c","d"]"#);
+                assert_eq!(apply("a\n\nb", "@.lines()"), r#"["a","","b"]"#);
             }
 
             #[test]
-            fn num_first() {
-                let n = NodeRef::from_json(r#"{}"#).unwrap();
-                let expr = Opath::parse("parseInt('10ab')").unwrap();
-                let res = expr.apply(&n, &n).unwrap().into_vec();
+            fn trailing_newline() {
+                assert_eq!(apply("a\nb\n", "@.lines()"), r#"["a","b"]"#);
+                assert_eq!(apply("a\r\n", "@.lines()"), r#"["a"]"#);
+                assert_eq!(apply("", "@.lines()"), "[]");
+            }
 
-                assert_eq!(res.len(), 1);
+            #[test]
+            fn words() {
+                assert_eq!(apply("  the quick\tbrown \n fox  ", "@.words()"), r#"["the","quick","brown","fox"]"#);
+                assert_eq!(apply("   ", "@.words()"), "[]");
+            }
+        }
 
-                let res = res.get(0).unwrap();
+        mod depth_from_root {
+            use super::*;
 
-                assert!(res.is_integer());
-                assert_eq!(res.as_integer().unwrap(), 10);
+            #[test]
+            fn nested_nodes() {
+                let n = NodeRef::from_json(r#"{"a": {"b": [{"c": 1}]}}"#).unwrap();
+                let depths: Vec<i64> = ["$", "$.a", "$.a.b", "$.a.b[0]", "$.a.b[0].c"]
+                    .iter()
+                    .map(|p| {
+                        let expr = Opath::parse(&format!("{}.depth_from_root()", p)).unwrap();
+                        let res = expr.apply(&n, &n).unwrap();
+                        res.into_one().unwrap().as_integer().unwrap()
+                    })
+                    .collect();
+                assert_eq!(depths, vec![0, 1, 2, 3, 4]);
             }
 
             #[test]
-            fn mul_tokens() {
-                let n = NodeRef::from_json(r#"{}"#).unwrap();
-                let expr = Opath::parse("parseInt('10 aaa')").unwrap();
-                let res = expr.apply(&n, &n).unwrap().into_vec();
+            fn matches_level_attr() {
+                let n = NodeRef::from_json(r#"{"a": {"b": {"c": true}}}"#).unwrap();
+                let depth = Opath::parse("$.a.b.c.depth_from_root()").unwrap().apply(&n, &n).unwrap();
+                let level = Opath::parse("$.a.b.c.@level").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(depth.into_one().unwrap().as_integer(), Some(3));
+                assert_eq!(level.into_one().unwrap().as_integer(), Some(3));
+            }
+        }
 
-                assert_eq!(res.len(), 1);
+        mod flat_map {
+            use super::*;
 
-                let res = res.get(0).unwrap();
+            static ORDERS_JSON: &str = r#"
+                {
+                    "orders": [
+                        {"id": 1, "items": ["a", "b"]},
+                        {"id": 2, "items": []},
+                        {"id": 3, "items": ["c", ["d", "e"]]}
+                    ]
+                }
+            "#;
 
-                assert!(res.is_integer());
-                assert_eq!(res.as_integer().unwrap(), 10)
+            #[test]
+            fn flattens_nested_arrays() {
+                let n = NodeRef::from_json(ORDERS_JSON).unwrap();
+
+                let res = Opath::parse("$.orders.*.flat_map(@.items.*)").unwrap().apply(&n, &n).unwrap();
+                assert!(res.is_one());
+                assert_eq!(res.into_one().unwrap().to_json(), r#"["a","b","c",["d","e"]]"#);
             }
 
             #[test]
-            fn whitespaces() {
-                let n = NodeRef::from_json(r#"{}"#).unwrap();
-                let expr = Opath::parse("parseInt('  10  ')").unwrap();
-                let res = expr.apply(&n, &n).unwrap().into_vec();
-
-                assert_eq!(res.len(), 1);
+            fn length_is_sum_of_match_counts() {
+                let n = NodeRef::from_json(ORDERS_JSON).unwrap();
+
+                let res = Opath::parse("$.orders.*.flat_map(@.items.*)").unwrap().apply(&n, &n).unwrap();
+                let len = res.into_one().unwrap().child_count();
+                let sum: usize = Opath::parse("$.orders.*")
+                    .unwrap()
+                    .apply(&n, &n)
+                    .unwrap()
+                    .into_vec()
+                    .iter()
+                    .map(|o| Opath::parse("@.items.*").unwrap().apply(&n, o).unwrap().len())
+                    .sum();
+                assert_eq!(len, sum);
+                assert_eq!(len, 4);
+            }
 
-                let res = res.get(0).unwrap();
+            #[test]
+            fn single_results() {
+                let n = NodeRef::from_json(ORDERS_JSON).unwrap();
 
-                assert!(res.is_integer());
-                assert_eq!(res.as_integer().unwrap(), 10)
+                let res = Opath::parse("$.orders.*.flat_map(@.id)").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(res.into_one().unwrap().to_json(), "[1,2,3]");
             }
         }
 
-        mod custom {
+        mod partition {
             use super::*;
 
-            #[test]
-            fn filter_numbers() {
-                fn filter_numbers(
-                    _name: &str,
-                    args: Args,
-                    env: Env<'_>,
-                    out: &mut NodeBuf,
-                ) -> FuncCallResult {
-                    let res = args.resolve_flat(false, env).unwrap();
-                    for r in res.into_iter() {
-                        if r.is_number() {
-                            out.add(r);
-                        }
-                    }
-                    Ok(())
+            static ITEMS_JSON: &str = r#"
+                {
+                    "items": [
+                        {"id": 1, "active": true},
+                        {"id": 2, "active": false},
+                        {"id": 3},
+                        {"id": 4, "active": true}
+                    ],
+                    "empty": []
                 }
+            "#;
 
-                let scope = ScopeMut::new()
-                    .with_func("filter_numbers".into(), box Func::new(filter_numbers));
-                let n = test_node();
-                let expr = Opath::parse("filter_numbers($.**).@path").unwrap();
-                let res = expr.apply_ext(&n, &n, scope.as_ref()).unwrap().into_vec();
+            fn partition(expr: &str) -> String {
+                let n = NodeRef::from_json(ITEMS_JSON).unwrap();
+                let res = Opath::parse(expr).unwrap().apply(&n, &n).unwrap();
+                res.into_one().unwrap().to_json()
+            }
 
-                assert_eq!(res.len(), 3);
-                assert_eq!(res[0].as_string(), "$.one");
-                assert_eq!(res[1].as_string(), "$.nested.two");
-                assert_eq!(res[2].as_string(), "$.nested.four");
+            #[test]
+            fn boolean_field() {
+                assert_eq!(
+                    partition("$.items.*.partition(@.active)"),
+                    r#"[[{"id":1,"active":true},{"id":4,"active":true}],[{"id":2,"active":false},{"id":3}]]"#
+                );
             }
-        }
-    }
 
-    mod method {
-        use super::*;
+            #[test]
+            fn all_matching() {
+                assert_eq!(partition("$.items.*.id.partition(@ > 0)"), "[[1,2,3,4],[]]");
+            }
 
-        mod find {
-            use super::*;
+            #[test]
+            fn empty_input() {
+                assert_eq!(partition("$.empty.*.partition(@.active)"), "[[],[]]");
+            }
 
             #[test]
-            fn nested_prop() {
-                let n = test_node();
+            fn source_not_modified() {
+                let n = NodeRef::from_json(ITEMS_JSON).unwrap();
+                Opath::parse("$.items.*.partition(@.active)").unwrap().apply(&n, &n).unwrap();
+                assert_eq!(n.get_child_key("items").unwrap().child_count(), 4);
+            }
+        }
 
-                let expr = Opath::parse("@.find('nested.two')").unwrap();
+        mod merge_deep {
+            use super::*;
 
-                let res = expr.apply(&n, &n).unwrap().into_vec();
+            static OVERLAY_JSON: &str = r#"
+                {
+                    "base": {
+                        "replicas": 1,
+                        "containers": [
+                            {"name": "app", "image": "app:1.0", "env": [{"name": "MODE", "value": "dev"}]},
+                            {"name": "sidecar", "image": "proxy:1.0"}
+                        ]
+                    },
+                    "overlay": {
+                        "replicas": 3,
+                        "containers": [
+                            {"name": "app", "image": "app:2.0", "env": [{"name": "MODE", "value": "prod"}, {"name": "DEBUG", "value": "0"}]},
+                            {"name": "metrics", "image": "metrics:1.0"}
+                        ]
+                    },
+                    "extra": [{"name": "extra"}],
+                    "unkeyed": [{"image": "app:3.0"}]
+                }
+            "#;
 
-                assert_eq!(res.len(), 1);
-                assert_eq!(Opath::from(&res[0]).to_string(), "$.nested.two");
+            fn merged(expr: &str) -> NodeRef {
+                let n = NodeRef::from_json(OVERLAY_JSON).unwrap();
+                Opath::parse(expr).unwrap().apply_one(&n, &n).unwrap()
             }
 
             #[test]
-            fn wildcard() {
-                let n = test_node();
+            fn overlapping_keys() {
+                let res = merged("$.base.merge_deep($.overlay, 'name')");
+                let expected = NodeRef::from_json(
+                    r#"{
+                        "replicas": 3,
+                        "containers": [
+                            {"name": "app", "image": "app:2.0", "env": [{"name": "MODE", "value": "prod"}, {"name": "DEBUG", "value": "0"}]},
+                            {"name": "sidecar", "image": "proxy:1.0"},
+                            {"name": "metrics", "image": "metrics:1.0"}
+                        ]
+                    }"#,
+                )
+                .unwrap();
+                assert!(res.is_identical_deep(&expected));
+            }
 
-                let expr = Opath::parse("@.find('nested.*')").unwrap();
+            #[test]
+            fn additions_only() {
+                let res = merged("$.base.containers.merge_deep($.extra, 'name')");
+                assert_eq!(res.child_count(), 3);
+                assert_eq!(res.get_child_index(2).unwrap().to_json(), r#"{"name":"extra"}"#);
+            }
 
-                let res = expr.apply(&n, &n).unwrap().into_vec();
+            #[test]
+            fn elements_without_key_are_appended() {
+                let res = merged("$.base.containers.merge_deep($.unkeyed, 'name')");
+                assert_eq!(res.child_count(), 3);
+                assert_eq!(res.get_child_index(0).unwrap().get_child_key("image").unwrap().as_string(), "app:1.0");
+            }
 
-                assert_eq!(res.len(), 3);
-                assert_eq!(Opath::from(&res[0]).to_string(), "$.nested.two");
-                assert_eq!(Opath::from(&res[1]).to_string(), "$.nested.three_string");
-                assert_eq!(Opath::from(&res[2]).to_string(), "$.nested.four");
+            #[test]
+            fn source_not_modified() {
+                let n = NodeRef::from_json(OVERLAY_JSON).unwrap();
+                let base = Opath::parse("$.base").unwrap().apply_one(&n, &n).unwrap().deep_copy();
+                Opath::parse("$.base.merge_deep($.overlay, 'name')").unwrap().apply(&n, &n).unwrap();
+                assert!(n.get_child_key("base").unwrap().is_identical_deep(&base));
             }
         }
 
@@ -31,6 +31,12 @@ pub enum ExprErrorDetail {
     #[display(fmt = "single node expected, got: '{node_set}'")]
     SingleNodeExpected { node_set: String },
 
+    #[display(fmt = "cannot parse selector: '{selector}'")]
+    InvalidSelector { selector: String },
+
+    #[display(fmt = "evaluation budget of {limit} steps exceeded")]
+    EvaluationBudgetExceeded { limit: usize },
+
     // This variant should probably be placed in resolve.rs module
     #[display(fmt = "too many iterations while resolving interpolations: '{depth}'")]
     InterpolationDepthReached { depth: usize },
@@ -81,6 +87,11 @@ impl NodeSet {
         }
     }
 
+    /// Returns `true` if any node in the set is truthy; an empty set is `false`.
+    pub fn is_truthy(&self) -> bool {
+        self.iter().any(|n| n.as_boolean())
+    }
+
     pub fn into_vec(self) -> Vec<NodeRef> {
         match self {
             NodeSet::Empty => Vec::new(),
@@ -365,21 +376,120 @@ impl<'a> DiffEnv<'a> {
     }
 }
 
+/// Limits the amount of work a single evaluation may perform. Every evaluated expression
+/// and every node visited by a descendants (`**`) query consumes one step.
+#[derive(Debug)]
+pub struct EvalBudget {
+    limit: usize,
+    used: std::cell::Cell<usize>,
+}
+
+impl EvalBudget {
+    pub fn new(limit: usize) -> EvalBudget {
+        EvalBudget {
+            limit,
+            used: std::cell::Cell::new(0),
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    pub fn used(&self) -> usize {
+        self.used.get()
+    }
+
+    fn consume(&self, steps: usize) -> ExprResult<()> {
+        let used = self.used.get().saturating_add(steps);
+        self.used.set(used);
+        if used > self.limit {
+            Err(ExprErrorDetail::EvaluationBudgetExceeded { limit: self.limit }).into_diag_res()
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Single step recorded by [`Opath::apply_traced`](crate::opath::Opath::apply_traced).
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEntry {
+    expr: String,
+    depth: usize,
+    count: usize,
+}
+
+impl TraceEntry {
+    /// Evaluated sub-expression.
+    pub fn expr(&self) -> &str {
+        &self.expr
+    }
+
+    /// Nesting level of the sub-expression, `0` for the whole expression.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Number of nodes produced by the sub-expression.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct EvalTrace {
+    entries: std::cell::RefCell<Vec<TraceEntry>>,
+    depth: std::cell::Cell<usize>,
+}
+
+impl EvalTrace {
+    pub(crate) fn new() -> EvalTrace {
+        EvalTrace::default()
+    }
+
+    fn begin(&self, expr: &Expr) -> usize {
+        let depth = self.depth.get();
+        self.depth.set(depth + 1);
+        let mut entries = self.entries.borrow_mut();
+        entries.push(TraceEntry {
+            expr: expr.to_string(),
+            depth,
+            count: 0,
+        });
+        entries.len() - 1
+    }
+
+    fn end(&self, index: usize, count: usize) {
+        self.depth.set(self.depth.get() - 1);
+        self.entries.borrow_mut()[index].count = count;
+    }
+
+    pub(crate) fn into_entries(self) -> Vec<TraceEntry> {
+        self.entries.into_inner()
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Env<'a> {
     current: &'a NodeRef,
+    origin: &'a NodeRef,
     root: &'a NodeRef,
     scope: Option<&'a Scope>,
     diff: Option<DiffEnv<'a>>,
+    budget: Option<&'a EvalBudget>,
+    trace: Option<&'a EvalTrace>,
 }
 
 impl<'a> Env<'a> {
     pub fn new(root: &'a NodeRef, current: &'a NodeRef, scope: Option<&'a Scope>) -> Env<'a> {
         Env {
             current,
+            origin: current,
             root,
             scope,
             diff: None,
+            budget: None,
+            trace: None,
         }
     }
 
@@ -399,6 +509,14 @@ impl<'a> Env<'a> {
         Env { diff: Some(DiffEnv::new(old_root, diff)), ..*self }
     }
 
+    pub fn with_budget(&'a self, budget: &'a EvalBudget) -> Env<'a> {
+        Env { budget: Some(budget), ..*self }
+    }
+
+    pub(crate) fn with_trace(&'a self, trace: &'a EvalTrace) -> Env<'a> {
+        Env { trace: Some(trace), ..*self }
+    }
+
     pub fn current(&self) -> &NodeRef {
         self.current
     }
@@ -407,6 +525,12 @@ impl<'a> Env<'a> {
         self.root
     }
 
+    /// Current node the whole expression was evaluated for. Unlike `current()`, it does
+    /// not change while evaluating sub-expressions.
+    pub fn origin(&self) -> &NodeRef {
+        self.origin
+    }
+
     pub fn scope(&self) -> Option<&Scope> {
         self.scope
     }
@@ -414,6 +538,17 @@ impl<'a> Env<'a> {
     pub fn diff(&self) -> Option<DiffEnv> {
         self.diff
     }
+
+    pub fn budget(&self) -> Option<&EvalBudget> {
+        self.budget
+    }
+
+    fn consume(&self, steps: usize) -> ExprResult<()> {
+        match self.budget {
+            Some(b) => b.consume(steps),
+            None => Ok(()),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -522,12 +657,15 @@ impl Id {
         let s = s.into();
         debug_assert!(!s.is_empty());
 
-        let mut kind = IdKind::Plain;
+        let mut kind = match s.as_str() {
+            "true" | "false" | "null" | "and" | "or" | "not" => IdKind::Quoted,
+            _ => IdKind::Plain,
+        };
         let mut first = true;
         for c in s.chars() {
             if first {
                 first = false;
-                if c.is_digit(10) {
+                if !c.is_alphabetic() && c != '_' {
                     kind = IdKind::Quoted;
                 }
             }
@@ -552,12 +690,30 @@ impl std::fmt::Display for Id {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self.kind {
             IdKind::Plain => write!(f, "{}", self.name),
-            IdKind::Quoted => write!(f, "\"{}\"", self.name),
-            IdKind::Encoded => write!(f, "{}", self.name.escape_debug()),
+            IdKind::Quoted | IdKind::Encoded => write_quoted(f, &self.name),
         }
     }
 }
 
+/// Writes `s` as a double-quoted string literal, escaping only the sequences
+/// recognized by the opath parser.
+fn write_quoted(f: &mut std::fmt::Formatter<'_>, s: &str) -> std::fmt::Result {
+    use std::fmt::Write;
+
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '\\' => f.write_str("\\\\")?,
+            '"' => f.write_str("\\\"")?,
+            '\t' => f.write_str("\\t")?,
+            '\r' => f.write_str("\\r")?,
+            '\n' => f.write_str("\\n")?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
 impl Deref for Id {
     type Target = str;
 
@@ -575,6 +731,8 @@ pub(crate) enum Attr {
     Index,
     #[display(fmt = "@level")]
     Level,
+    #[display(fmt = "@depth")]
+    Depth,
     #[display(fmt = "@type")]
     Type,
     #[display(fmt = "@kind")]
@@ -615,6 +773,7 @@ impl FromStr for Attr {
             "@key" => Attr::Key,
             "@index" => Attr::Index,
             "@level" => Attr::Level,
+            "@depth" => Attr::Depth,
             "@type" => Attr::Type,
             "@kind" => Attr::Kind,
             "@file" => Attr::File,
@@ -641,6 +800,15 @@ pub(crate) enum PathSegment {
     Index(usize),
 }
 
+impl PathSegment {
+    pub fn to_expr(&self) -> Expr {
+        match *self {
+            PathSegment::Key(ref id) => Expr::Property(Box::new(id.clone())),
+            PathSegment::Index(index) => Expr::Index(index as i64),
+        }
+    }
+}
+
 impl std::fmt::Display for PathSegment {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match *self {
@@ -670,9 +838,15 @@ pub(crate) enum Expr {
     Sub(Box<Expr>, Box<Expr>),
     Mul(Box<Expr>, Box<Expr>),
     Div(Box<Expr>, Box<Expr>),
+    BitAnd(Box<Expr>, Box<Expr>),
+    BitOr(Box<Expr>, Box<Expr>),
+    BitXor(Box<Expr>, Box<Expr>),
+    Shl(Box<Expr>, Box<Expr>),
+    Shr(Box<Expr>, Box<Expr>),
     Not(Box<Expr>),
     And(Box<Expr>, Box<Expr>),
     Or(Box<Expr>, Box<Expr>),
+    Coalesce(Box<Expr>, Box<Expr>),
     StartsWith(Box<Expr>, Box<Expr>),
     EndsWith(Box<Expr>, Box<Expr>),
     Contains(Box<Expr>, Box<Expr>),
@@ -685,6 +859,8 @@ pub(crate) enum Expr {
     Root,
     Current,
     Parent,
+    /// Passes current node through unless it is null, used for `?.` safe navigation
+    Guard,
     All,
     Ancestors(Box<LevelRange>),
     Descendants(Box<LevelRange>),
@@ -704,28 +880,43 @@ pub(crate) enum Expr {
     EnvExpr(Box<Expr>),
 }
 
+fn to_abs_index(index: i64, len: usize) -> usize {
+    if index < 0 {
+        let index = len as i64 + index;
+        if index >= 0 {
+            index as usize
+        } else {
+            len
+        }
+    } else {
+        index as usize
+    }
+}
+
 impl Expr {
     fn tag(&self) -> u8 {
         unsafe { *std::mem::transmute::<&Expr, &u8>(self) }
     }
 
     fn apply_to(&self, env: Env<'_>, ctx: Context, out: &mut NodeBuf) -> ExprResult<()> {
-        use std::{f64, i64};
+        env.consume(1)?;
 
-        #[inline]
-        fn to_abs_index(index: i64, len: usize) -> usize {
-            if index < 0 {
-                let index = len as i64 + index;
-                if index >= 0 {
-                    index as usize
-                } else {
-                    len
-                }
-            } else {
-                index as usize
+        match env.trace {
+            Some(trace) => {
+                let index = trace.begin(self);
+                let len = out.elems.len();
+                let res = self.eval_to(env, ctx, out);
+                trace.end(index, out.elems.len().saturating_sub(len));
+                res
             }
+            None => self.eval_to(env, ctx, out),
         }
+    }
+
+    fn eval_to(&self, env: Env<'_>, ctx: Context, out: &mut NodeBuf) -> ExprResult<()> {
+        use std::{f64, i64};
 
+        #[inline]
         fn get_child_all(current: &NodeRef, out: &mut NodeBuf) {
             match *current.data().value() {
                 Value::Array(ref elems) => out.append(elems.iter()),
@@ -776,11 +967,29 @@ impl Expr {
             }
         }
 
-        fn get_attr(current: &NodeRef, attr: Attr, out: &mut NodeBuf) {
+        /// `@depth` is the number of parent steps from `current` up to `origin` (the current
+        /// node of the evaluated expression). If `origin` is not an ancestor of `current`, or is
+        /// unknown, it is counted up to the tree root, same as `@level`.
+        fn get_attr(current: &NodeRef, origin: Option<&NodeRef>, attr: Attr, out: &mut NodeBuf) {
             match attr {
                 Attr::Key => out.add(NodeRef::string(current.data().key())),
                 Attr::Index => out.add(NodeRef::integer(current.data().index() as i64)),
                 Attr::Level => out.add(NodeRef::integer(current.data().level() as i64)),
+                Attr::Depth => {
+                    let mut n = current.clone();
+                    let mut depth = 0;
+                    while origin.map_or(true, |o| !n.is_ref_eq(o)) {
+                        let p = n.data().parent();
+                        match p {
+                            Some(p) => {
+                                n = p;
+                                depth += 1;
+                            }
+                            None => break,
+                        }
+                    }
+                    out.add(NodeRef::integer(depth));
+                }
                 Attr::Type => out.add(NodeRef::string(current.data().kind().as_type_str())),
                 Attr::Kind => out.add(NodeRef::string(current.data().kind().as_str())),
                 Attr::File => out.add(NodeRef::string(current.data().file_string())),
@@ -806,10 +1015,11 @@ impl Expr {
             }
         }
 
-        fn get_prop(current: &NodeRef, id: &str, out: &mut NodeBuf) {
+        fn get_prop(env: Env<'_>, id: &str, out: &mut NodeBuf) {
+            let current = env.current();
             if id.starts_with('@') {
                 if let Ok(attr) = Attr::from_str(id) {
-                    get_attr(current, attr, out);
+                    get_attr(current, Some(env.origin()), attr, out);
                     return;
                 }
             }
@@ -817,13 +1027,15 @@ impl Expr {
         }
 
         fn add_descendants(
+            env: Env<'_>,
             current: &NodeRef,
             level: i64,
             level_min: i64,
             level_max: i64,
             out: &mut NodeBuf,
-        ) {
+        ) -> ExprResult<()> {
             if level <= level_max {
+                env.consume(1)?;
                 if level >= level_min {
                     out.add(current.clone());
                 }
@@ -831,18 +1043,19 @@ impl Expr {
                     match *current.data().value() {
                         Value::Array(ref elems) => {
                             for e in elems.iter() {
-                                add_descendants(e, level + 1, level_min, level_max, out);
+                                add_descendants(env, e, level + 1, level_min, level_max, out)?;
                             }
                         }
                         Value::Object(ref props) => {
                             for e in props.values() {
-                                add_descendants(e, level + 1, level_min, level_max, out);
+                                add_descendants(env, e, level + 1, level_min, level_max, out)?;
                             }
                         }
                         _ => {}
                     }
                 }
             }
+            Ok(())
         }
 
         #[inline]
@@ -983,17 +1196,17 @@ impl Expr {
                     NodeSet::Empty => {
                         let nb = b.apply(env, Context::Expr)?;
                         for b in nb.into_iter() {
-                            apply_node(env.current(), ctx, b, out)?;
+                            apply_node(env, ctx, b, out)?;
                         }
                         Ok(())
                     }
                     NodeSet::One(a) => {
                         if a.as_boolean() {
-                            apply_node(env.current(), ctx, a, out)
+                            apply_node(env, ctx, a, out)
                         } else {
                             let nb = b.apply(env, Context::Expr)?;
                             for b in nb.into_iter() {
-                                apply_node(env.current(), ctx, b, out)?;
+                                apply_node(env, ctx, b, out)?;
                             }
                             Ok(())
                         }
@@ -1003,16 +1216,16 @@ impl Expr {
                         match nb {
                             NodeSet::Empty => {
                                 for a in a.into_iter() {
-                                    apply_node(env.current(), ctx, a, out)?;
+                                    apply_node(env, ctx, a, out)?;
                                 }
                                 Ok(())
                             }
                             NodeSet::One(b) => {
                                 for a in a.into_iter() {
                                     if a.as_boolean() {
-                                        apply_node(env.current(), ctx, a, out)?;
+                                        apply_node(env, ctx, a, out)?;
                                     } else {
-                                        apply_node(env.current(), ctx, b.clone(), out)?;
+                                        apply_node(env, ctx, b.clone(), out)?;
                                     }
                                 }
                                 Ok(())
@@ -1020,9 +1233,9 @@ impl Expr {
                             NodeSet::Many(b) => {
                                 for (a, b) in a.into_iter().zip(b.into_iter()) {
                                     if a.as_boolean() {
-                                        apply_node(env.current(), ctx, a, out)?;
+                                        apply_node(env, ctx, a, out)?;
                                     } else {
-                                        apply_node(env.current(), ctx, b, out)?;
+                                        apply_node(env, ctx, b, out)?;
                                     }
                                 }
                                 Ok(())
@@ -1053,6 +1266,31 @@ impl Expr {
             }
         }
 
+        #[inline]
+        fn coalesce_op(
+            env: Env<'_>,
+            ctx: Context,
+            a: &Expr,
+            b: &Expr,
+            out: &mut NodeBuf,
+        ) -> ApplyResult {
+            let na = a.apply(env, Context::Expr)?;
+            let missing = match na {
+                NodeSet::Empty => true,
+                NodeSet::One(ref a) => a.is_null(),
+                NodeSet::Many(_) => false,
+            };
+            let n = if missing {
+                b.apply(env, Context::Expr)?
+            } else {
+                na
+            };
+            for n in n.into_iter() {
+                apply_node(env, ctx, n, out)?;
+            }
+            Ok(())
+        }
+
         #[inline]
         fn bool_not_op(env: Env<'_>, ctx: Context, a: &Expr, out: &mut NodeBuf) -> ApplyResult {
             #[inline]
@@ -1093,13 +1331,13 @@ impl Expr {
 
         #[inline]
         fn apply_string(
-            current: &NodeRef,
+            env: Env<'_>,
             ctx: Context,
             s: Cow<str>,
             out: &mut NodeBuf,
         ) -> ApplyResult {
             match ctx {
-                Context::Property | Context::Index => get_prop(current, &s, out),
+                Context::Property | Context::Index => get_prop(env, &s, out),
                 _ => out.add(NodeRef::string(s)),
             };
             Ok(())
@@ -1157,11 +1395,12 @@ impl Expr {
 
         #[inline]
         fn apply_node(
-            current: &NodeRef,
+            env: Env<'_>,
             ctx: Context,
             n: NodeRef,
             out: &mut NodeBuf,
         ) -> ApplyResult {
+            let current = env.current();
             match ctx {
                 Context::Property | Context::Index => match *n.data().value() {
                     Value::Null => {}
@@ -1172,7 +1411,7 @@ impl Expr {
                     }
                     Value::Integer(n) => get_child_index(current, n, out),
                     Value::Float(n) => get_child_index(current, n as i64, out),
-                    Value::String(ref s) => get_prop(current, s, out),
+                    Value::String(ref s) => get_prop(env, s, out),
                     Value::Binary(_) | Value::Array(_) | Value::Object(_) => {
                         if n.as_boolean() {
                             out.add(current.clone());
@@ -1186,12 +1425,13 @@ impl Expr {
 
         #[inline]
         fn add(
-            current: &NodeRef,
+            env: Env<'_>,
             ctx: Context,
             a: &NodeRef,
             b: &NodeRef,
             out: &mut NodeBuf,
         ) -> ApplyResult {
+            let current = env.current();
             let a = a.data();
             let b = b.data();
             match (a.value(), b.value()) {
@@ -1208,28 +1448,28 @@ impl Expr {
                     let mut s = String::with_capacity(a.len() + b.len());
                     s.push_str(a.as_ref());
                     s.push_str(b.as_ref());
-                    apply_string(current, ctx, s.into(), out)
+                    apply_string(env, ctx, s.into(), out)
                 }
                 (&Value::Object(_), _) => apply_float(current, ctx, 0f64 + b.as_float(), out),
                 (&Value::String(ref a), &Value::String(ref b)) => {
                     let mut s = String::with_capacity(a.len() + b.len());
                     s.push_str(a);
                     s.push_str(b);
-                    apply_string(current, ctx, s.into(), out)
+                    apply_string(env, ctx, s.into(), out)
                 }
                 (&Value::String(ref a), _) => {
                     let b = &b.as_string();
                     let mut s = String::with_capacity(a.len() + b.len());
                     s.push_str(a);
                     s.push_str(b);
-                    apply_string(current, ctx, s.into(), out)
+                    apply_string(env, ctx, s.into(), out)
                 }
                 (_, &Value::String(ref b)) => {
                     let a = &a.as_string();
                     let mut s = String::with_capacity(a.len() + b.len());
                     s.push_str(a);
                     s.push_str(b);
-                    apply_string(current, ctx, s.into(), out)
+                    apply_string(env, ctx, s.into(), out)
                 }
                 (&Value::Integer(a), &Value::Integer(b)) => match a.checked_add(b) {
                     Some(res) => apply_integer(current, ctx, res, out),
@@ -1374,6 +1614,99 @@ impl Expr {
             }
         }
 
+        #[inline]
+        fn integer_op<F>(
+            current: &NodeRef,
+            ctx: Context,
+            a: &NodeRef,
+            b: &NodeRef,
+            op: F,
+            out: &mut NodeBuf,
+        ) -> ApplyResult
+        where
+            F: Fn(i64, i64) -> Option<i64>,
+        {
+            let a = a.data().as_integer();
+            let b = b.data().as_integer();
+            match (a, b) {
+                (Some(a), Some(b)) => match op(a, b) {
+                    Some(res) => apply_integer(current, ctx, res, out),
+                    None => apply_float(current, ctx, f64::NAN, out),
+                },
+                _ => apply_float(current, ctx, f64::NAN, out),
+            }
+        }
+
+        #[inline]
+        fn bit_and(
+            current: &NodeRef,
+            ctx: Context,
+            a: &NodeRef,
+            b: &NodeRef,
+            out: &mut NodeBuf,
+        ) -> ApplyResult {
+            integer_op(current, ctx, a, b, |a, b| Some(a & b), out)
+        }
+
+        #[inline]
+        fn bit_or(
+            current: &NodeRef,
+            ctx: Context,
+            a: &NodeRef,
+            b: &NodeRef,
+            out: &mut NodeBuf,
+        ) -> ApplyResult {
+            integer_op(current, ctx, a, b, |a, b| Some(a | b), out)
+        }
+
+        #[inline]
+        fn bit_xor(
+            current: &NodeRef,
+            ctx: Context,
+            a: &NodeRef,
+            b: &NodeRef,
+            out: &mut NodeBuf,
+        ) -> ApplyResult {
+            integer_op(current, ctx, a, b, |a, b| Some(a ^ b), out)
+        }
+
+        // shifting by 64 bits or more shifts out all bits, negative shift amounts yield NaN
+        #[inline]
+        fn shl(
+            current: &NodeRef,
+            ctx: Context,
+            a: &NodeRef,
+            b: &NodeRef,
+            out: &mut NodeBuf,
+        ) -> ApplyResult {
+            integer_op(current, ctx, a, b, |a, b| {
+                if b < 0 {
+                    None
+                } else if b >= 64 {
+                    Some(0)
+                } else {
+                    Some(a << b)
+                }
+            }, out)
+        }
+
+        #[inline]
+        fn shr(
+            current: &NodeRef,
+            ctx: Context,
+            a: &NodeRef,
+            b: &NodeRef,
+            out: &mut NodeBuf,
+        ) -> ApplyResult {
+            integer_op(current, ctx, a, b, |a, b| {
+                if b < 0 {
+                    None
+                } else {
+                    Some(a >> b.min(63))
+                }
+            }, out)
+        }
+
         match *self {
             Expr::Path(ref segments) => {
                 let mut n = env.root().clone();
@@ -1398,7 +1731,7 @@ impl Expr {
                 out.add(n);
                 Ok(())
             }
-            Expr::String(ref s) => apply_string(env.current(), ctx, s.as_str().into(), out),
+            Expr::String(ref s) => apply_string(env, ctx, s.as_str().into(), out),
             Expr::Integer(n) => apply_integer(env.current(), ctx, n, out),
             Expr::Float(n) => apply_float(env.current(), ctx, n, out),
             Expr::Boolean(b) => apply_boolean(env.current(), ctx, b, out),
@@ -1424,15 +1757,24 @@ impl Expr {
                 }
                 Ok(())
             }
-            Expr::Add(ref a, ref b) => math_binary_op(env, ctx, a, b, add, out),
+            Expr::Add(ref a, ref b) => {
+                // `add` may produce a string property name, resolved relative to `env`
+                math_binary_op(env, ctx, a, b, |_, ctx, a, b, out| add(env, ctx, a, b, out), out)
+            }
             Expr::Sub(ref a, ref b) => math_binary_op(env, ctx, a, b, sub, out),
             Expr::Mul(ref a, ref b) => math_binary_op(env, ctx, a, b, mul, out),
             Expr::Div(ref a, ref b) => math_binary_op(env, ctx, a, b, div, out),
+            Expr::BitAnd(ref a, ref b) => math_binary_op(env, ctx, a, b, bit_and, out),
+            Expr::BitOr(ref a, ref b) => math_binary_op(env, ctx, a, b, bit_or, out),
+            Expr::BitXor(ref a, ref b) => math_binary_op(env, ctx, a, b, bit_xor, out),
+            Expr::Shl(ref a, ref b) => math_binary_op(env, ctx, a, b, shl, out),
+            Expr::Shr(ref a, ref b) => math_binary_op(env, ctx, a, b, shr, out),
             Expr::Not(ref a) => bool_not_op(env, ctx, a, out),
             Expr::And(ref a, ref b) => {
                 bool_binary_op(env, ctx, a, b, |a, b| a.as_boolean() && b.as_boolean(), out)
             }
             Expr::Or(ref a, ref b) => bool_or_op(env, ctx, a, b, out),
+            Expr::Coalesce(ref a, ref b) => coalesce_op(env, ctx, a, b, out),
             Expr::Eq(ref a, ref b) => bool_binary_op(env, ctx, a, b, |a, b| a == b, out),
             Expr::Ne(ref a, ref b) => bool_binary_op(env, ctx, a, b, |a, b| a != b, out),
             Expr::Lt(ref a, ref b) => bool_binary_op(env, ctx, a, b, |a, b| a < b, out),
@@ -1492,6 +1834,12 @@ impl Expr {
                 }
                 Ok(())
             }
+            Expr::Guard => {
+                if !env.current().data().is_null() {
+                    out.add(env.current().clone());
+                }
+                Ok(())
+            }
             Expr::Ancestors(ref r) => {
                 out.multiple = true;
                 let nmin = r.min.apply_one(env, Context::Expr)?;
@@ -1527,7 +1875,7 @@ impl Expr {
                 let min = nmin.data().as_integer().unwrap_or(1);
                 let max = nmax.data().as_integer().unwrap_or(i64::MAX);
                 if min >= 0 && max >= min {
-                    add_descendants(env.current(), 0, min, max, out);
+                    add_descendants(env, env.current(), 0, min, max, out)?;
                 }
                 Ok(())
             }
@@ -1537,7 +1885,7 @@ impl Expr {
                 Ok(())
             }
             Expr::Attribute(attr) => {
-                get_attr(env.current(), attr, out);
+                get_attr(env.current(), Some(env.origin()), attr, out);
                 Ok(())
             },
             Expr::Property(ref id) => {
@@ -1631,15 +1979,22 @@ impl Expr {
 
                 for e in elems.iter() {
                     out2.clear();
-                    out2.merge_multiple(out1.multiple);
-                    for n in out1.elems.iter() {
-                        e.apply_to(env.with_current(n), Context::Expr, &mut out2)?;
+                    match *e {
+                        Expr::MethodCall(ref call) if call.id().is_set_method() => {
+                            func::apply_set_method_to(call.id(), call.args(), env, &out1.elems, &mut out2)?;
+                        }
+                        _ => {
+                            out2.merge_multiple(out1.multiple);
+                            for n in out1.elems.iter() {
+                                e.apply_to(env.with_current(n), Context::Expr, &mut out2)?;
+                            }
+                        }
                     }
                     std::mem::swap(&mut out1, &mut out2);
                 }
                 if ctx == Context::Index {
                     for n in out1.elems {
-                        apply_node(env.current(), ctx, n, out)?;
+                        apply_node(env, ctx, n, out)?;
                     }
                     Ok(())
                 } else {
@@ -1726,6 +2081,277 @@ impl Expr {
             }
         }
     }
+
+    pub(super) fn visit<F: FnMut(&Expr)>(&self, f: &mut F) {
+        f(self);
+        match *self {
+            Expr::Concat(ref elems) | Expr::Group(ref elems) | Expr::Sequence(ref elems) => {
+                for e in elems.iter() {
+                    e.visit(f);
+                }
+            }
+            Expr::Neg(ref a)
+            | Expr::Not(ref a)
+            | Expr::PropertyExpr(ref a)
+            | Expr::IndexExpr(ref a)
+            | Expr::VarExpr(ref a)
+            | Expr::EnvExpr(ref a) => a.visit(f),
+            Expr::Add(ref a, ref b)
+            | Expr::Sub(ref a, ref b)
+            | Expr::Mul(ref a, ref b)
+            | Expr::Div(ref a, ref b)
+            | Expr::BitAnd(ref a, ref b)
+            | Expr::BitOr(ref a, ref b)
+            | Expr::BitXor(ref a, ref b)
+            | Expr::Shl(ref a, ref b)
+            | Expr::Shr(ref a, ref b)
+            | Expr::And(ref a, ref b)
+            | Expr::Or(ref a, ref b)
+            | Expr::Coalesce(ref a, ref b)
+            | Expr::StartsWith(ref a, ref b)
+            | Expr::EndsWith(ref a, ref b)
+            | Expr::Contains(ref a, ref b)
+            | Expr::Eq(ref a, ref b)
+            | Expr::Ne(ref a, ref b)
+            | Expr::Gt(ref a, ref b)
+            | Expr::Ge(ref a, ref b)
+            | Expr::Lt(ref a, ref b)
+            | Expr::Le(ref a, ref b) => {
+                a.visit(f);
+                b.visit(f);
+            }
+            Expr::Ancestors(ref r) | Expr::Descendants(ref r) => {
+                r.min.visit(f);
+                r.max.visit(f);
+            }
+            Expr::Range(ref r) => {
+                for e in [&r.start, &r.step, &r.stop].iter() {
+                    if let Some(ref e) = **e {
+                        e.visit(f);
+                    }
+                }
+            }
+            Expr::MethodCall(ref call) => {
+                for e in call.args.iter() {
+                    e.visit(f);
+                }
+            }
+            Expr::FuncCall(ref call) => {
+                for e in call.args.iter() {
+                    e.visit(f);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn is_literal(&self) -> bool {
+        match *self {
+            Expr::String(_) | Expr::Integer(_) | Expr::Float(_) | Expr::Boolean(_) | Expr::Null => true,
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if this expression always yields integers (or `NaN` for invalid operands),
+    /// and never an empty set.
+    fn is_integer_valued(&self) -> bool {
+        match *self {
+            Expr::Integer(_)
+            | Expr::BitAnd(..)
+            | Expr::BitOr(..)
+            | Expr::BitXor(..)
+            | Expr::Shl(..)
+            | Expr::Shr(..) => true,
+            Expr::Add(ref a, ref b) | Expr::Sub(ref a, ref b) | Expr::Mul(ref a, ref b) => {
+                a.is_integer_valued() && b.is_integer_valued()
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if this expression always yields booleans, and never an empty set.
+    fn is_boolean_valued(&self) -> bool {
+        match *self {
+            Expr::Boolean(_)
+            | Expr::Not(..)
+            | Expr::And(..)
+            | Expr::StartsWith(..)
+            | Expr::EndsWith(..)
+            | Expr::Contains(..)
+            | Expr::Eq(..)
+            | Expr::Ne(..)
+            | Expr::Gt(..)
+            | Expr::Ge(..)
+            | Expr::Lt(..)
+            | Expr::Le(..) => true,
+            _ => false,
+        }
+    }
+
+    pub(super) fn simplify(self) -> Expr {
+        self.simplify_ctx(Context::Expr)
+    }
+
+    fn simplify_ctx(self, ctx: Context) -> Expr {
+        fn simplify_box(e: Box<Expr>) -> Box<Expr> {
+            Box::new(e.simplify_ctx(Context::Expr))
+        }
+
+        fn simplify_vec(elems: Vec<Expr>, ctx: Context) -> Vec<Expr> {
+            elems.into_iter().map(|e| e.simplify_ctx(ctx)).collect()
+        }
+
+        fn simplify_opt(e: Option<Expr>) -> Option<Expr> {
+            e.map(|e| e.simplify_ctx(Context::Expr))
+        }
+
+        let e = match self {
+            Expr::Concat(elems) => Expr::Concat(simplify_vec(elems, ctx)),
+            Expr::Neg(a) => Expr::Neg(simplify_box(a)),
+            Expr::Add(a, b) => Expr::Add(simplify_box(a), simplify_box(b)),
+            Expr::Sub(a, b) => Expr::Sub(simplify_box(a), simplify_box(b)),
+            Expr::Mul(a, b) => Expr::Mul(simplify_box(a), simplify_box(b)),
+            Expr::Div(a, b) => Expr::Div(simplify_box(a), simplify_box(b)),
+            Expr::BitAnd(a, b) => Expr::BitAnd(simplify_box(a), simplify_box(b)),
+            Expr::BitOr(a, b) => Expr::BitOr(simplify_box(a), simplify_box(b)),
+            Expr::BitXor(a, b) => Expr::BitXor(simplify_box(a), simplify_box(b)),
+            Expr::Shl(a, b) => Expr::Shl(simplify_box(a), simplify_box(b)),
+            Expr::Shr(a, b) => Expr::Shr(simplify_box(a), simplify_box(b)),
+            Expr::Not(a) => Expr::Not(simplify_box(a)),
+            Expr::And(a, b) => Expr::And(simplify_box(a), simplify_box(b)),
+            Expr::Or(a, b) => Expr::Or(simplify_box(a), simplify_box(b)),
+            Expr::Coalesce(a, b) => Expr::Coalesce(simplify_box(a), simplify_box(b)),
+            Expr::StartsWith(a, b) => Expr::StartsWith(simplify_box(a), simplify_box(b)),
+            Expr::EndsWith(a, b) => Expr::EndsWith(simplify_box(a), simplify_box(b)),
+            Expr::Contains(a, b) => Expr::Contains(simplify_box(a), simplify_box(b)),
+            Expr::Eq(a, b) => Expr::Eq(simplify_box(a), simplify_box(b)),
+            Expr::Ne(a, b) => Expr::Ne(simplify_box(a), simplify_box(b)),
+            Expr::Gt(a, b) => Expr::Gt(simplify_box(a), simplify_box(b)),
+            Expr::Ge(a, b) => Expr::Ge(simplify_box(a), simplify_box(b)),
+            Expr::Lt(a, b) => Expr::Lt(simplify_box(a), simplify_box(b)),
+            Expr::Le(a, b) => Expr::Le(simplify_box(a), simplify_box(b)),
+            Expr::Ancestors(r) => Expr::Ancestors(Box::new(LevelRange {
+                min: r.min.simplify_ctx(Context::Expr),
+                max: r.max.simplify_ctx(Context::Expr),
+            })),
+            Expr::Descendants(r) => Expr::Descendants(Box::new(LevelRange {
+                min: r.min.simplify_ctx(Context::Expr),
+                max: r.max.simplify_ctx(Context::Expr),
+            })),
+            Expr::PropertyExpr(e) => Expr::PropertyExpr(Box::new(e.simplify_ctx(Context::Property))),
+            Expr::IndexExpr(e) => Expr::IndexExpr(Box::new(e.simplify_ctx(Context::Index))),
+            Expr::Range(r) => Expr::Range(Box::new(NumberRange {
+                start: simplify_opt(r.start),
+                step: simplify_opt(r.step),
+                stop: simplify_opt(r.stop),
+            })),
+            Expr::Group(elems) => Expr::Group(simplify_vec(elems, ctx)),
+            Expr::Sequence(elems) => Expr::Sequence(simplify_vec(elems, Context::Expr)),
+            Expr::MethodCall(call) => {
+                let MethodCall { id, args } = *call;
+                Expr::MethodCall(Box::new(MethodCall::new(id, simplify_vec(args, Context::Expr))))
+            }
+            Expr::FuncCall(call) => {
+                let FuncCall { id, args } = *call;
+                Expr::FuncCall(Box::new(FuncCall::new(id, simplify_vec(args, Context::Expr))))
+            }
+            e => e,
+        };
+
+        // arithmetic yields the same result when folded in any context, while boolean operators
+        // and concatenation behave differently in property and index contexts
+        let foldable = match e {
+            Expr::Neg(ref a) => a.is_literal(),
+            Expr::Add(ref a, ref b)
+            | Expr::Sub(ref a, ref b)
+            | Expr::Mul(ref a, ref b)
+            | Expr::Div(ref a, ref b)
+            | Expr::BitAnd(ref a, ref b)
+            | Expr::BitOr(ref a, ref b)
+            | Expr::BitXor(ref a, ref b)
+            | Expr::Shl(ref a, ref b)
+            | Expr::Shr(ref a, ref b) => a.is_literal() && b.is_literal(),
+            Expr::Not(ref a) => ctx == Context::Expr && a.is_literal(),
+            Expr::And(ref a, ref b)
+            | Expr::Or(ref a, ref b)
+            | Expr::StartsWith(ref a, ref b)
+            | Expr::EndsWith(ref a, ref b)
+            | Expr::Contains(ref a, ref b)
+            | Expr::Eq(ref a, ref b)
+            | Expr::Ne(ref a, ref b)
+            | Expr::Gt(ref a, ref b)
+            | Expr::Ge(ref a, ref b)
+            | Expr::Lt(ref a, ref b)
+            | Expr::Le(ref a, ref b) => ctx == Context::Expr && a.is_literal() && b.is_literal(),
+            Expr::Concat(ref elems) => ctx == Context::Expr && elems.iter().all(|e| e.is_literal()),
+            _ => false,
+        };
+
+        if foldable {
+            let n = NodeRef::null();
+            if let Ok(NodeSet::One(res)) = e.apply(Env::new(&n, &n, None), Context::Expr) {
+                let folded = match *res.data().value() {
+                    Value::Null => Some(Expr::Null),
+                    Value::Boolean(b) => Some(Expr::Boolean(b)),
+                    Value::Integer(n) => Some(Expr::Integer(n)),
+                    Value::Float(n) if n.is_finite() => Some(Expr::Float(n)),
+                    Value::String(ref s) => Some(Expr::String(s.clone())),
+                    _ => None,
+                };
+                if let Some(folded) = folded {
+                    return folded;
+                }
+            }
+        }
+
+        // identities are collapsed only if the remaining operand is known to yield integers
+        // or booleans, otherwise e.g. `$.a + 0` would turn a string into a number
+        if ctx == Context::Expr {
+            let collapsed = match e {
+                Expr::Add(ref a, ref b) => match (&**a, &**b) {
+                    (x, Expr::Integer(0)) | (Expr::Integer(0), x) if x.is_integer_valued() => {
+                        Some(x)
+                    }
+                    _ => None,
+                },
+                Expr::Sub(ref a, ref b) => match (&**a, &**b) {
+                    (x, Expr::Integer(0)) if x.is_integer_valued() => Some(x),
+                    _ => None,
+                },
+                Expr::Mul(ref a, ref b) => match (&**a, &**b) {
+                    (x, Expr::Integer(1)) | (Expr::Integer(1), x) if x.is_integer_valued() => {
+                        Some(x)
+                    }
+                    _ => None,
+                },
+                Expr::And(ref a, ref b) => match (&**a, &**b) {
+                    (x, Expr::Boolean(true)) | (Expr::Boolean(true), x)
+                        if x.is_boolean_valued() =>
+                    {
+                        Some(x)
+                    }
+                    _ => None,
+                },
+                Expr::Or(ref a, ref b) => match (&**a, &**b) {
+                    (x, Expr::Boolean(false)) | (Expr::Boolean(false), x)
+                        if x.is_boolean_valued() =>
+                    {
+                        Some(x)
+                    }
+                    _ => None,
+                },
+                Expr::Not(ref a) => match **a {
+                    Expr::Not(ref x) if x.is_boolean_valued() => Some(&**x),
+                    _ => None,
+                },
+                _ => None,
+            };
+            if let Some(x) = collapsed {
+                return x.clone();
+            }
+        }
+        e
+    }
 }
 
 impl std::fmt::Display for Expr {
@@ -1754,9 +2380,9 @@ impl std::fmt::Display for Expr {
                 }
                 Ok(())
             }
-            Expr::String(ref s) => write!(f, "{:?}", s),
+            Expr::String(ref s) => write_quoted(f, s),
             Expr::Integer(n) => write!(f, "{}", n),
-            Expr::Float(n) => write!(f, "{}", n),
+            Expr::Float(n) => write!(f, "{}", float_format().format(n)),
             Expr::Boolean(b) => write!(f, "{}", b),
             Expr::Null => write!(f, "null"),
             Expr::Concat(ref elems) => {
@@ -1770,9 +2396,15 @@ impl std::fmt::Display for Expr {
             Expr::Sub(ref a, ref b) => write!(f, "({} - {})", a, b),
             Expr::Mul(ref a, ref b) => write!(f, "({} * {})", a, b),
             Expr::Div(ref a, ref b) => write!(f, "({} / {})", a, b),
+            Expr::BitAnd(ref a, ref b) => write!(f, "({} & {})", a, b),
+            Expr::BitOr(ref a, ref b) => write!(f, "({} | {})", a, b),
+            Expr::BitXor(ref a, ref b) => write!(f, "({} ^ {})", a, b),
+            Expr::Shl(ref a, ref b) => write!(f, "({} << {})", a, b),
+            Expr::Shr(ref a, ref b) => write!(f, "({} >> {})", a, b),
             Expr::Not(ref a) => write!(f, "!({})", a),
             Expr::And(ref a, ref b) => write!(f, "({} and {})", a, b),
             Expr::Or(ref a, ref b) => write!(f, "({} or {})", a, b),
+            Expr::Coalesce(ref a, ref b) => write!(f, "({} ?? {})", a, b),
             Expr::StartsWith(ref a, ref b) => write!(f, "({} ^= {})", a, b),
             Expr::EndsWith(ref a, ref b) => write!(f, "({} $= {})", a, b),
             Expr::Contains(ref a, ref b) => write!(f, "({} *= {})", a, b),
@@ -1785,6 +2417,7 @@ impl std::fmt::Display for Expr {
             Expr::Root => write!(f, "$"),
             Expr::Current => write!(f, "@"),
             Expr::Parent => write!(f, "^"),
+            Expr::Guard => write!(f, "?"),
             Expr::All => {
                 if f.alternate() {
                     write!(f, "*")
@@ -1802,7 +2435,10 @@ impl std::fmt::Display for Expr {
                     write!(f, "[{}]", id)
                 }
             }
-            Expr::PropertyExpr(ref e) => write!(f, ".{:#}", e),
+            Expr::PropertyExpr(ref e) => match **e {
+                Expr::Group(_) => write!(f, ".{}", e),
+                _ => write!(f, ".({:#})", e),
+            },
             Expr::Index(index) => write!(f, "[{}]", index),
             Expr::IndexExpr(ref e) => write!(f, "[{:#}]", e),
             Expr::Range(ref r) => write!(f, "{}", r),
@@ -1830,7 +2466,7 @@ impl std::fmt::Display for Expr {
                 write!(f, ")")?;
                 Ok(())
             }
-            Expr::Var(ref id) => write!(f, "${}", id),
+            Expr::Var(ref id) => write!(f, "${}", id.name),
             Expr::VarExpr(ref e) => write!(f, "${{{:#}}}", e),
             Expr::Env(ref id) => write!(f, "env:{}", id),
             Expr::EnvExpr(ref e) => write!(f, "env:({:#})", e),
@@ -1856,9 +2492,15 @@ impl PartialEq for Expr {
                 (&Expr::Sub(ref a1, ref b1), &Expr::Sub(ref a2, ref b2)) => a1 == a2 && b1 == b2,
                 (&Expr::Mul(ref a1, ref b1), &Expr::Mul(ref a2, ref b2)) => a1 == a2 && b1 == b2,
                 (&Expr::Div(ref a1, ref b1), &Expr::Div(ref a2, ref b2)) => a1 == a2 && b1 == b2,
+                (&Expr::BitAnd(ref a1, ref b1), &Expr::BitAnd(ref a2, ref b2)) => a1 == a2 && b1 == b2,
+                (&Expr::BitOr(ref a1, ref b1), &Expr::BitOr(ref a2, ref b2)) => a1 == a2 && b1 == b2,
+                (&Expr::BitXor(ref a1, ref b1), &Expr::BitXor(ref a2, ref b2)) => a1 == a2 && b1 == b2,
+                (&Expr::Shl(ref a1, ref b1), &Expr::Shl(ref a2, ref b2)) => a1 == a2 && b1 == b2,
+                (&Expr::Shr(ref a1, ref b1), &Expr::Shr(ref a2, ref b2)) => a1 == a2 && b1 == b2,
                 (&Expr::Not(ref a1), &Expr::Not(ref a2)) => a1 == a2,
                 (&Expr::And(ref a1, ref b1), &Expr::And(ref a2, ref b2)) => a1 == a2 && b1 == b2,
                 (&Expr::Or(ref a1, ref b1), &Expr::Or(ref a2, ref b2)) => a1 == a2 && b1 == b2,
+                (&Expr::Coalesce(ref a1, ref b1), &Expr::Coalesce(ref a2, ref b2)) => a1 == a2 && b1 == b2,
                 (&Expr::StartsWith(ref a1, ref b1), &Expr::StartsWith(ref a2, ref b2)) => {
                     a1 == a2 && b1 == b2
                 }
@@ -1877,6 +2519,7 @@ impl PartialEq for Expr {
                 (&Expr::Root, &Expr::Root) => true,
                 (&Expr::Current, &Expr::Current) => true,
                 (&Expr::Parent, &Expr::Parent) => true,
+                (&Expr::Guard, &Expr::Guard) => true,
                 (&Expr::All, &Expr::All) => true,
                 (&Expr::Ancestors(ref l1), &Expr::Ancestors(ref l2)) => l1 == l2,
                 (&Expr::Descendants(ref l1), &Expr::Descendants(ref l2)) => l1 == l2,
@@ -1931,6 +2574,26 @@ impl Hash for Expr {
                 a.hash(state);
                 b.hash(state);
             }
+            Expr::BitAnd(ref a, ref b) => {
+                a.hash(state);
+                b.hash(state);
+            }
+            Expr::BitOr(ref a, ref b) => {
+                a.hash(state);
+                b.hash(state);
+            }
+            Expr::BitXor(ref a, ref b) => {
+                a.hash(state);
+                b.hash(state);
+            }
+            Expr::Shl(ref a, ref b) => {
+                a.hash(state);
+                b.hash(state);
+            }
+            Expr::Shr(ref a, ref b) => {
+                a.hash(state);
+                b.hash(state);
+            }
             Expr::Not(ref a) => a.hash(state),
             Expr::And(ref a, ref b) => {
                 a.hash(state);
@@ -1940,6 +2603,10 @@ impl Hash for Expr {
                 a.hash(state);
                 b.hash(state);
             }
+            Expr::Coalesce(ref a, ref b) => {
+                a.hash(state);
+                b.hash(state);
+            }
             Expr::StartsWith(ref a, ref b) => {
                 a.hash(state);
                 b.hash(state);
@@ -1979,6 +2646,7 @@ impl Hash for Expr {
             Expr::Root => {}
             Expr::Current => {}
             Expr::Parent => {}
+            Expr::Guard => {}
             Expr::All => {}
             Expr::Ancestors(ref l) => l.hash(state),
             Expr::Descendants(ref l) => l.hash(state),
@@ -2036,6 +2704,111 @@ mod tests {
             assert_eq!(hash(e1), hash(e2));
         }
 
+        #[test]
+        fn scientific_float_literals() {
+            let n = NodeRef::null();
+            let eval = |s: &str| {
+                let r = Opath::parse(s).unwrap().apply(&n, &n).unwrap();
+                let r = r.into_one().unwrap();
+                assert!(r.is_float());
+                r.as_float()
+            };
+
+            assert_eq!(eval("1e3"), 1000.0);
+            assert_eq!(eval("1.5e-2"), 0.015);
+            assert_eq!(eval("-2E+4"), -20000.0);
+            assert_eq!(eval("1e3 + 1"), 1001.0);
+        }
+
+        #[test]
+        fn bitwise_operators() {
+            let n = NodeRef::from_json(r#"{"mode": 493, "flag": 4}"#).unwrap();
+            let eval = |s: &str| {
+                let r = Opath::parse(s).unwrap().apply(&n, &n).unwrap();
+                r.into_one().unwrap()
+            };
+
+            assert_eq!(eval("0xFF & 0x0F").as_integer(), Some(0x0F));
+            assert_eq!(eval("0b1010 | 0b0101").as_integer(), Some(0b1111));
+            assert_eq!(eval("6 ^ 3").as_integer(), Some(5));
+            assert_eq!(eval("1 << 10").as_integer(), Some(1024));
+            assert_eq!(eval("1024 >> 3").as_integer(), Some(128));
+            assert_eq!(eval("-16 >> 2").as_integer(), Some(-4));
+            assert_eq!(eval("$.mode & $.flag").as_integer(), Some(4));
+            assert_eq!(eval("$.mode ^ 0o777").as_integer(), Some(0o022));
+            assert_eq!(eval("'12' | 1").as_integer(), Some(13));
+        }
+
+        #[test]
+        fn bitwise_operators_non_integer() {
+            let n = NodeRef::from_json(r#"{"arr": [1], "pi": 3.14}"#).unwrap();
+            let eval = |s: &str| {
+                let r = Opath::parse(s).unwrap().apply(&n, &n).unwrap();
+                r.into_one().unwrap()
+            };
+
+            assert!(eval("'abc' & 1").as_float().is_nan());
+            assert!(eval("$.arr | 1").as_float().is_nan());
+            assert!(eval("$.missing ^ 1").as_float().is_nan());
+            assert_eq!(eval("$.pi | 0").as_integer(), Some(3));
+        }
+
+        #[test]
+        fn shift_by_large_amount() {
+            let n = NodeRef::null();
+            let eval = |s: &str| {
+                let r = Opath::parse(s).unwrap().apply(&n, &n).unwrap();
+                r.into_one().unwrap()
+            };
+
+            assert_eq!(eval("1 << 63").as_integer(), Some(i64::MIN));
+            assert_eq!(eval("1 << 64").as_integer(), Some(0));
+            assert_eq!(eval("1 << 1000").as_integer(), Some(0));
+            assert_eq!(eval("5 >> 64").as_integer(), Some(0));
+            assert_eq!(eval("-5 >> 1000").as_integer(), Some(-1));
+            assert!(eval("1 << -1").as_float().is_nan());
+        }
+
+        #[test]
+        fn coalesce_left_present() {
+            let n = NodeRef::from_json(r#"{"override": "a", "default": "b"}"#).unwrap();
+            let r = Opath::parse("@.override ?? @.default").unwrap().apply(&n, &n).unwrap();
+            assert_eq!(r.into_one().unwrap().as_string(), "a");
+        }
+
+        #[test]
+        fn coalesce_left_empty_or_null() {
+            let n = NodeRef::from_json(r#"{"override": null, "default": "b"}"#).unwrap();
+            let r = Opath::parse("@.missing ?? @.default").unwrap().apply(&n, &n).unwrap();
+            assert_eq!(r.into_one().unwrap().as_string(), "b");
+
+            let r = Opath::parse("@.override ?? @.default").unwrap().apply(&n, &n).unwrap();
+            assert_eq!(r.into_one().unwrap().as_string(), "b");
+
+            let r = Opath::parse("@.missing ?? @.other").unwrap().apply(&n, &n).unwrap();
+            assert!(r.is_empty());
+        }
+
+        #[test]
+        fn coalesce_keeps_falsy_values() {
+            let n = NodeRef::from_json(r#"{"zero": 0, "no": false}"#).unwrap();
+            let r = Opath::parse("@.zero ?? 1").unwrap().apply(&n, &n).unwrap();
+            assert_eq!(r.into_one().unwrap().as_integer(), Some(0));
+
+            let r = Opath::parse("@.no ?? true").unwrap().apply(&n, &n).unwrap();
+            assert_eq!(r.into_one().unwrap().as_boolean(), false);
+        }
+
+        #[test]
+        fn coalesce_chained() {
+            let n = NodeRef::from_json(r#"{"c": 3}"#).unwrap();
+            let r = Opath::parse("@.a ?? @.b ?? @.c").unwrap().apply(&n, &n).unwrap();
+            assert_eq!(r.into_one().unwrap().as_integer(), Some(3));
+
+            let r = Opath::parse("@.a ?? @.b ?? 'none'").unwrap().apply(&n, &n).unwrap();
+            assert_eq!(r.into_one().unwrap().as_string(), "none");
+        }
+
         #[test]
         fn metadata_file_path_components() {
             use std::path::Path;
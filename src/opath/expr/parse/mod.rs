@@ -8,6 +8,28 @@ pub type Error = ParseDiag;
 
 pub type Token = LexToken<Terminal>;
 
+/// Returns the span of input the parse error points at, suitable for highlighting
+/// the error location in an editor.
+pub fn parse_error_span(err: &Error) -> Option<Span> {
+    if let Some(q) = err.quotes().last() {
+        return Some(q.span());
+    }
+    let detail = err.detail();
+    if let Some(d) = detail.downcast_ref::<ParseErrorDetail>() {
+        d.span()
+    } else if let Some(d) = detail.downcast_ref::<kg_diag::parse::ParseErrorDetail>() {
+        use kg_diag::parse::ParseErrorDetail as DiagParseErrorDetail;
+        match *d {
+            DiagParseErrorDetail::UnexpectedEof { pos, .. }
+            | DiagParseErrorDetail::UnexpectedInput { pos, .. } => Some(Span::with_pos(pos, pos)),
+            DiagParseErrorDetail::Numerical { span, .. } => Some(span),
+            DiagParseErrorDetail::Io(_) => None,
+        }
+    } else {
+        None
+    }
+}
+
 #[derive(Debug, Display, Detail)]
 #[diag(code_offset = 300)]
 pub enum ParseErrorDetail {
@@ -64,6 +86,24 @@ pub enum ParseErrorDetail {
 }
 
 impl ParseErrorDetail {
+    /// Returns the span of input this error refers to, if known.
+    pub fn span(&self) -> Option<Span> {
+        match *self {
+            ParseErrorDetail::InvalidEscape { from, to }
+            | ParseErrorDetail::InvalidChar { from, to, .. }
+            | ParseErrorDetail::InvalidCharOne { from, to, .. }
+            | ParseErrorDetail::InvalidCharMany { from, to, .. } => Some(Span::with_pos(from, to)),
+            ParseErrorDetail::UnexpectedEoi { pos }
+            | ParseErrorDetail::UnexpectedEoiOne { pos, .. }
+            | ParseErrorDetail::UnexpectedEoiMany { pos, .. }
+            | ParseErrorDetail::UnexpectedEoiOneString { pos, .. } => Some(Span::with_pos(pos, pos)),
+            ParseErrorDetail::UnexpectedToken { ref token }
+            | ParseErrorDetail::UnexpectedTokenOne { ref token, .. }
+            | ParseErrorDetail::UnexpectedTokenMany { ref token, .. } => Some(token.span()),
+            ParseErrorDetail::UnclosedGroup(_) => None,
+        }
+    }
+
     pub fn invalid_escape<T>(r: &mut dyn CharReader) -> Result<T, Error> {
         let p1 = r.position();
         let err = match r.next_char()? {
@@ -225,6 +265,8 @@ pub enum Terminal {
     Dot,
     #[display(fmt = "'..'")]
     DoubleDot,
+    #[display(fmt = "'?.'")]
+    SafeDot,
     #[display(fmt = "':'")]
     Colon,
     #[display(fmt = "'^'")]
@@ -245,6 +287,16 @@ pub enum Terminal {
     And,
     #[display(fmt = "'||' or 'or'")]
     Or,
+    #[display(fmt = "'??'")]
+    Coalesce,
+    #[display(fmt = "'&'")]
+    Ampersand,
+    #[display(fmt = "'|'")]
+    Pipe,
+    #[display(fmt = "'<<'")]
+    Shl,
+    #[display(fmt = "'>>'")]
+    Shr,
     #[display(fmt = "'=='")]
     Eq,
     #[display(fmt = "'!='")]
@@ -316,8 +368,13 @@ enum Context {
     Index,
     Range,
     Env,
+    OpCoalesce,
     OpAndOr,
     OpCmp,
+    OpBitOr,
+    OpBitXor,
+    OpBitAnd,
+    OpShift,
     OpAddSub,
     OpMulDivMod,
     OpNot,
@@ -420,6 +477,23 @@ impl Parser {
                 Some('+') => consume(r, 1, Terminal::Plus),
                 Some('-') => consume(r, 1, Terminal::Minus),
                 Some('/') => consume(r, 1, Terminal::Slash),
+                Some('?') => {
+                    let p1 = r.position();
+                    r.next_char()?;
+                    match r.peek_char(0)? {
+                        Some('?') => {
+                            r.next_char()?;
+                            let p2 = r.position();
+                            Ok(Token::new(Terminal::Coalesce, p1, p2))
+                        }
+                        Some('.') => {
+                            r.next_char()?;
+                            let p2 = r.position();
+                            Ok(Token::new(Terminal::SafeDot, p1, p2))
+                        }
+                        _ => ParseErrorDetail::invalid_input_one(r, '?'),
+                    }
+                }
                 Some('|') => {
                     let p1 = r.position();
                     r.next_char()?;
@@ -428,7 +502,8 @@ impl Parser {
                         let p2 = r.position();
                         Ok(Token::new(Terminal::Or, p1, p2))
                     } else {
-                        ParseErrorDetail::invalid_input_one(r, '|')
+                        let p2 = r.position();
+                        Ok(Token::new(Terminal::Pipe, p1, p2))
                     }
                 }
                 Some('&') => {
@@ -439,7 +514,8 @@ impl Parser {
                         let p2 = r.position();
                         Ok(Token::new(Terminal::And, p1, p2))
                     } else {
-                        ParseErrorDetail::invalid_input_one(r, '&')
+                        let p2 = r.position();
+                        Ok(Token::new(Terminal::Ampersand, p1, p2))
                     }
                 }
                 Some('^') => {
@@ -481,25 +557,41 @@ impl Parser {
                 Some('>') => {
                     let p1 = r.position();
                     r.next_char()?;
-                    if let Some('=') = r.peek_char(0)? {
-                        r.next_char()?;
-                        let p2 = r.position();
-                        Ok(Token::new(Terminal::Ge, p1, p2))
-                    } else {
-                        let p2 = r.position();
-                        Ok(Token::new(Terminal::Gt, p1, p2))
+                    match r.peek_char(0)? {
+                        Some('=') => {
+                            r.next_char()?;
+                            let p2 = r.position();
+                            Ok(Token::new(Terminal::Ge, p1, p2))
+                        }
+                        Some('>') => {
+                            r.next_char()?;
+                            let p2 = r.position();
+                            Ok(Token::new(Terminal::Shr, p1, p2))
+                        }
+                        _ => {
+                            let p2 = r.position();
+                            Ok(Token::new(Terminal::Gt, p1, p2))
+                        }
                     }
                 }
                 Some('<') => {
                     let p1 = r.position();
                     r.next_char()?;
-                    if let Some('=') = r.peek_char(0)? {
-                        r.next_char()?;
-                        let p2 = r.position();
-                        Ok(Token::new(Terminal::Le, p1, p2))
-                    } else {
-                        let p2 = r.position();
-                        Ok(Token::new(Terminal::Lt, p1, p2))
+                    match r.peek_char(0)? {
+                        Some('=') => {
+                            r.next_char()?;
+                            let p2 = r.position();
+                            Ok(Token::new(Terminal::Le, p1, p2))
+                        }
+                        Some('<') => {
+                            r.next_char()?;
+                            let p2 = r.position();
+                            Ok(Token::new(Terminal::Shl, p1, p2))
+                        }
+                        _ => {
+                            let p2 = r.position();
+                            Ok(Token::new(Terminal::Lt, p1, p2))
+                        }
                     }
                 }
                 Some('.') => {
@@ -1023,6 +1115,51 @@ impl Parser {
                         e = Expr::Contains(Box::new(e), Box::new(f))
                     }
                 }
+                Terminal::Pipe => {
+                    if ctx > Context::OpBitOr {
+                        self.push_token(t);
+                        return Ok(e);
+                    } else {
+                        let f = self.parse_expr(r, Context::OpBitOr)?;
+                        e = Expr::BitOr(Box::new(e), Box::new(f))
+                    }
+                }
+                Terminal::Caret => {
+                    if ctx > Context::OpBitXor {
+                        self.push_token(t);
+                        return Ok(e);
+                    } else {
+                        let f = self.parse_expr(r, Context::OpBitXor)?;
+                        e = Expr::BitXor(Box::new(e), Box::new(f))
+                    }
+                }
+                Terminal::Ampersand => {
+                    if ctx > Context::OpBitAnd {
+                        self.push_token(t);
+                        return Ok(e);
+                    } else {
+                        let f = self.parse_expr(r, Context::OpBitAnd)?;
+                        e = Expr::BitAnd(Box::new(e), Box::new(f))
+                    }
+                }
+                Terminal::Shl => {
+                    if ctx > Context::OpShift {
+                        self.push_token(t);
+                        return Ok(e);
+                    } else {
+                        let f = self.parse_expr(r, Context::OpShift)?;
+                        e = Expr::Shl(Box::new(e), Box::new(f))
+                    }
+                }
+                Terminal::Shr => {
+                    if ctx > Context::OpShift {
+                        self.push_token(t);
+                        return Ok(e);
+                    } else {
+                        let f = self.parse_expr(r, Context::OpShift)?;
+                        e = Expr::Shr(Box::new(e), Box::new(f))
+                    }
+                }
                 Terminal::And => {
                     if ctx > Context::OpAndOr {
                         self.push_token(t);
@@ -1041,6 +1178,16 @@ impl Parser {
                         e = Expr::Or(Box::new(e), Box::new(f))
                     }
                 }
+                Terminal::Coalesce => {
+                    // `env:NAME ?? default` applies the default to the variable value, not its name
+                    if ctx > Context::OpCoalesce || ctx == Context::Env {
+                        self.push_token(t);
+                        return Ok(e);
+                    } else {
+                        let f = self.parse_expr(r, Context::OpCoalesce)?;
+                        e = Expr::Coalesce(Box::new(e), Box::new(f))
+                    }
+                }
                 Terminal::DoubleDot | Terminal::Colon => {
                     self.push_token(t);
                     if ctx < Context::Range {
@@ -1209,6 +1356,7 @@ impl Parser {
             let t = self.next_token(r)?;
             match t.term() {
                 Terminal::Caret => {
+                    let tc = t;
                     let t = self.next_token(r)?;
                     match t.term() {
                         Terminal::DoubleStar => {
@@ -1218,13 +1366,41 @@ impl Parser {
                             };
                             elems.push(Expr::Ancestors(Box::new(l)));
                         }
+                        // caret followed by an operand is a bitwise xor operator
+                        Terminal::IntDecimal
+                        | Terminal::IntHex
+                        | Terminal::IntOctal
+                        | Terminal::IntBinary
+                        | Terminal::Float
+                        | Terminal::String
+                        | Terminal::True
+                        | Terminal::False
+                        | Terminal::Null
+                        | Terminal::Root
+                        | Terminal::Current
+                        | Terminal::Attr
+                        | Terminal::Id
+                        | Terminal::Var
+                        | Terminal::VarBegin
+                        | Terminal::Env
+                        | Terminal::Not
+                        | Terminal::Minus
+                        | Terminal::ParenLeft => {
+                            self.push_token(tc);
+                            self.push_token(t);
+                            break;
+                        }
                         _ => {
                             self.push_token(t);
                             elems.push(Expr::Parent);
                         }
                     }
                 }
-                Terminal::Dot => {
+                Terminal::Dot | Terminal::SafeDot => {
+                    // safe navigation, rest of the sequence is skipped for null nodes
+                    if t.term() == Terminal::SafeDot {
+                        elems.push(Expr::Guard);
+                    }
                     let t = self.next_token(r)?;
                     match t.term() {
                         Terminal::String => {
@@ -1362,10 +1538,25 @@ impl Parser {
         let op = t;
 
         let mut elems = Vec::new();
+        // elements consisting of a single bare identifier, e.g. `a` in `[a, b]`
+        let mut bare = Vec::new();
 
         loop {
+            let mut is_bare = false;
+            if ctx == Context::Index {
+                let t1 = self.next_token(r)?;
+                if t1.term() == Terminal::Id {
+                    let t2 = self.next_token(r)?;
+                    is_bare = t2.term() == Terminal::Comma || t2.term() == tsep;
+                    self.push_token(t1);
+                    self.push_token(t2);
+                } else {
+                    self.push_token(t1);
+                }
+            }
             let e = self.parse_expr(r, ctx)?;
             elems.push(e);
+            bare.push(is_bare);
 
             let t = self.next_token(r)?;
             match t.term() {
@@ -1384,6 +1575,23 @@ impl Parser {
         Ok(if elems.len() == 1 {
             elems.pop().unwrap()
         } else {
+            // bare identifiers in a key list (`[a, b, c]`) select properties, same as quoted
+            // keys, while explicit `@.a` is still evaluated; a single `[a]` keeps its
+            // meaning of `[@.a]`, so that e.g. `$.items.*[enabled]` filters
+            for (e, bare) in elems.iter_mut().zip(bare) {
+                let key = match *e {
+                    Expr::Sequence(ref s) if bare && s.len() == 2 && s[0] == Expr::Current => {
+                        match s[1] {
+                            Expr::Property(ref id) => Some(id.name.clone()),
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                };
+                if let Some(key) = key {
+                    *e = Expr::String(key);
+                }
+            }
             Expr::Group(elems)
         })
     }
@@ -1418,9 +1626,14 @@ impl Parser {
                 match t.term() {
                     Terminal::BraceRight => {}
                     Terminal::Comma => {
-                        let max = self.parse_expr(r, Context::Expr)?;
-                        l.set_max(max);
-                        self.expect_token(r, Terminal::BraceRight)?;
+                        // `{min,}` leaves the upper bound open
+                        let t = self.next_token(r)?;
+                        if t.term() != Terminal::BraceRight {
+                            self.push_token(t);
+                            let max = self.parse_expr(r, Context::Expr)?;
+                            l.set_max(max);
+                            self.expect_token(r, Terminal::BraceRight)?;
+                        }
                     }
                     _ => {
                         let expected = vec![Terminal::BraceRight, Terminal::Comma];
@@ -0,0 +1,91 @@
+use crate::opath::*;
+use crate::opath::Expr::*;
+
+#[test]
+fn bit_and() {
+    assert_expr!("6 & 3", BitAnd(Box::new(Integer(6)), Box::new(Integer(3))))
+}
+
+#[test]
+fn bit_or() {
+    assert_expr!("6 | 3", BitOr(Box::new(Integer(6)), Box::new(Integer(3))))
+}
+
+#[test]
+fn bit_xor() {
+    assert_expr!("6 ^ 3", BitXor(Box::new(Integer(6)), Box::new(Integer(3))))
+}
+
+#[test]
+fn shift_left() {
+    assert_expr!("1 << 4", Shl(Box::new(Integer(1)), Box::new(Integer(4))))
+}
+
+#[test]
+fn shift_right() {
+    assert_expr!("16 >> 4", Shr(Box::new(Integer(16)), Box::new(Integer(4))))
+}
+
+#[test]
+fn xor_after_property() {
+    assert_expr!("@.a ^ 3", BitXor(
+        Box::new(Sequence(vec![Current, Property(Box::new(Id::new("a")))])),
+        Box::new(Integer(3))))
+}
+
+#[test]
+fn xor_with_negative_operand() {
+    assert_expr!("@.a ^ -1", BitXor(
+        Box::new(Sequence(vec![Current, Property(Box::new(Id::new("a")))])),
+        Box::new(Integer(-1))))
+}
+
+#[test]
+fn xor_with_negated_operand() {
+    assert_expr!("@.a ^ -@.b", BitXor(
+        Box::new(Sequence(vec![Current, Property(Box::new(Id::new("a")))])),
+        Box::new(Neg(Box::new(Sequence(vec![Current, Property(Box::new(Id::new("b")))]))))))
+}
+
+#[test]
+fn caret_before_plus_is_parent() {
+    // there is no unary plus, so `^ +` can only be the parent followed by addition
+    assert_expr!("@.a^ + 1", Add(
+        Box::new(Sequence(vec![Current, Property(Box::new(Id::new("a"))), Parent])),
+        Box::new(Integer(1))))
+}
+
+#[test]
+fn caret_before_dot_is_parent() {
+    assert_expr!("@.a^.b", Sequence(vec![
+        Current,
+        Property(Box::new(Id::new("a"))),
+        Parent,
+        Property(Box::new(Id::new("b"))),
+    ]))
+}
+
+#[test]
+fn precedence() {
+    assert_expr!("1 | 2 ^ 3 & 4 << 5 + 6", BitOr(
+        Box::new(Integer(1)),
+        Box::new(BitXor(
+            Box::new(Integer(2)),
+            Box::new(BitAnd(
+                Box::new(Integer(3)),
+                Box::new(Shl(
+                    Box::new(Integer(4)),
+                    Box::new(Add(Box::new(Integer(5)), Box::new(Integer(6)))),
+                )),
+            )),
+        )),
+    ))
+}
+
+#[test]
+fn comparison_binds_looser() {
+    assert_expr!("@ & 1 == 1", Eq(
+        Box::new(BitAnd(Box::new(Current), Box::new(Integer(1)))),
+        Box::new(Integer(1)),
+    ))
+}
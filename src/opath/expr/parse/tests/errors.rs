@@ -18,26 +18,11 @@ fn parse_error_detail(err: &ParseDiag) -> &DiagParseErrorDetail {
 }
 
 #[test]
-fn or_single_pipe() {
-    let diag = Opath::parse("true |   true").unwrap_err();
-    let err = parse_err(&diag);
-        match *err {
-            ParseErrorDetail::InvalidCharOne { .. } => {
-//                assert_eq!(expected, &["|"]);
-//                assert_eq!(token.term(), "t");
-            }
-            _ => panic!("Wrong error kind")
-        }
-
-    assert_eq!(diag.quotes().len(), 1);
-}
-
-#[test]
-fn or_single_pipe_eoi() {
+fn bit_or_eoi() {
     let diag = Opath::parse("true | ").unwrap_err();
     let err = parse_err(&diag);
     match *err {
-        ParseErrorDetail::UnexpectedEoiOne { .. } => {
+        ParseErrorDetail::UnexpectedTokenMany { .. } => {
 //            assert_eq!(pos, "????");
         }
         _ => panic!("Wrong error kind")
@@ -47,26 +32,11 @@ fn or_single_pipe_eoi() {
 }
 
 #[test]
-fn and_single_amp() {
-    let diag = Opath::parse("true &   true").unwrap_err();
-    let err = parse_err(&diag);
-    match *err {
-        ParseErrorDetail::InvalidCharOne { .. } => {
-//            assert_eq!(expected, &["&"]);
-//            assert_eq!(token, "t");
-        }
-        _ => panic!("Wrong error kind")
-    }
-
-    assert_eq!(diag.quotes().len(), 1);
-}
-
-#[test]
-fn and_single_amp_eoi() {
+fn bit_and_eoi() {
     let diag = Opath::parse("true & ").unwrap_err();
     let err = parse_err(&diag);
     match *err {
-        ParseErrorDetail::UnexpectedEoiOne { .. } => {
+        ParseErrorDetail::UnexpectedTokenMany { .. } => {
 //            assert_eq!(pos, "????");
         }
         _ => panic!("Wrong error kind")
@@ -114,6 +84,46 @@ fn no_digits_after_e_eof() {
     assert_eq!(diag.quotes().len(),0);
 }
 
+#[test]
+fn hex_invalid_digit() {
+    let diag = Opath::parse("0xG1").unwrap_err();
+    let err = parse_error_detail(&diag);
+    match *err {
+        DiagParseErrorDetail::UnexpectedInput { .. } => {}
+        _ => panic!("Wrong error kind")
+    }
+}
+
+#[test]
+fn octal_invalid_digit() {
+    let diag = Opath::parse("0o8").unwrap_err();
+    let err = parse_error_detail(&diag);
+    match *err {
+        DiagParseErrorDetail::UnexpectedInput { .. } => {}
+        _ => panic!("Wrong error kind")
+    }
+}
+
+#[test]
+fn binary_invalid_digit() {
+    let diag = Opath::parse("0b2").unwrap_err();
+    let err = parse_error_detail(&diag);
+    match *err {
+        DiagParseErrorDetail::UnexpectedInput { .. } => {}
+        _ => panic!("Wrong error kind")
+    }
+}
+
+#[test]
+fn binary_no_digits_eof() {
+    let diag = Opath::parse("0b").unwrap_err();
+    let err = parse_error_detail(&diag);
+    match *err {
+        DiagParseErrorDetail::UnexpectedEof { .. } => {}
+        _ => panic!("Wrong error kind")
+    }
+}
+
 #[test]
 fn scientific_notation_unexp_input() {
     let diag = Opath::parse("12.5e:").unwrap_err();
@@ -246,3 +256,40 @@ fn parse_group_sep() {
     }
     assert_eq!(diag.quotes().len(), 2);
 }
+
+mod span {
+    use super::*;
+
+    fn error_offsets(expr: &str) -> (usize, usize) {
+        let diag = Opath::parse(expr).unwrap_err();
+        let span = parse_error_span(&diag).expect("error span");
+        (span.from.offset, span.to.offset)
+    }
+
+    #[test]
+    fn unclosed_bracket() {
+        assert_eq!(error_offsets("$.a[1"), (5, 5));
+    }
+
+    #[test]
+    fn unclosed_paren() {
+        assert_eq!(error_offsets("(1 + 2"), (6, 6));
+    }
+
+    #[test]
+    fn unclosed_bracket_unexpected_token() {
+        assert_eq!(error_offsets("$.a[1 2]"), (6, 7));
+    }
+
+    #[test]
+    fn invalid_char() {
+        assert_eq!(error_offsets("$.a # b"), (4, 5));
+    }
+
+    #[test]
+    fn invalid_number_without_quote() {
+        let diag = Opath::parse("12.5e-;").unwrap_err();
+        assert_eq!(diag.quotes().len(), 0);
+        assert!(parse_error_span(&diag).is_some());
+    }
+}
@@ -46,3 +46,38 @@ fn index() {
         Index(5)
     ]));
 }
+
+#[test]
+fn key_list() {
+    assert_expr!("$[a, b, 'c d']", Sequence(vec![
+        Root,
+        IndexExpr(box Group(vec![
+            String("a".into()),
+            String("b".into()),
+            String("c d".into()),
+        ]))
+    ]));
+}
+
+#[test]
+fn explicit_current_key_list() {
+    assert_expr!("$[@.a, @.'b']", Sequence(vec![
+        Root,
+        IndexExpr(box Group(vec![
+            Sequence(vec![Current, Property(box Id::new("a"))]),
+            Sequence(vec![Current, Property(box Id::new("b"))]),
+        ]))
+    ]));
+}
+
+#[test]
+fn index_list() {
+    assert_expr!("$[0, 2, -1]", Sequence(vec![
+        Root,
+        IndexExpr(box Group(vec![
+            Integer(0),
+            Integer(2),
+            Integer(-1),
+        ]))
+    ]));
+}
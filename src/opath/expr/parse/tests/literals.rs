@@ -15,6 +15,31 @@ fn positive_integer_overflow() {
     assert_expr!("18446744073709551616", Expr::Float(18446744073709552000.0))
 }
 
+#[test]
+fn hex_integer() {
+    assert_expr!("0xFF", Expr::Integer(255))
+}
+
+#[test]
+fn hex_integer_lowercase() {
+    assert_expr!("0xdead_beef", Expr::Integer(0xdead_beef))
+}
+
+#[test]
+fn octal_integer() {
+    assert_expr!("0o755", Expr::Integer(0o755))
+}
+
+#[test]
+fn binary_integer() {
+    assert_expr!("0b1010", Expr::Integer(10))
+}
+
+#[test]
+fn negative_hex_integer() {
+    assert_expr!("-0x10", Expr::Integer(-16))
+}
+
 #[test]
 fn positive_float_dot() {
     assert_expr!("1.13", Expr::Float(1.13))
@@ -41,6 +66,21 @@ fn negative_float_e() {
     assert_expr!("-1E-2", Expr::Float(-1E-2))
 }
 
+#[test]
+fn float_exponent() {
+    assert_expr!("1e3", Expr::Float(1e3))
+}
+
+#[test]
+fn float_dot_negative_exponent() {
+    assert_expr!("1.5e-2", Expr::Float(1.5e-2))
+}
+
+#[test]
+fn negative_float_positive_exponent() {
+    assert_expr!("-2E+4", Expr::Float(-2E+4))
+}
+
 #[test]
 fn string_quot() {
     assert_expr!("'string'", Expr::String(String::from("string")))
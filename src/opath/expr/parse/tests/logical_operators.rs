@@ -170,3 +170,43 @@ mod negation {
         assert_expr!("not 0.0", Boolean(true))
     }
 }
+
+mod coalesce {
+    use super::*;
+
+    #[test]
+    fn literals() {
+        assert_expr!("null ?? 1",
+                    Coalesce(
+                        Box::new(Null),
+                        Box::new(Integer(1))
+                        )
+                );
+    }
+
+    #[test]
+    fn chained() {
+        assert_expr!("null ?? null ?? 1",
+                    Coalesce(
+                        Box::new(Null),
+                        Box::new(Coalesce(
+                            Box::new(Null),
+                            Box::new(Integer(1))
+                            ))
+                        )
+                );
+    }
+
+    #[test]
+    fn binds_looser_than_or() {
+        assert_expr!("false or true ?? 1",
+                    Coalesce(
+                        Box::new(Or(
+                            Box::new(Boolean(false)),
+                            Box::new(Boolean(true))
+                            )),
+                        Box::new(Integer(1))
+                        )
+                );
+    }
+}
@@ -21,6 +21,7 @@ macro_rules! assert_expr {
 mod logical_operators;
 mod comp_operators;
 mod math_operators;
+mod bitwise_operators;
 mod literals;
 mod number_ranges;
 mod prop_access;
@@ -28,3 +29,4 @@ mod operator_precedence;
 mod filtering;
 mod indexing;
 mod errors;
+mod round_trip;
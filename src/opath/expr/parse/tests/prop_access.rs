@@ -327,4 +327,30 @@ mod wildcards {
     }
 }
 
+mod safe_navigation {
+    use super::*;
+
+    #[test]
+    fn safe_property() {
+        assert_expr!("@.a?.b",
+        Sequence(
+            vec![
+                Current,
+                Property(box Id::new("a")),
+                Guard,
+                Property(box Id::new("b"))
+                ]))
+    }
 
+    #[test]
+    fn safe_method() {
+        assert_expr!("$.a?.length()",
+        Sequence(
+            vec![
+                Root,
+                Property(box Id::new("a")),
+                Guard,
+                MethodCall(Box::new(MethodCall::new(MethodId::Length, vec![])))
+                ]))
+    }
+}
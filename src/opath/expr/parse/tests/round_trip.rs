@@ -0,0 +1,116 @@
+use crate::opath::Opath;
+
+macro_rules! assert_round_trip {
+    ($expr:expr) => ({
+        let o1 = Opath::parse($expr).unwrap();
+        let s1 = o1.to_string();
+        let o2 = match Opath::parse(&s1) {
+            Ok(o) => o,
+            Err(e) => panic!("cannot parse display form {:?} of {:?}: {:?}", s1, $expr, e),
+        };
+        assert_eq!(o1.expr(), o2.expr(), "display form: {:?}", s1);
+        assert_eq!(s1, o2.to_string());
+    });
+}
+
+#[test]
+fn paths() {
+    assert_round_trip!("$");
+    assert_round_trip!("@");
+    assert_round_trip!("$.a.b[0]");
+    assert_round_trip!("$[\"a b\"].c");
+    assert_round_trip!("$['0abc']");
+}
+
+#[test]
+fn shorthands() {
+    assert_round_trip!("@.a");
+    assert_round_trip!("a.b");
+    assert_round_trip!("$.*");
+    assert_round_trip!("@.*.a");
+    assert_round_trip!("$.**");
+    assert_round_trip!("$.**{2}");
+    assert_round_trip!("$.**{,3}");
+    assert_round_trip!("$.**{1,3}");
+    assert_round_trip!("@^");
+    assert_round_trip!("@^**");
+    assert_round_trip!("$.a^.b");
+    assert_round_trip!("@.@key");
+    assert_round_trip!("@index");
+}
+
+#[test]
+fn special_keys() {
+    assert_round_trip!("$['true']");
+    assert_round_trip!("$['not']");
+    assert_round_trip!("$['$a']");
+    assert_round_trip!("$['@key']");
+    assert_round_trip!("$['quo\"te']");
+    assert_round_trip!("$['back\\\\slash']");
+    assert_round_trip!("$['a\\nb']");
+}
+
+#[test]
+fn string_escaping() {
+    assert_round_trip!("'plain'");
+    assert_round_trip!("'it\\'s'");
+    assert_round_trip!("'say \"hi\"'");
+    assert_round_trip!("'tab\\there\\r\\n'");
+    assert_round_trip!("'back\\\\slash'");
+    assert_round_trip!("'ąćźę'");
+}
+
+#[test]
+fn numbers() {
+    assert_round_trip!("0");
+    assert_round_trip!("0xFF");
+    assert_round_trip!("0o755");
+    assert_round_trip!("0b1010");
+    assert_round_trip!("-2");
+    assert_round_trip!("1.0");
+    assert_round_trip!("-1.5");
+    assert_round_trip!("0.1");
+    assert_round_trip!("1e-7");
+    assert_round_trip!("1.13e-10");
+    assert_round_trip!("1e300");
+    assert_round_trip!("1e3");
+    assert_round_trip!("1.5e-2");
+    assert_round_trip!("-2E+4");
+    assert_round_trip!("6.02e23");
+}
+
+#[test]
+fn operators() {
+    assert_round_trip!("1 + 2 * 3");
+    assert_round_trip!("(1 - 2) / 3");
+    assert_round_trip!("-@.a");
+    assert_round_trip!("1 - -2");
+    assert_round_trip!("@.a > 1 and @.b != 'x' or not @.c");
+    assert_round_trip!("@.a ^= 'x' || @.a $= 'y' && @.a *= 'z'");
+    assert_round_trip!("@.a >= 1 && @.a <= 2 && @.a < 3 && @.a == 1.5");
+    assert_round_trip!("@.a & 0xFF | 1 << 4 ^ @.b >> 2");
+    assert_round_trip!("@.a ?? @.b ?? 'c'");
+}
+
+#[test]
+fn indexing() {
+    assert_round_trip!("$.a[-1]");
+    assert_round_trip!("$.a[1..3]");
+    assert_round_trip!("$.a[0, 2]");
+    assert_round_trip!("$.a[@.b > 1]");
+    assert_round_trip!("$.(@.name)");
+    assert_round_trip!("$.(a, b)");
+    assert_round_trip!("$.('a' + 'b')");
+}
+
+#[test]
+fn calls_and_variables() {
+    assert_round_trip!("$.a.length()");
+    assert_round_trip!("$.a?.b?.length()");
+    assert_round_trip!("sqrt(@.a)");
+    assert_round_trip!("$x");
+    assert_round_trip!("$x.a");
+    assert_round_trip!("${'a' + 'b'}");
+    assert_round_trip!("env:HOME");
+    assert_round_trip!("env:('HO' + 'ME')");
+}
@@ -174,6 +174,15 @@ trait ScopeImpl: Sized {
         keys.sort();
         keys
     }
+
+    fn for_each_var<F: FnMut(&str, &NodeSet)>(&self, mut f: F) {
+        let inner = self.borrow();
+        let mut vars: Vec<(&Symbol, &NodeSet)> = inner.var_map.iter().collect();
+        vars.sort_by(|a, b| a.0.cmp(b.0));
+        for (name, var) in vars {
+            f(name.as_ref(), var);
+        }
+    }
 }
 
 trait ScopeMutImpl: ScopeImpl + Sized {
@@ -239,6 +248,10 @@ trait ScopeMutImpl: ScopeImpl + Sized {
 pub struct Scope(Rc<RefCell<Inner>>);
 
 impl Scope {
+    pub fn new_child(&self) -> ScopeMut {
+        ScopeMut::child(self.clone())
+    }
+
     pub fn get_func(&self, func: &'_ str) -> Option<Ref<Box<dyn FuncCallable>>> {
         ScopeImpl::get_func(self, func)
     }
@@ -278,6 +291,12 @@ impl Scope {
     pub fn var_names(&self) -> Vec<Symbol> {
         ScopeImpl::var_names(self)
     }
+
+    /// Calls `f` for every variable defined directly in this scope, in name order. The scope
+    /// stays borrowed during iteration, so `f` must not modify it.
+    pub fn for_each_var<F: FnMut(&str, &NodeSet)>(&self, f: F) {
+        ScopeImpl::for_each_var(self, f)
+    }
 }
 
 impl ScopeImpl for Scope {
@@ -354,6 +373,12 @@ impl ScopeMut {
         ScopeImpl::var_names(self)
     }
 
+    /// Calls `f` for every variable defined directly in this scope, in name order. The scope
+    /// stays borrowed during iteration, so `f` must not modify it.
+    pub fn for_each_var<F: FnMut(&str, &NodeSet)>(&self, f: F) {
+        ScopeImpl::for_each_var(self, f)
+    }
+
     pub fn with_func(self, name: Symbol, func: Box<dyn FuncCallable>) -> Self {
         ScopeMutImpl::with_func(self, name, func)
     }
@@ -484,4 +509,64 @@ mod tests {
 
         child.borrow_mut().var_map.clear();
     }
+
+    #[test]
+    fn list_vars() {
+        let s = ScopeMut::new();
+        s.set_var("b".into(), NodeSet::One(NodeRef::integer(2)));
+        s.set_var("a".into(), NodeSet::One(NodeRef::integer(1)));
+        s.set_var("c".into(), NodeSet::Empty);
+
+        let names = s.var_names();
+        assert_eq!(names, vec!["a", "b", "c"]);
+
+        let mut vars = Vec::new();
+        s.for_each_var(|name, var| vars.push((name.to_string(), var.len())));
+        assert_eq!(vars, vec![("a".to_string(), 1), ("b".to_string(), 1), ("c".to_string(), 0)]);
+    }
+
+    #[test]
+    fn list_vars_empty() {
+        let s = ScopeMut::new();
+        assert!(s.var_names().is_empty());
+        let mut count = 0;
+        s.for_each_var(|_, _| count += 1);
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn child_scope_shadows_parent_var() {
+        let parent = ScopeMut::new();
+        parent.set_var("var1".into(), NodeSet::One(NodeRef::string("value1")));
+        parent.set_var("var2".into(), NodeSet::One(NodeRef::string("value2")));
+
+        let child = parent.new_child();
+        assert_eq!(child.get_var_value::<String>("var1").unwrap(), "value1");
+
+        child.set_var("var1".into(), NodeSet::One(NodeRef::string("child1")));
+        assert_eq!(child.get_var_value::<String>("var1").unwrap(), "child1");
+        assert_eq!(child.get_var_value::<String>("var2").unwrap(), "value2");
+        assert_eq!(parent.get_var_value::<String>("var1").unwrap(), "value1");
+
+        child.remove_var("var1");
+        assert_eq!(child.get_var_value::<String>("var1").unwrap(), "value1");
+    }
+
+    #[test]
+    fn child_scope_in_expr() {
+        let parent = ScopeMut::new();
+        parent.set_var("a".into(), NodeSet::One(NodeRef::integer(1)));
+        parent.set_var("b".into(), NodeSet::One(NodeRef::integer(2)));
+
+        let child = parent.new_child();
+        child.set_var("b".into(), NodeSet::One(NodeRef::integer(10)));
+
+        let n = NodeRef::null();
+        let expr = Opath::parse("$a + $b").unwrap();
+        let res = expr.apply_one_ext(&n, &n, child.as_ref()).unwrap();
+        assert_eq!(res.as_integer(), Some(11));
+
+        let res = expr.apply_one_ext(&n, &n, parent.as_ref()).unwrap();
+        assert_eq!(res.as_integer(), Some(3));
+    }
 }
@@ -7,9 +7,10 @@ pub use self::expr::func::{
     Args, Func, FuncCallError, FuncCallResult, FuncCallable, FuncId, Method, MethodCallable,
     MethodId,
 };
-pub use self::expr::parse::{Error as OpathParseError, Parser};
+pub use self::expr::parse::{parse_error_span, Error as OpathParseError, Parser};
 pub use self::expr::{
-    Env, ExprErrorDetail, ExprResult, FuncCallErrorDetail, NodeBuf, NodeSet, Scope, ScopeMut,
+    Env, EvalBudget, ExprErrorDetail, ExprResult, FuncCallErrorDetail, NodeBuf, NodeSet, Scope, ScopeMut,
+    TraceEntry,
 };
 pub use self::interpolation::Interpolation;
 pub use self::matcher::NodePathMatcher;
@@ -17,6 +18,7 @@ pub use self::opath::Opath;
 pub use self::resolve::{
     DefaultResolveStrategy, ResolveStrategy, RootedResolveStrategy, TreeResolver,
 };
+pub use self::update::{delete_all, update_all};
 
 mod cache;
 mod expr;
@@ -24,3 +26,4 @@ mod interpolation;
 mod matcher;
 mod opath;
 mod resolve;
+mod update;
@@ -82,6 +82,11 @@ impl Opath {
         Opath::new(Expr::Path(seg))
     }
 
+    /// Same as [`Opath::from`], but the path is taken from `cache` and computed only on cache miss.
+    pub fn from_cached(node: &NodeRef, cache: &mut dyn OpathCache) -> Opath {
+        cache.get(node).clone()
+    }
+
     pub fn string(value: String) -> Opath {
         Opath::new(Expr::String(value))
     }
@@ -118,6 +123,44 @@ impl Opath {
         self.apply_env(Env::new(root, current, None))
     }
 
+    /// Evaluates this expression and returns only matched nodes that are attached to a parent
+    /// (lvalues), so they can be safely mutated with `replace_with()`, `set_child()` etc.
+    /// Computed values, like arithmetic results or function outputs, and tree roots
+    /// (e.g. `$`) are filtered out.
+    pub fn apply_lvalues(&self, root: &NodeRef, current: &NodeRef) -> ExprResult<Vec<NodeRef>> {
+        let res = self.apply(root, current)?.into_vec();
+        Ok(res
+            .into_iter()
+            .filter(|n| !n.is_consumable() && n.data().parent().is_some())
+            .collect())
+    }
+
+    /// Like [`apply`](Opath::apply), but aborts with `EvaluationBudgetExceeded` once
+    /// `budget` is used up.
+    pub fn apply_with_budget(
+        &self,
+        root: &NodeRef,
+        current: &NodeRef,
+        budget: &EvalBudget,
+    ) -> ExprResult<NodeSet> {
+        self.apply_env(Env::new(root, current, None).with_budget(budget))
+    }
+
+    /// Like [`apply`](Opath::apply), but also records every evaluated sub-expression together
+    /// with the number of produced nodes into `trace`, in evaluation order. Entries are
+    /// recorded even if evaluation fails.
+    pub fn apply_traced(
+        &self,
+        root: &NodeRef,
+        current: &NodeRef,
+        trace: &mut Vec<TraceEntry>,
+    ) -> ExprResult<NodeSet> {
+        let t = EvalTrace::new();
+        let res = self.apply_env(Env::new(root, current, None).with_trace(&t));
+        trace.extend(t.into_entries());
+        res
+    }
+
     pub fn apply_ext(&self, root: &NodeRef, current: &NodeRef, scope: &Scope) -> ExprResult<NodeSet> {
         self.apply_env(Env::new(root, current, Some(scope)))
     }
@@ -133,6 +176,83 @@ impl Opath {
         self.apply_env(Env::new(root, current, Some(scope)).with_diff(old_root, diff))
     }
 
+    /// Folds constant sub-expressions and collapses identities like `x + 0`, `x * 1`,
+    /// `true and x` or `!!x`, where `x` is known to yield integers or booleans.
+    pub fn simplify(self) -> Opath {
+        Opath::new(self.expr.simplify())
+    }
+
+    pub fn referenced_properties(&self) -> Vec<String> {
+        let mut props: Vec<String> = Vec::new();
+        self.expr.visit(&mut |e: &Expr| {
+            let name: Option<&str> = match *e {
+                Expr::Path(ref segments) => match segments.first() {
+                    Some(&PathSegment::Key(ref id)) => Some(&**id),
+                    _ => None,
+                },
+                Expr::Sequence(ref elems) => match (elems.get(0), elems.get(1)) {
+                    (Some(&Expr::Root), Some(&Expr::Property(ref id))) => Some(&***id),
+                    _ => None,
+                },
+                _ => None,
+            };
+            if let Some(name) = name {
+                if !props.iter().any(|p| p == name) {
+                    props.push(name.to_string());
+                }
+            }
+        });
+        props
+    }
+
+    pub fn variables(&self) -> Vec<String> {
+        let mut vars: Vec<String> = Vec::new();
+        self.expr.visit(&mut |e: &Expr| {
+            // names computed from non-literal expressions (e.g. `${$name}`) cannot be known statically
+            let name: Option<&str> = match *e {
+                Expr::Var(ref id) => Some(&***id),
+                Expr::VarExpr(box Expr::String(ref s)) => Some(s.as_str()),
+                _ => None,
+            };
+            if let Some(name) = name {
+                if !vars.iter().any(|v| v == name) {
+                    vars.push(name.to_string());
+                }
+            }
+        });
+        vars
+    }
+
+    pub fn env_vars(&self) -> Vec<String> {
+        let mut vars: Vec<String> = Vec::new();
+        self.expr.visit(&mut |e: &Expr| {
+            let name: Option<&str> = match *e {
+                Expr::Env(ref id) => Some(&***id),
+                Expr::EnvExpr(box Expr::String(ref s)) => Some(s.as_str()),
+                _ => None,
+            };
+            if let Some(name) = name {
+                if !vars.iter().any(|v| v == name) {
+                    vars.push(name.to_string());
+                }
+            }
+        });
+        vars
+    }
+
+    pub fn apply_each(&self, root: &NodeRef, currents: &[NodeRef]) -> ExprResult<Vec<NodeSet>> {
+        currents
+            .iter()
+            .map(|current| self.apply_env(Env::new(root, current, None)))
+            .collect()
+    }
+
+    /// Turns this expression into a reusable predicate, evaluating to the truthiness of the
+    /// result set (see [`NodeSet::is_truthy`]).
+    pub fn into_predicate(self) -> impl Fn(&NodeRef, &NodeRef) -> ExprResult<bool> {
+        move |root, current| self.apply(root, current).map(|res| res.is_truthy())
+    }
+
     pub fn apply_one(&self, root: &NodeRef, current: &NodeRef) -> ExprResult<NodeRef> {
         let ns = self.apply_env(Env::new(root, current, None))?;
         let res = match ns {
@@ -204,6 +324,53 @@ impl Opath {
             _ => false,
         }
     }
+
+    pub fn relative_to(&self, base: &Opath) -> Option<Opath> {
+        let (segments, base_segments): (&[PathSegment], &[PathSegment]) = match (&self.expr, &base.expr) {
+            (&Expr::Root, &Expr::Root) => (&[], &[]),
+            (&Expr::Path(ref a), &Expr::Root) => (a.as_slice(), &[]),
+            (&Expr::Path(ref a), &Expr::Path(ref b)) => (a.as_slice(), b.as_slice()),
+            _ => return None,
+        };
+        if !segments.starts_with(base_segments) {
+            return None;
+        }
+        let rest = &segments[base_segments.len()..];
+        if rest.is_empty() {
+            Some(Opath::current())
+        } else {
+            let mut elems = Vec::with_capacity(rest.len() + 1);
+            elems.push(Expr::Current);
+            elems.extend(rest.iter().map(|s| s.to_expr()));
+            Some(Opath::new(Expr::Sequence(elems)))
+        }
+    }
+
+    pub fn child_key(&self, key: &str) -> Opath {
+        self.child(PathSegment::Key(Id::new(key)))
+    }
+
+    pub fn child_index(&self, index: usize) -> Opath {
+        self.child(PathSegment::Index(index))
+    }
+
+    fn child(&self, segment: PathSegment) -> Opath {
+        let expr = match self.expr {
+            Expr::Root => Expr::Path(vec![segment]),
+            Expr::Path(ref segments) => {
+                let mut segments = segments.clone();
+                segments.push(segment);
+                Expr::Path(segments)
+            }
+            Expr::Sequence(ref elems) => {
+                let mut elems = elems.clone();
+                elems.push(segment.to_expr());
+                Expr::Sequence(elems)
+            }
+            ref e => Expr::Sequence(vec![e.clone(), segment.to_expr()]),
+        };
+        Opath::new(expr)
+    }
 }
 
 impl Clone for Opath {
@@ -371,4 +538,464 @@ mod tests {
             assert_eq!(p.to_string(), "$.prop1");
         }
     }
+
+    mod simplify {
+        use super::*;
+
+        fn simplify(expr: &str) -> Opath {
+            Opath::parse(expr).unwrap().simplify()
+        }
+
+        #[test]
+        fn constant_folding() {
+            assert_eq!(simplify("2 + 3 * 4").to_string(), "14");
+            assert_eq!(simplify("(2 + 3) * 4.5").to_string(), "22.5");
+            assert_eq!(simplify("'ab' + 'cd'").to_string(), "\"abcd\"");
+            assert_eq!(simplify("1 < 2 and 'a' == 'a'").to_string(), "true");
+            assert_eq!(simplify("@.a + 2 * 3").to_string(), "(@.a + 6)");
+            assert_eq!(simplify("max(1 + 1)").to_string(), "max(2)");
+        }
+
+        #[test]
+        fn identities() {
+            assert_eq!(simplify("($.a & 3) + 0").to_string(), "($.a & 3)");
+            assert_eq!(simplify("1 * ($.a << 1) - 0").to_string(), "($.a << 1)");
+            assert_eq!(simplify("true and $.a > 1").to_string(), "($.a > 1)");
+            assert_eq!(simplify("false or $.b == 'x'").to_string(), "($.b == \"x\")");
+            assert_eq!(simplify("!!($.a == 1)").to_string(), "($.a == 1)");
+            assert_eq!(simplify("!!(1 == 1)").to_string(), "true");
+        }
+
+        #[test]
+        fn identities_not_collapsed_for_unknown_types() {
+            for e in &["$.a + 0", "$.b * 1", "$.a - 0", "true and $.a", "$.a or false", "!!$.a"] {
+                let o = Opath::parse(e).unwrap();
+                assert_eq!(o.clone().simplify(), o);
+            }
+        }
+
+        #[test]
+        fn node_references_untouched() {
+            for e in &["$.a + $.b", "@.a * 2", "$var + 1", "${'v' + 1}", "env:HOME + 'x'", "@.*[@.a > 1]", "$.a.b[1]"] {
+                let o = Opath::parse(e).unwrap();
+                assert_eq!(o.clone().simplify(), o);
+            }
+        }
+
+        #[test]
+        fn same_results() {
+            let n = NodeRef::from_json(r#"{"a": 1, "b": "x", "arr": [1, 2, 3, {"a": 3}], "c": true}"#).unwrap();
+            let exprs = [
+                "2 + 3 * 4",
+                "10 / 4 - 1",
+                "-(2 + 1)",
+                "$.a + 2 * 3",
+                "$.b + ('y' + 'z')",
+                "$.arr[1 + 1]",
+                "$.arr.*[@ > 1 + 0]",
+                "$.arr[true and true]",
+                "$.c and (1 > 2 or true)",
+                "!(1 == 1)",
+                "1..(1 + 2)",
+                "($.a & 3) + 0",
+                "1 * ($.a << 1) - 0",
+                "true and $.a > 1",
+                "$.arr.* > 1 and true",
+                "false or $.b == 'x'",
+                "!!($.a == 1)",
+                "!!($.arr.* == 1)",
+            ];
+            for e in exprs.iter() {
+                let o = Opath::parse(e).unwrap();
+                let res1 = o.apply(&n, &n).unwrap().into_vec();
+                let res2 = o.simplify().apply(&n, &n).unwrap().into_vec();
+                assert_eq!(res1.len(), res2.len(), "{}", e);
+                for (a, b) in res1.iter().zip(res2.iter()) {
+                    assert!(a.is_identical_deep(b), "{}", e);
+                }
+            }
+        }
+    }
+
+    mod referenced_properties {
+        use super::*;
+
+        fn props(expr: &str) -> Vec<String> {
+            Opath::parse(expr).unwrap().referenced_properties()
+        }
+
+        #[test]
+        fn paths() {
+            assert_eq!(props("$.a + $.b"), vec!["a", "b"]);
+            assert_eq!(props("$.a.x + $.b[1] + $.a.y"), vec!["a", "b"]);
+            assert_eq!(props("$.a.*.name"), vec!["a"]);
+        }
+
+        #[test]
+        fn nested_filters() {
+            assert_eq!(props("$.items.*[@.price > $.limit].name"), vec!["items", "limit"]);
+            assert_eq!(props("array($.x, $.y.join($.sep))"), vec!["x", "y", "sep"]);
+        }
+
+        #[test]
+        fn relative_only() {
+            assert!(props("@.a + @.b").is_empty());
+            assert!(props("1 + 2").is_empty());
+        }
+    }
+
+    mod variables {
+        use super::*;
+
+        #[test]
+        fn static_names() {
+            let o = Opath::parse("$a + ${'b'} + $a + env:HOME + $.prop.find($c)").unwrap();
+            assert_eq!(o.variables(), vec!["a", "b", "c"]);
+        }
+
+        #[test]
+        fn dynamic_names() {
+            let o = Opath::parse("${$name} + ${@.key} + env:(@.env_name)").unwrap();
+            assert_eq!(o.variables(), vec!["name"]);
+            assert!(o.env_vars().is_empty());
+        }
+
+        #[test]
+        fn env_names() {
+            let o = Opath::parse("array(env:HOME, env:PATH, env:HOME)").unwrap();
+            assert_eq!(o.env_vars(), vec!["HOME", "PATH"]);
+            assert!(o.variables().is_empty());
+        }
+    }
+
+    mod apply_each {
+        use super::*;
+
+        #[test]
+        fn property() {
+            let n = NodeRef::from_json(r#"{"users": [{"name": "a"}, {"name": "b"}, {"id": 1}]}"#)
+                .unwrap();
+            let users = Opath::parse("$.users.*").unwrap().apply(&n, &n).unwrap().into_vec();
+
+            let res = Opath::parse("@.name").unwrap().apply_each(&n, &users).unwrap();
+
+            assert_eq!(res.len(), 3);
+            assert_eq!(res[0].len(), 1);
+            assert_eq!(res[0].iter().next().unwrap().as_string(), "a");
+            assert_eq!(res[1].len(), 1);
+            assert_eq!(res[1].iter().next().unwrap().as_string(), "b");
+            assert!(res[2].is_empty());
+        }
+
+        #[test]
+        fn empty() {
+            let n = NodeRef::null();
+            let res = Opath::parse("@.name").unwrap().apply_each(&n, &[]).unwrap();
+            assert!(res.is_empty());
+        }
+    }
+
+    mod into_predicate {
+        use super::*;
+
+        #[test]
+        fn matches_apply() {
+            let root = NodeRef::from_json(r#"[
+                {"role": "admin", "active": true},
+                {"role": "user", "active": true},
+                {"role": "admin", "active": false},
+                {"role": "guest"}
+            ]"#)
+            .unwrap();
+            let users = Opath::parse("$.*").unwrap().apply(&root, &root).unwrap().into_vec();
+
+            let expr = "@.role == 'admin' and @.active";
+            let pred = Opath::parse(expr).unwrap().into_predicate();
+            let opath = Opath::parse(expr).unwrap();
+
+            let res: Vec<bool> = users.iter().map(|u| pred(&root, u).unwrap()).collect();
+            assert_eq!(res, vec![true, false, false, false]);
+
+            for u in users.iter() {
+                let manual = opath.apply(&root, u).unwrap().into_one().unwrap().as_boolean();
+                assert_eq!(pred(&root, u).unwrap(), manual);
+            }
+        }
+
+        #[test]
+        fn empty_result_is_false() {
+            let root = NodeRef::from_json(r#"{"a": 1}"#).unwrap();
+            let pred = Opath::parse("@.missing").unwrap().into_predicate();
+            assert_eq!(pred(&root, &root).unwrap(), false);
+        }
+    }
+
+    mod apply_lvalues {
+        use super::*;
+
+        static TEST_JSON: &str = r#"{"a": 1, "b": {"c": 2}, "s": "text"}"#;
+
+        #[test]
+        fn properties_are_lvalues() {
+            let n = NodeRef::from_json(TEST_JSON).unwrap();
+            let res = Opath::parse("($.a, $.b.c)").unwrap().apply_lvalues(&n, &n).unwrap();
+            assert_eq!(res.len(), 2);
+            assert!(res[0].is_ref_eq(&n.get_child_key("a").unwrap()));
+        }
+
+        #[test]
+        fn computed_values_are_filtered_out() {
+            let n = NodeRef::from_json(TEST_JSON).unwrap();
+            let res = Opath::parse("($.a, $.a + 1, $.s.length(), $.b.c)")
+                .unwrap()
+                .apply_lvalues(&n, &n)
+                .unwrap();
+            assert_eq!(res.len(), 2);
+            assert_eq!(res[0].data().key(), "a");
+            assert_eq!(res[1].data().key(), "c");
+        }
+
+        #[test]
+        fn mutate_lvalues() {
+            let n = NodeRef::from_json(TEST_JSON).unwrap();
+            for r in Opath::parse("$.b.*").unwrap().apply_lvalues(&n, &n).unwrap() {
+                r.replace_with(NodeRef::string("changed")).unwrap();
+            }
+            assert_eq!(n.to_json(), r#"{"a":1,"b":{"c":"changed"},"s":"text"}"#);
+        }
+
+        #[test]
+        fn root_is_not_lvalue() {
+            let n = NodeRef::from_json(TEST_JSON).unwrap();
+            let res = Opath::parse("($, @, $.a)").unwrap().apply_lvalues(&n, &n).unwrap();
+            assert_eq!(res.len(), 1);
+            assert_eq!(res[0].data().key(), "a");
+        }
+    }
+
+    mod apply_with_budget {
+        use super::*;
+
+        fn large_tree() -> NodeRef {
+            let items: Vec<String> = (0..200)
+                .map(|i| format!(r#"{{"id": {}, "tags": ["a", "b", {{"deep": [1, 2, 3]}}]}}"#, i))
+                .collect();
+            NodeRef::from_json(&format!("[{}]", items.join(","))).unwrap()
+        }
+
+        #[test]
+        fn low_budget_aborts() {
+            let n = large_tree();
+            let budget = EvalBudget::new(100);
+            let err = Opath::parse("$.**").unwrap().apply_with_budget(&n, &n, &budget).unwrap_err();
+            let detail: &ExprErrorDetail = err.detail().downcast_ref().unwrap();
+            match *detail {
+                ExprErrorDetail::EvaluationBudgetExceeded { limit } => assert_eq!(limit, 100),
+                _ => panic!("EvaluationBudgetExceeded expected"),
+            }
+        }
+
+        #[test]
+        fn high_budget_succeeds() {
+            let n = large_tree();
+            let budget = EvalBudget::new(1_000_000);
+            let expr = Opath::parse("$.**").unwrap();
+            let res = expr.apply_with_budget(&n, &n, &budget).unwrap();
+            assert_eq!(res.len(), expr.apply(&n, &n).unwrap().len());
+            assert!(res.len() >= 2000);
+            assert!(budget.used() >= res.len());
+        }
+    }
+
+    mod key_list {
+        use super::*;
+
+        fn values(expr: &str, json: &str) -> Vec<String> {
+            let n = NodeRef::from_json(json).unwrap();
+            Opath::parse(expr)
+                .unwrap()
+                .apply(&n, &n)
+                .unwrap()
+                .into_vec()
+                .iter()
+                .map(|n| n.as_string())
+                .collect()
+        }
+
+        #[test]
+        fn object_keys() {
+            let json = r#"{"a": "1", "b": "2", "c": "3", "d": "4"}"#;
+            assert_eq!(values("$[a, b, c]", json), vec!["1", "2", "3"]);
+            assert_eq!(values("$[c, 'a']", json), vec!["3", "1"]);
+        }
+
+        #[test]
+        fn explicit_current_is_dynamic() {
+            let json = r#"{"a": "b", "b": "c", "c": "x", "i": 0, "j": 2, "arr": ["p", "q", "r"]}"#;
+            assert_eq!(values("$[@.a, @.b]", json), vec!["c", "x"]);
+            assert_eq!(values("$.arr[$.i, $.j]", json), vec!["p", "r"]);
+        }
+
+        #[test]
+        fn array_indices() {
+            let json = r#"{"arr": ["x", "y", "z", "w"]}"#;
+            assert_eq!(values("$.arr[0, 2, 3]", json), vec!["x", "z", "w"]);
+        }
+
+        #[test]
+        fn absent_keys() {
+            let json = r#"{"a": "1", "b": "2"}"#;
+            assert_eq!(values("$[a, missing, b]", json), vec!["1", "2"]);
+            assert!(values("$[x, y]", json).is_empty());
+        }
+    }
+
+    mod apply_traced {
+        use super::*;
+
+        #[test]
+        fn filter_steps() {
+            let n = NodeRef::from_json(r#"{"items": [{"price": 5}, {"price": 20}, {"price": 30}]}"#).unwrap();
+            let expr = Opath::parse("$.items[@.price > 10].price").unwrap();
+            let mut trace = Vec::new();
+            let res = expr.apply_traced(&n, &n, &mut trace).unwrap();
+            assert_eq!(res.len(), 2);
+
+            assert_eq!(trace[0].depth(), 0);
+            assert_eq!(trace[0].expr(), expr.to_string());
+            assert_eq!(trace[0].count(), 2);
+
+            // root, property, filter, then property applied to each match
+            let steps: Vec<(&str, usize)> = trace
+                .iter()
+                .filter(|e| e.depth() == 1)
+                .map(|e| (e.expr(), e.count()))
+                .collect();
+            assert_eq!(steps.len(), 5);
+            assert_eq!(steps[0], ("$", 1));
+            assert_eq!(steps[1], (".items", 1));
+            assert!(steps[2].0.starts_with('['));
+            assert_eq!(steps[2].1, 2);
+            assert_eq!(steps[3], (".price", 1));
+            assert_eq!(steps[4], (".price", 1));
+
+            // filter condition is evaluated for each array element
+            let filter_pos = trace.iter().position(|e| e.depth() == 1 && e.expr().starts_with('[')).unwrap();
+            assert!(trace[filter_pos + 1].depth() == 2);
+        }
+
+        #[test]
+        fn no_trace_by_default() {
+            let n = NodeRef::from_json(r#"{"a": 1}"#).unwrap();
+            let expr = Opath::parse("$.a").unwrap();
+            let mut trace = Vec::new();
+            expr.apply_traced(&n, &n, &mut trace).unwrap();
+            assert!(!trace.is_empty());
+            let len = trace.len();
+            expr.apply(&n, &n).unwrap();
+            assert_eq!(trace.len(), len);
+        }
+    }
+
+    mod child {
+        use super::*;
+
+        #[test]
+        fn build_path() {
+            let a = Opath::root().child_key("a");
+            assert_eq!(a.to_string(), "$.a");
+
+            let b = a.child_key("b");
+            assert_eq!(b.to_string(), "$.a.b");
+
+            let c = b.child_index(0);
+            assert_eq!(c.to_string(), "$.a.b[0]");
+            assert_eq!(c, Opath::parse("$.a.b[0]").unwrap());
+            assert!(a.is_ancestor_path(&c));
+        }
+
+        #[test]
+        fn quoted_key() {
+            let o = Opath::root().child_key("a b");
+            assert_eq!(o.to_string(), "$[\"a b\"]");
+        }
+
+        #[test]
+        fn relative_path() {
+            let o = Opath::current().child_key("a").child_index(1);
+            assert_eq!(o.to_string(), "@.a[1]");
+            assert_eq!(o, Opath::parse("@.a[1]").unwrap());
+        }
+    }
+
+    mod from_cached {
+        use super::*;
+
+        #[test]
+        fn equals_uncached() {
+            let n = NodeRef::from_json(r#"{"a": {"b": [1, {"c": 2}]}}"#).unwrap();
+            let nodes = Opath::parse("$.**").unwrap().apply(&n, &n).unwrap().into_vec();
+            let mut cache = NodePathCache::new();
+
+            for e in nodes.iter() {
+                assert_eq!(Opath::from_cached(e, &mut cache), Opath::from(e));
+            }
+            assert_eq!(cache.len(), nodes.len());
+        }
+
+        #[test]
+        fn populates_cache() {
+            let n = NodeRef::from_json(r#"{"a": {"b": 1}}"#).unwrap();
+            let b = n.get_child_key("a").unwrap().get_child_key("b").unwrap();
+            let mut cache = NodePathLruCache::with_size(4);
+
+            assert!(!cache.contains(&b));
+            let p = Opath::from_cached(&b, &mut cache);
+            assert!(cache.contains(&b));
+            assert_eq!(p.to_string(), "$.a.b");
+            assert_eq!(Opath::from_cached(&b, &mut cache), p);
+            assert_eq!(cache.len(), 1);
+        }
+    }
+
+    mod relative_to {
+        use super::*;
+
+        #[test]
+        fn prefix() {
+            let o = Opath::parse("$.prop1.arr[3].prop2").unwrap();
+            let base = Opath::parse("$.prop1").unwrap();
+            let r = o.relative_to(&base).unwrap();
+
+            assert_eq!(r.to_string(), "@.arr[3].prop2");
+            assert_eq!(r, Opath::parse("@.arr[3].prop2").unwrap());
+        }
+
+        #[test]
+        fn root_base() {
+            let o = Opath::parse("$.prop1.prop2").unwrap();
+            let r = o.relative_to(&Opath::root()).unwrap();
+
+            assert_eq!(r.to_string(), "@.prop1.prop2");
+        }
+
+        #[test]
+        fn non_prefix() {
+            let o = Opath::parse("$.prop1.arr[3]").unwrap();
+            let base = Opath::parse("$.prop2").unwrap();
+            assert!(o.relative_to(&base).is_none());
+
+            let base = Opath::parse("$.prop1.arr[3].prop2").unwrap();
+            assert!(o.relative_to(&base).is_none());
+        }
+
+        #[test]
+        fn exact_match() {
+            let o = Opath::parse("$.prop1.arr[3]").unwrap();
+            let r = o.relative_to(&o.clone()).unwrap();
+
+            assert_eq!(r, Opath::current());
+            assert_eq!(r.to_string(), "@");
+        }
+    }
 }
@@ -0,0 +1,188 @@
+use super::*;
+
+fn parse_selector(selector: &str) -> ExprResult<Opath> {
+    Opath::parse(selector).map_err_as_cause(|| ExprErrorDetail::InvalidSelector {
+        selector: selector.to_string(),
+    })
+}
+
+/// Replaces the value of every node matched by `selector` with a copy of `value`,
+/// returning the number of updated nodes. Matches that are not lvalues, i.e. values computed
+/// by the expression rather than nodes of the tree, are skipped.
+pub fn update_all(root: &NodeRef, selector: &str, value: NodeRef) -> ExprResult<usize> {
+    let res = parse_selector(selector)?.apply(root, root)?;
+    let mut count = 0;
+    for n in res.into_vec() {
+        // same check as `NodeSet::is_lvalue()`
+        if !n.is_consumable() {
+            n.replace_with(value.clone())?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// Removes every node matched by `selector` from its parent, returning the number of removed
+/// nodes. Matches without a parent are skipped.
+pub fn delete_all(root: &NodeRef, selector: &str) -> ExprResult<usize> {
+    let res = parse_selector(selector)?.apply(root, root)?;
+
+    let mut groups: Vec<(NodeRef, Vec<(usize, String)>)> = Vec::new();
+    for n in res.into_vec() {
+        let (parent, index, key) = {
+            let d = n.data();
+            (d.parent(), d.index(), d.key().to_string())
+        };
+        if let Some(p) = parent {
+            match groups.iter_mut().find(|(g, _)| g.is_ref_eq(&p)) {
+                Some((_, items)) => items.push((index, key)),
+                None => groups.push((p, vec![(index, key)])),
+            }
+        }
+    }
+
+    let mut count = 0;
+    for (p, mut items) in groups {
+        // remove back-to-front, so indices of pending items stay valid
+        items.sort_by(|a, b| b.0.cmp(&a.0));
+        items.dedup_by_key(|i| i.0);
+        let is_object = p.is_object();
+        let removed = p.remove_children(
+            false,
+            items.into_iter().map(|(index, key)| {
+                if is_object {
+                    (None, Some(key.into()))
+                } else {
+                    (Some(index), None)
+                }
+            }),
+        )?;
+        count += removed.len();
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static SERVICES_JSON: &str = r#"
+        {
+            "services": {
+                "web": {"replicas": 1, "ports": [80, 443]},
+                "db": {"replicas": 2, "ports": [5432]}
+            }
+        }
+    "#;
+
+    mod update_all {
+        use super::*;
+
+        #[test]
+        fn object_properties() {
+            let n = NodeRef::from_json(SERVICES_JSON).unwrap();
+
+            let count = update_all(&n, "$.services.*.replicas", NodeRef::integer(3)).unwrap();
+            assert_eq!(count, 2);
+
+            let res = Opath::parse("$.services.*.replicas").unwrap().apply(&n, &n).unwrap();
+            let res = res.into_vec();
+            assert_eq!(res.len(), 2);
+            assert!(res.iter().all(|r| r.as_integer() == Some(3)));
+        }
+
+        #[test]
+        fn array_elements() {
+            let n = NodeRef::from_json(SERVICES_JSON).unwrap();
+
+            let count = update_all(&n, "$.services.web.ports.*", NodeRef::string("any")).unwrap();
+            assert_eq!(count, 2);
+            assert_eq!(
+                n.to_json(),
+                r#"{"services":{"web":{"replicas":1,"ports":["any","any"]},"db":{"replicas":2,"ports":[5432]}}}"#
+            );
+
+            let e = n.get_child_key("services").unwrap().get_child_key("web").unwrap();
+            let e = e.get_child_key("ports").unwrap().get_child_index(1).unwrap();
+            assert_eq!(Opath::from(&e).to_string(), "$.services.web.ports[1]");
+        }
+
+        #[test]
+        fn non_lvalues_skipped() {
+            let n = NodeRef::from_json(SERVICES_JSON).unwrap();
+
+            let count = update_all(&n, "$.services.web.replicas + 1", NodeRef::integer(0)).unwrap();
+            assert_eq!(count, 0);
+            assert_eq!(n.get_child_key("services").unwrap().get_child_key("web").unwrap()
+                .get_child_key("replicas").unwrap().as_integer(), Some(1));
+        }
+
+        #[test]
+        fn invalid_selector() {
+            let n = NodeRef::from_json(SERVICES_JSON).unwrap();
+
+            let err = update_all(&n, "$.services[", NodeRef::integer(0)).unwrap_err();
+            let detail: &ExprErrorDetail = err.detail().downcast_ref().unwrap();
+            match *detail {
+                ExprErrorDetail::InvalidSelector { ref selector } => assert_eq!(selector, "$.services["),
+                _ => panic!("Wrong error kind"),
+            }
+        }
+    }
+
+    mod delete_all {
+        use super::*;
+
+        #[test]
+        fn array_elements_by_predicate() {
+            let n = NodeRef::from_json(r#"
+                {
+                    "items": [
+                        {"id": 1, "deprecated": true},
+                        {"id": 2},
+                        {"id": 3, "deprecated": true},
+                        {"id": 4, "deprecated": false},
+                        {"id": 5, "deprecated": true}
+                    ]
+                }
+            "#).unwrap();
+
+            let count = delete_all(&n, "$.items.*[@.deprecated]").unwrap();
+            assert_eq!(count, 3);
+
+            let items = n.get_child_key("items").unwrap();
+            assert_eq!(items.to_json(), r#"[{"id":2},{"id":4,"deprecated":false}]"#);
+            for i in 0..2 {
+                let e = items.get_child_index(i).unwrap();
+                assert_eq!(e.data().index(), i);
+                assert_eq!(e.data().key(), i.to_string());
+                assert!(e.data().parent().unwrap().is_ref_eq(&items));
+            }
+        }
+
+        #[test]
+        fn object_properties() {
+            let n = NodeRef::from_json(SERVICES_JSON).unwrap();
+
+            let count = delete_all(&n, "$.services.*.ports").unwrap();
+            assert_eq!(count, 2);
+            assert_eq!(n.to_json(), r#"{"services":{"web":{"replicas":1},"db":{"replicas":2}}}"#);
+
+            let db = n.get_child_key("services").unwrap().get_child_key("db").unwrap();
+            assert_eq!(db.data().index(), 1);
+
+            let count = delete_all(&n, "$.services.web").unwrap();
+            assert_eq!(count, 1);
+            assert_eq!(db.data().index(), 0);
+            assert_eq!(Opath::from(&db).to_string(), "$.services.db");
+        }
+
+        #[test]
+        fn root_skipped() {
+            let n = NodeRef::from_json(SERVICES_JSON).unwrap();
+
+            assert_eq!(delete_all(&n, "$").unwrap(), 0);
+            assert_eq!(delete_all(&n, "$.missing").unwrap(), 0);
+        }
+    }
+}
@@ -275,11 +275,31 @@ pub enum Terminal {
 impl LexTerm for Terminal {}
 
 
+/// Controls how the parser handles keys defined more than once in the same object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateKeyPolicy {
+    /// Report `RedefinedKey` error.
+    Error,
+    /// Keep the last value.
+    LastWins,
+    /// Keep the first value.
+    First,
+    /// Collect all values into an array, in document order.
+    Array,
+}
+
+impl Default for DuplicateKeyPolicy {
+    fn default() -> Self {
+        DuplicateKeyPolicy::Error
+    }
+}
+
 #[derive(Debug)]
 pub struct Parser {
     num_parser: NumberParser,
     token_queue: VecDeque<Token>,
     buf: String,
+    duplicate_key_policy: DuplicateKeyPolicy,
 }
 
 impl Parser {
@@ -295,9 +315,23 @@ impl Parser {
             num_parser: num,
             token_queue: VecDeque::new(),
             buf: String::new(),
+            duplicate_key_policy: DuplicateKeyPolicy::default(),
         }
     }
 
+    pub fn with_duplicate_key_policy(mut self, policy: DuplicateKeyPolicy) -> Self {
+        self.duplicate_key_policy = policy;
+        self
+    }
+
+    pub fn duplicate_key_policy(&self) -> DuplicateKeyPolicy {
+        self.duplicate_key_policy
+    }
+
+    pub fn set_duplicate_key_policy(&mut self, policy: DuplicateKeyPolicy) {
+        self.duplicate_key_policy = policy;
+    }
+
     fn lex(&mut self, r: &mut dyn CharReader) -> Result<Token, Error> {
         fn consume(r: &mut dyn CharReader, count: usize, term: Terminal) -> Result<Token, Error> {
             let p1 = r.position();
@@ -435,12 +469,22 @@ impl Parser {
     fn parse_object(&mut self, r: &mut dyn CharReader) -> Result<NodeRef, Error> {
         let p1 = self.expect_token(r, Terminal::BraceLeft)?.start();
         let mut props = Properties::new();
+        // values of duplicated keys collected with `DuplicateKeyPolicy::Array`
+        let mut collected: Vec<(Symbol, Elements)> = Vec::new();
         let mut comma = false;
         let mut literal = true;
         loop {
             let t = self.next_token(r)?;
             match t.term() {
                 Terminal::BraceRight if (comma || literal) => {
+                    for (key, elems) in collected {
+                        let span = elems[0].data().metadata().span();
+                        let mut array = NodeRef::array(elems);
+                        if let Some(span) = span {
+                            array = array.with_span(span);
+                        }
+                        props.insert(key, array);
+                    }
                     let span = Span {
                         start: p1,
                         end: t.end(),
@@ -455,10 +499,26 @@ impl Parser {
                     let key = Symbol::from(&self.buf);
                     self.expect_token(r, Terminal::Colon)?;
                     let value = self.parse_value(r)?;
-                    if let Some(child) = props.get(&key) {
-                        return ParseErrorDetail::key_redefined_node(r, t.span(), &child, &key);
+                    let insert = match props.get(&key) {
+                        None => true,
+                        Some(child) => match self.duplicate_key_policy {
+                            DuplicateKeyPolicy::Error => {
+                                return ParseErrorDetail::key_redefined_node(r, t.span(), &child, &key);
+                            }
+                            DuplicateKeyPolicy::LastWins => true,
+                            DuplicateKeyPolicy::First => false,
+                            DuplicateKeyPolicy::Array => {
+                                match collected.iter_mut().find(|(k, _)| *k == key) {
+                                    Some((_, elems)) => elems.push(value.clone()),
+                                    None => collected.push((key.clone(), vec![child.clone(), value.clone()])),
+                                }
+                                false
+                            }
+                        },
+                    };
+                    if insert {
+                        props.insert(key, value);
                     }
-                    props.insert(key, value);
                     comma = true;
                     literal = false;
                 }
@@ -3,7 +3,7 @@ use super::*;
 use kg_display::ListDisplay;
 
 use crate::serial::fmt::toml::Terminal::BracketLeft;
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 
 pub type Error = ParseDiag;
 
@@ -446,6 +446,10 @@ pub struct Parser {
     /// Nodes that cannot be defined again
     /// but can be used in dotted keys
     defined_nodes: Vec<NodeRef>,
+    /// Preserve comments as node trivia
+    trivia: bool,
+    /// Comment lines waiting to be attached to the next key or table
+    trivia_buf: String,
 }
 
 impl Parser {
@@ -455,6 +459,30 @@ impl Parser {
             buf: String::new(),
             static_arrays: vec![],
             defined_nodes: vec![],
+            trivia: false,
+            trivia_buf: String::new(),
+        }
+    }
+
+    pub fn with_trivia(mut self, trivia: bool) -> Self {
+        self.set_trivia(trivia);
+        self
+    }
+
+    pub fn is_trivia(&self) -> bool {
+        self.trivia
+    }
+
+    /// When set, comment lines directly preceding a key or table header are attached
+    /// to the corresponding node (see `Metadata::trivia()`).
+    pub fn set_trivia(&mut self, trivia: bool) {
+        self.trivia = trivia
+    }
+
+    fn attach_trivia(&mut self, node: &NodeRef) {
+        if !self.trivia_buf.is_empty() {
+            let trivia = std::mem::take(&mut self.trivia_buf);
+            node.data_mut().metadata_mut().set_trivia(Some(trivia));
         }
     }
 
@@ -798,6 +826,7 @@ impl Parser {
 
     pub fn parse(&mut self, r: &mut dyn CharReader) -> Result<NodeRef, Error> {
         self.token_queue.clear();
+        self.trivia_buf.clear();
         let mut root = NodeRef::object(Properties::new());
         self.parse_inner(r, &mut root)?;
         Ok(root)
@@ -805,10 +834,12 @@ impl Parser {
 
     fn parse_inner(&mut self, r: &mut dyn CharReader, parent: &mut NodeRef) -> Result<(), Error> {
         let mut current = parent.clone();
+        let mut prev = Terminal::Newline;
 
         loop {
             let t = self.next_token(r)?;
-            match t.term() {
+            let term = t.term();
+            match term {
                 Terminal::BareKey | Terminal::Integer | Terminal::Float => {
                     self.push_token(t);
                     self.parse_kv(r, &mut current)?;
@@ -836,9 +867,21 @@ impl Parser {
                         self.defined_nodes.push(node.clone());
                         current = node;
                     }
+                    self.attach_trivia(&current);
+                }
+                Terminal::Newline => {
+                    // a blank line separates comments from the following key
+                    if prev == Terminal::Newline {
+                        self.trivia_buf.clear();
+                    }
+                }
+                Terminal::Comment => {
+                    if self.trivia {
+                        let comment = r.slice_pos(t.start(), t.end())?;
+                        self.trivia_buf.push_str(comment.trim_end());
+                        self.trivia_buf.push('\n');
+                    }
                 }
-                Terminal::Newline => {}
-                Terminal::Comment => {}
                 Terminal::End => return Ok(()),
                 _ => {
                     return ParseErrDetail::unexpected_token_many(
@@ -855,6 +898,7 @@ impl Parser {
                     );
                 }
             };
+            prev = term;
         }
     }
 
@@ -955,6 +999,7 @@ impl Parser {
         let (node, key) = self.parse_key(r, parent)?;
         self.expect_token(r, Terminal::Equals)?;
         let val = self.parse_value(r, parent)?;
+        self.attach_trivia(&val);
         self.defined_nodes.push(val.clone());
         node.add_child(None, Some(key.into()), val).unwrap();
         let next = self.next_token(r)?;
@@ -1279,6 +1324,162 @@ impl Parser {
     }
 }
 
+/// Splits TOML key (e.g. table header) into segments, unquoting quoted segments.
+fn split_key(key: &str) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut seg = String::new();
+    let mut quote = None;
+    let mut chars = key.trim().chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some('"'), '\\') => {
+                if let Some(c) = chars.next() {
+                    seg.push(c);
+                }
+            }
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => seg.push(c),
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '.') => segments.push(std::mem::take(&mut seg)),
+            (None, c) if c.is_whitespace() => {}
+            (None, c) => seg.push(c),
+        }
+    }
+    segments.push(seg);
+    segments
+}
+
+/// Returns key of `key = value` line, or `None` if the line is not a key-value pair.
+fn line_key(line: &str) -> Option<String> {
+    let line = line.trim_start();
+    let end = if line.starts_with('"') || line.starts_with('\'') {
+        let q = line.chars().next().unwrap();
+        let mut escaped = false;
+        line.char_indices().skip(1).find(|&(_, c)| {
+            let end = !escaped && c == q;
+            escaped = !escaped && q == '"' && c == '\\';
+            end
+        })?.0 + 1
+    } else {
+        line.find(|c: char| c == '=' || c.is_whitespace())?
+    };
+    if line[end..].trim_start().starts_with('=') {
+        split_key(&line[..end]).pop()
+    } else {
+        None
+    }
+}
+
+/// Returns the multi-line string delimiter (`"""` or `'''`) still open at the end of `line`,
+/// given the one open at its beginning.
+fn open_multiline_string(line: &str, mut open: Option<&'static str>) -> Option<&'static str> {
+    let b = line.as_bytes();
+    let mut i = 0;
+    while i < b.len() {
+        match open {
+            Some(delim) => {
+                if b[i] == b'\\' && delim == "\"\"\"" {
+                    i += 2;
+                } else if b[i..].starts_with(delim.as_bytes()) {
+                    open = None;
+                    i += 3;
+                } else {
+                    i += 1;
+                }
+            }
+            None => match b[i] {
+                b'#' => break,
+                q @ b'"' | q @ b'\'' => {
+                    if b[i..].starts_with(&[q, q, q]) {
+                        open = Some(if q == b'"' { "\"\"\"" } else { "'''" });
+                        i += 3;
+                    } else {
+                        i += 1;
+                        while i < b.len() && b[i] != q {
+                            if b[i] == b'\\' && q == b'"' {
+                                i += 1;
+                            }
+                            i += 1;
+                        }
+                        i += 1;
+                    }
+                }
+                _ => i += 1,
+            },
+        }
+    }
+    open
+}
+
+/// Re-inserts comments stored as trivia in `root` tree into `toml`, which must be
+/// a serialized form of `root`.
+pub(crate) fn insert_trivia(root: &NodeRef, toml: &str) -> String {
+    fn write_trivia(n: &NodeRef, out: &mut String) {
+        if let Some(t) = n.data().metadata().trivia() {
+            out.push_str(t);
+        }
+    }
+
+    let mut out = String::with_capacity(toml.len());
+    let mut table = Some(root.clone());
+    // number of elements opened so far for each array of tables, by key path
+    let mut counters: HashMap<Vec<String>, usize> = HashMap::new();
+    let mut multiline = None;
+
+    for line in toml.lines() {
+        // lines inside multi-line strings are copied as they are
+        let in_string = multiline.is_some();
+        multiline = open_multiline_string(line, multiline);
+        if in_string {
+            out.push_str(line);
+            out.push('\n');
+            continue;
+        }
+
+        let trimmed = line.trim();
+        let header = if trimmed.starts_with("[[") && trimmed.ends_with("]]") {
+            Some((&trimmed[2..trimmed.len() - 2], true))
+        } else if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            Some((&trimmed[1..trimmed.len() - 1], false))
+        } else {
+            None
+        };
+
+        if let Some((key, array)) = header {
+            let segments = split_key(key);
+            if array {
+                *counters.entry(segments.clone()).or_insert(0) += 1;
+                // a new element starts, so arrays of tables nested in it start from scratch
+                counters.retain(|k, _| k.len() <= segments.len() || !k.starts_with(&segments));
+            }
+            // elements of arrays of tables are resolved to the most recently opened one
+            let mut n = Some(root.clone());
+            for (i, seg) in segments.iter().enumerate() {
+                n = n.and_then(|n| n.get_child_key(seg)).and_then(|c| {
+                    if c.is_array() {
+                        let count = counters.get(&segments[..=i]).cloned().unwrap_or(0);
+                        c.get_child_index(count.max(1) - 1)
+                    } else {
+                        Some(c)
+                    }
+                });
+            }
+            if let Some(ref n) = n {
+                write_trivia(n, &mut out);
+            }
+            table = n;
+        } else if let Some(key) = line_key(line) {
+            if let Some(n) = table.as_ref().and_then(|t| t.get_child_key(&key)) {
+                write_trivia(&n, &mut out);
+            }
+        }
+
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -11,6 +11,7 @@ mod ser;
 
 pub mod json {
     use super::*;
+    pub use fmt::json::DuplicateKeyPolicy;
     pub use fmt::json::ParseErrorDetail as JsonParseErrorDetail;
     pub use fmt::json::Parser as JsonParser;
     pub use fmt::json::Terminal;
@@ -23,6 +24,7 @@ pub mod toml {
 
     pub use fmt::toml::ParseErrDetail as TomlParseErrDetail;
     pub use fmt::toml::Parser as TomlParser;
+    pub(crate) use fmt::toml::insert_trivia;
 
     pub fn from_str<'de, T>(toml: &'de str) -> Result<T, ParseDiag>
     where
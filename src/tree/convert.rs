@@ -45,3 +45,45 @@ impl Primitive for bool {
         false
     }
 }
+
+fn file_format(file_path: &Path) -> FileFormat {
+    file_path
+        .extension()
+        .map_or(FileFormat::Text, |ext| FileFormat::from(ext.to_string_lossy().as_ref()))
+}
+
+/// Converts file `from_path` to `to_path`, with formats detected from file extensions.
+/// If either of the files is binary or text, the content is copied unchanged.
+pub fn convert_file(from_path: &Path, to_path: &Path) -> TreeResult<()> {
+    let from_format = file_format(from_path);
+    let to_format = file_format(to_path);
+
+    let is_raw = |f: FileFormat| f == FileFormat::Binary || f == FileFormat::Text;
+    if is_raw(from_format) || is_raw(to_format) {
+        let f = fs::FileBuffer::open(from_path)?;
+        fs::write(to_path, f.as_slice())?;
+    } else {
+        let n = NodeRef::from_file(from_path, Some(from_format))?;
+        fs::write(to_path, n.reformat(to_format)?)?;
+    }
+    Ok(())
+}
+
+impl NodeRef {
+    /// Serializes this node to `format`. JSON output is pretty-printed.
+    pub fn reformat(&self, format: FileFormat) -> TreeResult<String> {
+        match format {
+            FileFormat::Json => {
+                self.check_serializable(format)?;
+                serde_json::to_string_pretty(self).map_err(|err| serialization_err(format, err))
+            }
+            FileFormat::Yaml => self.try_to_yaml(),
+            FileFormat::Toml => self.try_to_toml(),
+            FileFormat::JsonLines => {
+                self.check_serializable(format)?;
+                Ok(self.to_json_lines())
+            }
+            FileFormat::Binary | FileFormat::Text => Ok(self.to_format(format, false)),
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use std::sync::Arc;
+
+use super::*;
+
+/// Immutable, thread-safe snapshot of a node tree.
+///
+/// Unlike `NodeRef`, `FrozenNode` is `Send + Sync`, so a single snapshot can be shared
+/// between threads (e.g. with `Arc` or rayon) and queried concurrently with [`apply`].
+/// Only values are captured; metadata such as spans and file info is not preserved.
+///
+/// [`apply`]: FrozenNode::apply
+#[derive(Debug, Clone)]
+pub struct FrozenNode(Arc<FrozenValue>);
+
+#[derive(Debug)]
+pub enum FrozenValue {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Binary(Vec<u8>),
+    Array(Vec<FrozenNode>),
+    Object(Vec<(String, FrozenNode)>),
+}
+
+impl FrozenNode {
+    pub fn new(n: &NodeRef) -> FrozenNode {
+        let value = match *n.data().value() {
+            Value::Null => FrozenValue::Null,
+            Value::Boolean(b) => FrozenValue::Boolean(b),
+            Value::Integer(i) => FrozenValue::Integer(i),
+            Value::Float(f) => FrozenValue::Float(f),
+            Value::String(ref s) => FrozenValue::String(s.clone()),
+            Value::Binary(ref b) => FrozenValue::Binary(b.clone()),
+            Value::Array(ref elems) => {
+                FrozenValue::Array(elems.iter().map(FrozenNode::new).collect())
+            }
+            Value::Object(ref props) => FrozenValue::Object(
+                props
+                    .iter()
+                    .map(|(k, v)| (k.as_ref().to_string(), FrozenNode::new(v)))
+                    .collect(),
+            ),
+        };
+        FrozenNode(Arc::new(value))
+    }
+
+    pub fn value(&self) -> &FrozenValue {
+        &self.0
+    }
+
+    /// Creates a new mutable tree from this snapshot.
+    pub fn thaw(&self) -> NodeRef {
+        match *self.0 {
+            FrozenValue::Null => NodeRef::null(),
+            FrozenValue::Boolean(b) => NodeRef::boolean(b),
+            FrozenValue::Integer(i) => NodeRef::integer(i),
+            FrozenValue::Float(f) => NodeRef::float(f),
+            FrozenValue::String(ref s) => NodeRef::string(s.as_str()),
+            FrozenValue::Binary(ref b) => NodeRef::binary(b.as_slice()),
+            FrozenValue::Array(ref elems) => {
+                NodeRef::array(elems.iter().map(|e| e.thaw()).collect())
+            }
+            FrozenValue::Object(ref props) => {
+                let mut map = Properties::with_capacity(props.len());
+                for (k, v) in props.iter() {
+                    map.insert(k.as_str().into(), v.thaw());
+                }
+                NodeRef::object(map)
+            }
+        }
+    }
+
+    /// Evaluates `expr` against this snapshot, using it both as root and current node.
+    ///
+    /// The whole snapshot is thawed on the calling thread for every call, so each query costs
+    /// O(tree size), and the results are frozen copies detached from the tree (without key,
+    /// index or parent). To evaluate many expressions use [`apply_par`], which thaws the
+    /// snapshot only once per worker thread.
+    ///
+    /// [`apply_par`]: FrozenNode::apply_par
+    pub fn apply(&self, expr: &Opath) -> ExprResult<Vec<FrozenNode>> {
+        let root = self.thaw();
+        apply_thawed(&root, expr)
+    }
+
+    /// Evaluates each of `exprs` against this snapshot, spreading them over up to `threads`
+    /// worker threads. Every worker thaws the snapshot once and evaluates its share of
+    /// expressions. Results are returned in the same order as `exprs`.
+    pub fn apply_par(&self, exprs: &[Opath], threads: usize) -> Vec<ExprResult<Vec<FrozenNode>>> {
+        if exprs.is_empty() {
+            return Vec::new();
+        }
+        let threads = threads.max(1).min(exprs.len());
+        let chunk_size = (exprs.len() + threads - 1) / threads;
+        std::thread::scope(|s| {
+            let handles: Vec<_> = exprs
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    s.spawn(move || {
+                        let root = self.thaw();
+                        chunk.iter().map(|e| apply_thawed(&root, e)).collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+                .collect()
+        })
+    }
+
+    /// Compares this snapshot with `other` without thawing it, leaf values are compared
+    /// like in [`NodeRef::is_equal`].
+    pub fn is_equal_deep(&self, other: &NodeRef) -> bool {
+        let b = other.data();
+        match (&*self.0, b.value()) {
+            (&FrozenValue::Object(ref ap), &Value::Object(ref bp)) => {
+                ap.len() == bp.len()
+                    && ap.iter().all(|(k, va)| match bp.get(k.as_str()) {
+                        Some(vb) => va.is_equal_deep(vb),
+                        None => false,
+                    })
+            }
+            (&FrozenValue::Array(ref ae), &Value::Array(ref be)) => {
+                ae.len() == be.len()
+                    && ae.iter().zip(be.iter()).all(|(va, vb)| va.is_equal_deep(vb))
+            }
+            _ => {
+                drop(b);
+                self.thaw().is_equal(other)
+            }
+        }
+    }
+}
+
+fn apply_thawed(root: &NodeRef, expr: &Opath) -> ExprResult<Vec<FrozenNode>> {
+    let res = expr.apply(root, root)?;
+    Ok(res.iter().map(FrozenNode::new).collect())
+}
+
+impl<'a> From<&'a NodeRef> for FrozenNode {
+    fn from(n: &'a NodeRef) -> Self {
+        FrozenNode::new(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static TEST_JSON: &str = r#"
+        {
+            "hosts": [
+                {"name": "a", "port": 80},
+                {"name": "b", "port": 443},
+                {"name": "c", "port": 8080}
+            ]
+        }
+    "#;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn is_send_sync() {
+        assert_send_sync::<FrozenNode>();
+    }
+
+    #[test]
+    fn thaw_round_trip() {
+        let n = NodeRef::from_json(TEST_JSON).unwrap();
+        let f = FrozenNode::new(&n);
+        assert!(f.is_equal_deep(&n));
+    }
+
+    #[test]
+    fn apply_across_threads() {
+        let n = NodeRef::from_json(TEST_JSON).unwrap();
+        let expected: Vec<String> = Opath::parse("$.hosts[@.port > 100].name")
+            .unwrap()
+            .apply(&n, &n)
+            .unwrap()
+            .into_vec()
+            .iter()
+            .map(|n| n.as_string())
+            .collect();
+        assert_eq!(expected, vec!["b", "c"]);
+
+        let f = Arc::new(FrozenNode::new(&n));
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let f = f.clone();
+                std::thread::spawn(move || {
+                    let expr = Opath::parse("$.hosts[@.port > 100].name").unwrap();
+                    f.apply(&expr)
+                        .unwrap()
+                        .iter()
+                        .map(|n| n.thaw().as_string())
+                        .collect::<Vec<String>>()
+                })
+            })
+            .collect();
+
+        for h in handles {
+            assert_eq!(h.join().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn apply_par_matches_apply() {
+        let n = NodeRef::from_json(TEST_JSON).unwrap();
+        let f = FrozenNode::new(&n);
+        let exprs: Vec<Opath> = ["$.hosts.*.name", "$.hosts[@.port > 100].port", "$.hosts.missing"]
+            .iter()
+            .map(|e| Opath::parse(e).unwrap())
+            .collect();
+
+        for threads in 1..5 {
+            let res = f.apply_par(&exprs, threads);
+            assert_eq!(res.len(), exprs.len());
+            for (e, r) in exprs.iter().zip(res.iter()) {
+                let expected = e.apply(&n, &n).unwrap().into_vec();
+                let r = r.as_ref().unwrap();
+                assert_eq!(r.len(), expected.len());
+                for (a, b) in r.iter().zip(expected.iter()) {
+                    assert!(a.is_equal_deep(b));
+                }
+            }
+        }
+        assert!(f.apply_par(&[], 4).is_empty());
+    }
+
+    #[test]
+    fn is_equal_deep_without_thaw() {
+        let n = NodeRef::from_json(TEST_JSON).unwrap();
+        let f = FrozenNode::new(&n);
+        let other = NodeRef::from_json(r#"{"hosts": [{"name": "a", "port": 80}]}"#).unwrap();
+        assert!(f.is_equal_deep(&n));
+        assert!(!f.is_equal_deep(&other));
+    }
+}
@@ -14,6 +14,7 @@ pub enum FileFormat {
     Json,
     Yaml,
     Toml,
+    JsonLines,
 }
 
 impl FileFormat {
@@ -22,6 +23,11 @@ impl FileFormat {
             FileFormat::Text
         } else if f.eq_ignore_ascii_case("json") {
             FileFormat::Json
+        } else if f.eq_ignore_ascii_case("jsonl")
+            || f.eq_ignore_ascii_case("ndjson")
+            || f.eq_ignore_ascii_case("jsonlines")
+        {
+            FileFormat::JsonLines
         } else if f.eq_ignore_ascii_case("yaml") || f.eq_ignore_ascii_case("yml") {
             FileFormat::Yaml
         } else if f.eq_ignore_ascii_case("toml") {
@@ -54,6 +60,7 @@ impl std::fmt::Display for FileFormat {
             FileFormat::Json => write!(f, "json"),
             FileFormat::Yaml => write!(f, "yaml"),
             FileFormat::Toml => write!(f, "toml"),
+            FileFormat::JsonLines => write!(f, "jsonl"),
         }
     }
 }
@@ -220,6 +227,7 @@ pub struct Metadata {
     key: Symbol,
     file: Option<FileInfo>,
     span: Option<Box<Span>>,
+    trivia: Option<Box<String>>,
 }
 
 impl Metadata {
@@ -230,6 +238,7 @@ impl Metadata {
             key: Symbol::default(),
             file: None,
             span: None,
+            trivia: None,
         }
     }
 
@@ -280,6 +289,16 @@ impl Metadata {
         self.span = span.map(|s| Box::new(s));
     }
 
+    /// Comment lines preceding this node in the source file, if comments were preserved
+    /// while parsing.
+    pub fn trivia(&self) -> Option<&str> {
+        self.trivia.as_ref().map(|t| t.as_str())
+    }
+
+    pub fn set_trivia(&mut self, trivia: Option<String>) {
+        self.trivia = trivia.map(Box::new);
+    }
+
     pub(super) fn detach(&mut self) {
         self.parent = None;
         self.index = 0;
@@ -293,16 +312,22 @@ impl Metadata {
             key: Symbol::default(),
             file: self.file.clone(),
             span: self.span.clone(),
+            trivia: self.trivia.clone(),
         }
     }
 }
 
 impl HeapSizeOf for Metadata {
     fn heap_size_of_children(&self) -> usize {
-        if self.span.is_some() {
+        let span = if self.span.is_some() {
             std::mem::size_of::<Span>()
         } else {
             0
-        }
+        };
+        let trivia = match self.trivia {
+            Some(ref t) => std::mem::size_of::<String>() + t.heap_size_of_children(),
+            None => 0,
+        };
+        span + trivia
     }
 }
@@ -8,16 +8,20 @@ use serde::de;
 use serde::ser;
 use serde::ser::{SerializeMap, SerializeSeq};
 
-use super::opath::Opath;
+use super::diff::DiffSummary;
+use super::opath::{ExprResult, Interpolation, Opath, ResolveStrategy, Scope, TreeResolver};
 use super::*;
 use crate::tree::TreeErrorDetail::{
-    AddChildInvalidType, ExtendIncompatibleTypes, RemoveChildInvalidType,
+    AddChildInvalidType, ExtendIncompatibleTypes, RemoveChildInvalidType, ReorderKeysInvalidType,
 };
 use std::str::Utf8Error;
 
 pub mod convert;
+pub mod frozen;
 pub mod metadata;
 pub mod node;
+pub mod schema;
+pub mod source_map;
 
 pub type TreeError = BasicDiag;
 pub type TreeResult<T> = Result<T, TreeError>;
@@ -48,11 +52,35 @@ pub enum TreeErrorDetail {
         format: FileFormat,
     },
 
+    #[display(fmt = "cannot serialize node to '{format}': {err}")]
+    SerializationErr { format: FileFormat, err: String },
+
+    #[display(fmt = "cannot merge {source} into {target}")]
+    MergeConflict { target: Kind, source: Kind },
+
+    #[display(fmt = "cannot reorder keys of type '{kind}'")]
+    ReorderKeysInvalidType { kind: Kind },
+
+    #[display(fmt = "cannot deserialize node from json line {line}")]
+    JsonLineErr { line: usize },
+
     //FIXME ws to be removed
     #[display(fmt = "Error in line '{_0}'")]
     Undef(u32),
 }
 
+/// Controls how values are combined by `NodeRef::merge()`. Objects are always merged
+/// recursively, key by key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// Merged values replace existing ones.
+    Replace,
+    /// Like `Replace`, but arrays are appended to existing arrays.
+    Append,
+    /// Arrays are appended, any other collision is an error.
+    NoOverwrite,
+}
+
 #[derive(Debug)]
 pub struct Context {
     base_path: PathBuf,
@@ -178,11 +206,42 @@ impl NodeRef {
         parser.parse(&mut r)
     }
 
+    /// Parses JSON directly from bytes, without converting them to `&str` first. Input is
+    /// decoded by the reader while parsing, so invalid UTF-8 is reported as a parse error.
+    pub fn from_json_bytes(b: &[u8]) -> Result<NodeRef, ParseDiag> {
+        let mut parser = serial::json::JsonParser::new();
+        let mut r = MemCharReader::new(b);
+        parser.parse(&mut r)
+    }
+
+    /// Parses newline-delimited JSON (NDJSON) into an array node, one element per line.
+    /// Blank lines are skipped, errors are reported with 1-based line numbers.
+    pub fn from_json_lines(s: &str) -> TreeResult<NodeRef> {
+        let mut elems = Vec::new();
+        for (i, line) in s.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let n = NodeRef::from_json(line)
+                .map_err_as_cause(|| TreeErrorDetail::JsonLineErr { line: i + 1 })?;
+            elems.push(n);
+        }
+        Ok(NodeRef::array(elems))
+    }
+
     pub fn from_yaml(s: &str) -> Result<NodeRef, ParseDiag> {
         // TODO use custom implementation from kg_tree
         serde_yaml::from_str(s).map_err(|err| {
-            println!("{}", err);
-            panic!("Error deserializing Node from yaml")
+            let pos = match err.location() {
+                Some(l) => Position::with(l.index(), l.line() as u32, l.column() as u32),
+                None => Position::new(),
+            };
+            ParseDiag::from(kg_diag::parse::ParseErrorDetail::UnexpectedInput {
+                pos,
+                found: None,
+                expected: None,
+                task: format!("parsing yaml: {}", err),
+            })
         })
     }
 
@@ -192,11 +251,20 @@ impl NodeRef {
         parser.parse(&mut r)
     }
 
+    /// Parses TOML, keeping comment lines preceding keys and table headers as node trivia,
+    /// so they can be written back with `to_toml_with_trivia()`.
+    pub fn from_toml_with_trivia(s: &str) -> Result<NodeRef, ParseDiag> {
+        let mut parser = serial::toml::TomlParser::new().with_trivia(true);
+        let mut r = MemCharReader::new(s.as_bytes());
+        parser.parse(&mut r)
+    }
+
     pub fn from_str(s: Cow<'_, str>, format: FileFormat) -> TreeResult<NodeRef> {
         let res = match format {
             FileFormat::Json => NodeRef::from_json(&s),
             FileFormat::Yaml => NodeRef::from_yaml(&s),
             FileFormat::Toml => NodeRef::from_toml(&s),
+            FileFormat::JsonLines => return NodeRef::from_json_lines(&s),
             FileFormat::Text => Ok(NodeRef::string(s)),
             FileFormat::Binary => Ok(NodeRef::binary(s.as_bytes())),
         };
@@ -216,6 +284,7 @@ impl NodeRef {
             FileFormat::Json => NodeRef::from_json(to_str(s)?),
             FileFormat::Yaml => NodeRef::from_yaml(to_str(s)?),
             FileFormat::Toml => NodeRef::from_toml(to_str(s)?),
+            FileFormat::JsonLines => return NodeRef::from_json_lines(to_str(s)?),
             FileFormat::Text => Ok(NodeRef::string(to_str(s)?)),
             FileFormat::Binary => Ok(NodeRef::binary(s)),
         };
@@ -246,6 +315,12 @@ impl NodeRef {
         Ok(n)
     }
 
+    pub fn from_reader(r: &mut dyn std::io::Read, format: FileFormat) -> TreeResult<NodeRef> {
+        let mut buf = Vec::new();
+        r.read_to_end(&mut buf).map_err(IoErrorDetail::from)?;
+        NodeRef::from_bytes(&buf, format)
+    }
+
     pub fn to_type<'de, T>(&self) -> Result<T, serial::Error>
     where
         T: serde::Deserialize<'de>,
@@ -261,17 +336,140 @@ impl NodeRef {
         serde_json::to_string_pretty(self).expect("Node should be always serializable")
     }
 
+    /// Fallible version of `to_json()`. Unlike `to_json()`, which writes binary values as
+    /// arrays of bytes and non-finite floats as `null`, this returns an error for such values.
+    pub fn try_to_json(&self) -> TreeResult<String> {
+        self.check_serializable(FileFormat::Json)?;
+        serde_json::to_string(self).map_err(|err| serialization_err(FileFormat::Json, err))
+    }
+
+    /// Serializes array node to newline-delimited JSON (NDJSON), one element per line.
+    /// Any other node is written as a single line.
+    pub fn to_json_lines(&self) -> String {
+        let mut out = String::new();
+        match *self.data().value() {
+            Value::Array(ref elems) => {
+                for e in elems.iter() {
+                    out.push_str(&e.to_json());
+                    out.push('\n');
+                }
+            }
+            _ => {
+                out.push_str(&self.to_json());
+                out.push('\n');
+            }
+        }
+        out
+    }
+
     pub fn to_yaml(&self) -> String {
         serde_yaml::to_string(self).expect("Node should be always serializable")
     }
 
+    /// Fallible version of `to_yaml()`, returning an error for binary values.
+    pub fn try_to_yaml(&self) -> TreeResult<String> {
+        self.check_serializable(FileFormat::Yaml)?;
+        serde_yaml::to_string(self).map_err(|err| serialization_err(FileFormat::Yaml, err))
+    }
+
+    /// Serializes this node to TOML, writing binary values as arrays of bytes.
     pub fn to_toml(&self) -> String {
-        toml::to_string(self).expect("Node should be always serializable")
+        toml::to_string(&TomlNode(self)).expect("Node should be always serializable")
+    }
+
+    /// Serializes this node to TOML. Only objects can be serialized at the top level;
+    /// arrays of objects are written as arrays of tables (`[[table]]`). Binary values are
+    /// reported as errors.
+    pub fn try_to_toml(&self) -> TreeResult<String> {
+        self.check_serializable(FileFormat::Toml)?;
+        toml::to_string(&TomlNode(self)).map_err(|err| serialization_err(FileFormat::Toml, err))
+    }
+
+    /// Like `try_to_toml()`, but also writes comments stored in node trivia. Comments are
+    /// kept for nodes modified in place (e.g. with `replace_with()`), but not for replaced nodes.
+    pub fn to_toml_with_trivia(&self) -> TreeResult<String> {
+        let s = self.try_to_toml()?;
+        Ok(serial::toml::insert_trivia(self, &s))
+    }
+
+    /// Renders this node as an indented outline, one line per node. Object properties are
+    /// labeled with their key, array elements with `[index]`, and scalar values are written
+    /// as JSON. Intended for human inspection, the format is not meant to be parsed.
+    pub fn to_tree_string(&self) -> String {
+        fn write_children(n: &NodeRef, indent: usize, out: &mut String) {
+            match *n.data().value() {
+                Value::Array(ref elems) => {
+                    for (i, e) in elems.iter().enumerate() {
+                        write_child(&format!("[{}]", i), e, indent, out);
+                    }
+                }
+                Value::Object(ref props) => {
+                    for (k, e) in props.iter() {
+                        write_child(k.as_ref(), e, indent, out);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        fn write_child(label: &str, n: &NodeRef, indent: usize, out: &mut String) {
+            for _ in 0..indent {
+                out.push_str("  ");
+            }
+            out.push_str(label);
+            out.push(':');
+            if n.data().children_count().unwrap_or(0) > 0 {
+                out.push('\n');
+                write_children(n, indent + 1, out);
+            } else {
+                out.push(' ');
+                out.push_str(&n.to_json());
+                out.push('\n');
+            }
+        }
+
+        if self.data().children_count().unwrap_or(0) > 0 {
+            let mut out = String::new();
+            write_children(self, 0, &mut out);
+            out
+        } else {
+            self.to_json() + "\n"
+        }
+    }
+
+    /// Checks for values that cannot be represented in text `format` without loss.
+    fn check_serializable(&self, format: FileFormat) -> TreeResult<()> {
+        let mut res = Ok(());
+        self.visit_recursive(|_, _, n| {
+            let err = match *n.data().value() {
+                Value::Binary(_) => Some("binary values are not supported"),
+                Value::Float(f)
+                    if (format == FileFormat::Json || format == FileFormat::JsonLines) && !f.is_finite() =>
+                {
+                    Some("non-finite floats are not supported")
+                }
+                _ => None,
+            };
+            if let Some(err) = err {
+                res = Err(serialization_err(format, format!("{} at {}", err, n.path())));
+            }
+            res.is_ok()
+        });
+        res
     }
 
     pub fn to_format(&self, format: FileFormat, pretty: bool) -> String {
         match format {
-            FileFormat::Binary | FileFormat::Text => self.as_string(),
+            FileFormat::Binary => self.as_string(),
+            // binary content is written as text if it is valid UTF-8, and as hex otherwise
+            FileFormat::Text => {
+                let d = self.data();
+                if d.is_binary() {
+                    d.as_utf8_string().or_else(|| d.as_hex_string()).unwrap()
+                } else {
+                    d.as_string().into_owned()
+                }
+            }
             FileFormat::Json => {
                 if pretty {
                     self.to_json_pretty()
@@ -281,9 +479,16 @@ impl NodeRef {
             }
             FileFormat::Toml => self.to_toml(),
             FileFormat::Yaml => self.to_yaml(),
+            FileFormat::JsonLines => self.to_json_lines(),
         }
     }
 
+    /// Returns span of this node in the source it was parsed from, if known.
+    /// Use `SourceMap` to get the corresponding source text.
+    pub fn source_span(&self) -> Option<Span> {
+        self.data().metadata().span()
+    }
+
     pub fn data(&self) -> Ref<Node> {
         self.0.borrow()
     }
@@ -323,10 +528,46 @@ impl NodeRef {
         self.data().as_binary().map(|b| b.to_vec())
     }
 
+    pub fn as_utf8_string(&self) -> Option<String> {
+        self.data().as_utf8_string()
+    }
+
+    pub fn as_hex_string(&self) -> Option<String> {
+        self.data().as_hex_string()
+    }
+
     pub fn as_boolean(&self) -> bool {
         self.data().as_boolean()
     }
 
+    /// Borrows elements of an array node, or returns `None` for other kinds. The node cannot
+    /// be mutated while the returned reference is alive.
+    pub fn as_vec(&self) -> Option<Ref<Elements>> {
+        let d = self.data();
+        if d.is_array() {
+            Some(Ref::map(d, |d| match *d.value() {
+                Value::Array(ref elems) => elems,
+                _ => unreachable!(),
+            }))
+        } else {
+            None
+        }
+    }
+
+    /// Borrows properties of an object node, or returns `None` for other kinds. The node
+    /// cannot be mutated while the returned reference is alive.
+    pub fn as_map(&self) -> Option<Ref<Properties>> {
+        let d = self.data();
+        if d.is_object() {
+            Some(Ref::map(d, |d| match *d.value() {
+                Value::Object(ref props) => props,
+                _ => unreachable!(),
+            }))
+        } else {
+            None
+        }
+    }
+
     pub fn is_integer(&self) -> bool {
         self.data().is_integer()
     }
@@ -367,6 +608,21 @@ impl NodeRef {
         self.data().is_parent()
     }
 
+    /// Returns `true` for scalar nodes, including `null` and binary values.
+    pub fn is_leaf(&self) -> bool {
+        !self.is_parent()
+    }
+
+    /// Returns `true` for arrays and objects, even if empty. Same as `is_parent()`.
+    pub fn is_container(&self) -> bool {
+        self.is_parent()
+    }
+
+    /// Number of elements or properties, `0` for scalar nodes.
+    pub fn child_count(&self) -> usize {
+        self.data().children_count().unwrap_or(0)
+    }
+
     pub fn root(&self) -> NodeRef {
         let mut r = self.clone();
         loop {
@@ -398,6 +654,30 @@ impl NodeRef {
         false
     }
 
+    pub fn is_descendant_of(&self, other: &NodeRef) -> bool {
+        other.is_ancestor(self)
+    }
+
+    /// Returns the deepest node that is an ancestor of both `a` and `b`. A node counts as
+    /// its own ancestor here, so if `a` contains `b`, `a` is returned.
+    pub fn common_ancestor(a: &NodeRef, b: &NodeRef) -> Option<NodeRef> {
+        let mut chain = Vec::new();
+        let mut n = Some(a.clone());
+        while let Some(p) = n {
+            n = p.data().parent();
+            chain.push(p);
+        }
+
+        let mut n = Some(b.clone());
+        while let Some(p) = n {
+            if chain.iter().any(|c| c.is_ref_eq(&p)) {
+                return Some(p);
+            }
+            n = p.data().parent();
+        }
+        None
+    }
+
     pub fn get_child_index(&self, index: usize) -> Option<NodeRef> {
         match *self.data().value() {
             Value::Array(ref elems) => elems.get(index).cloned(),
@@ -584,6 +864,37 @@ impl NodeRef {
         Ok(n)
     }
 
+    /// Removes this node from its parent, returning `true` if it was attached to one.
+    pub fn detach(&self) -> bool {
+        let (parent, index, key) = {
+            let d = self.data();
+            (d.parent(), d.index(), d.key().to_string())
+        };
+        match parent {
+            Some(p) => {
+                let key = if p.is_object() { Some(key.into()) } else { None };
+                match p.remove_child(Some(index), key) {
+                    Ok(n) => n.is_some(),
+                    Err(_) => false,
+                }
+            }
+            None => false,
+        }
+    }
+
+    /// Replaces the value of this node with the value of `new_value`, keeping this node's
+    /// identity, so existing references and the parent's child slot remain valid.
+    pub fn replace_with(&self, new_value: NodeRef) -> TreeResult<()> {
+        if self.is_ref_eq(&new_value) {
+            return Ok(());
+        }
+        let new_value = new_value.into_consumable();
+        let value = std::mem::replace(new_value.data_mut().value_mut(), Value::Null);
+        *self.data_mut().value_mut() = value;
+        self.update_children_metadata();
+        Ok(())
+    }
+
     pub fn remove_children<'a, I>(
         &self,
         drop: bool,
@@ -701,6 +1012,194 @@ impl NodeRef {
         Ok(())
     }
 
+    /// Deep-merges `o` into this node according to `strategy`. Nodes from `o` are copied
+    /// if they are still referenced elsewhere.
+    pub fn merge(&self, o: NodeRef, strategy: MergeStrategy) -> TreeResult<()> {
+        if self.is_ref_eq(&o) {
+            return Ok(());
+        }
+        let target = self.data().kind();
+        let source = o.data().kind();
+        match (target, source) {
+            (Kind::Object, Kind::Object) => {
+                let props: Vec<(Symbol, NodeRef)> = match *o.data().value() {
+                    Value::Object(ref props) => props.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+                    _ => unreachable!(),
+                };
+                for (k, v) in props {
+                    match self.get_child_key(k.as_ref()) {
+                        Some(c) => c.merge(v, strategy)?,
+                        None => {
+                            self.add_child(None, Some(k), v.into_consumable())?;
+                        }
+                    }
+                }
+                Ok(())
+            }
+            (Kind::Array, Kind::Array) if strategy != MergeStrategy::Replace => {
+                self.extend(o.into_consumable(), None)
+            }
+            _ if strategy == MergeStrategy::NoOverwrite => {
+                Err(TreeErrorDetail::MergeConflict { target, source }.into())
+            }
+            _ => self.replace_with(o),
+        }
+    }
+
+    /// Merges `o` into the node at dot-separated `path`, creating missing intermediate objects.
+    pub fn merge_at_path(&self, path: &str, o: NodeRef, strategy: MergeStrategy) -> TreeResult<()> {
+        let mut n = self.clone();
+        for key in path.split('.').filter(|k| !k.is_empty()) {
+            let c = match n.get_child_key(key) {
+                Some(c) => c,
+                None => {
+                    let kind = n.data().kind();
+                    if kind != Kind::Object {
+                        return Err(AddChildInvalidType { kind }.into());
+                    }
+                    let c = NodeRef::object(Properties::new());
+                    n.add_child(None, Some(key.into()), c.clone())?;
+                    c
+                }
+            };
+            n = c;
+        }
+        n.merge(o, strategy)
+    }
+
+    /// Like `merge()` with `MergeStrategy::Replace`, but arrays are merged element by element.
+    /// Elements of `o` are matched with elements of this array having equal `key_field`
+    /// property; matched elements are merged recursively, unmatched ones are appended.
+    pub fn merge_by_key(&self, o: NodeRef, key_field: &str) -> TreeResult<()> {
+        if self.is_ref_eq(&o) {
+            return Ok(());
+        }
+        let target = self.data().kind();
+        let source = o.data().kind();
+        match (target, source) {
+            (Kind::Object, Kind::Object) => {
+                let props: Vec<(Symbol, NodeRef)> = match *o.data().value() {
+                    Value::Object(ref props) => props.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+                    _ => unreachable!(),
+                };
+                for (k, v) in props {
+                    match self.get_child_key(k.as_ref()) {
+                        Some(c) => c.merge_by_key(v, key_field)?,
+                        None => {
+                            self.add_child(None, Some(k), v.into_consumable())?;
+                        }
+                    }
+                }
+                Ok(())
+            }
+            (Kind::Array, Kind::Array) => {
+                let elems: Elements = match *o.data().value() {
+                    Value::Array(ref elems) => elems.clone(),
+                    _ => unreachable!(),
+                };
+                for e in elems {
+                    let matched = match (e.get_child_key(key_field), &*self.data().value()) {
+                        (Some(key), Value::Array(elems)) => elems
+                            .iter()
+                            .find(|c| {
+                                c.data().is_object()
+                                    && c.get_child_key(key_field).map_or(false, |k| k.is_equal(&key))
+                            })
+                            .cloned(),
+                        _ => None,
+                    };
+                    match matched {
+                        Some(c) => c.merge_by_key(e, key_field)?,
+                        None => {
+                            self.add_child(None, None, e.into_consumable())?;
+                        }
+                    }
+                }
+                Ok(())
+            }
+            _ => self.replace_with(o),
+        }
+    }
+
+    /// Moves property `key` of this object to position `to_index` (clamped to the number of
+    /// properties). Returns `false` if there is no such property.
+    pub fn move_key(&self, key: &str, to_index: usize) -> TreeResult<bool> {
+        let kind = self.data().kind();
+        let moved = match *self.data_mut().value_mut() {
+            Value::Object(ref mut props) => match props.remove(key) {
+                Some(v) => {
+                    let index = to_index.min(props.len());
+                    props.insert_at(index, key.into(), v);
+                    true
+                }
+                None => false,
+            },
+            _ => return Err(ReorderKeysInvalidType { kind }.into()),
+        };
+        if moved {
+            self.update_children_metadata();
+        }
+        Ok(moved)
+    }
+
+    /// Sorts properties of this object by key, using `cmp` (stable sort).
+    pub fn sort_keys<F>(&self, mut cmp: F) -> TreeResult<()>
+    where
+        F: FnMut(&str, &str) -> Ordering,
+    {
+        let kind = self.data().kind();
+        match *self.data_mut().value_mut() {
+            Value::Object(ref mut props) => {
+                let mut entries = Vec::with_capacity(props.len());
+                while let Some(e) = props.pop_front() {
+                    entries.push(e);
+                }
+                entries.sort_by(|a, b| cmp(a.0.as_ref(), b.0.as_ref()));
+                for (k, v) in entries {
+                    props.insert(k, v);
+                }
+            }
+            _ => return Err(ReorderKeysInvalidType { kind }.into()),
+        }
+        self.update_children_metadata();
+        Ok(())
+    }
+
+    /// Recursively renames all object keys in this subtree using `f`. If two keys of one
+    /// object are renamed to the same key, the latter property wins.
+    pub fn rename_keys<F>(&self, mut f: F)
+    where
+        F: FnMut(&str) -> String,
+    {
+        fn rename<F: FnMut(&str) -> String>(n: &NodeRef, f: &mut F) {
+            let updated = match *n.data_mut().value_mut() {
+                Value::Object(ref mut props) => {
+                    let mut entries = Vec::with_capacity(props.len());
+                    while let Some(e) = props.pop_front() {
+                        entries.push(e);
+                    }
+                    for (k, v) in entries {
+                        rename(&v, f);
+                        props.insert(f(k.as_ref()).into(), v);
+                    }
+                    true
+                }
+                Value::Array(ref elems) => {
+                    for e in elems.iter() {
+                        rename(e, f);
+                    }
+                    false
+                }
+                _ => false,
+            };
+            if updated {
+                n.update_children_metadata();
+            }
+        }
+
+        rename(self, &mut f);
+    }
+
     pub fn extend_multiple<I>(&self, mut extends: I) -> TreeResult<()>
     where
         I: Iterator<Item = (NodeRef, Option<usize>)>,
@@ -722,10 +1221,114 @@ impl NodeRef {
         Rc::ptr_eq(&self.0, &other.0)
     }
 
+    /// Creates a thread-safe, read-only snapshot of this subtree.
+    pub fn freeze(&self) -> frozen::FrozenNode {
+        frozen::FrozenNode::new(self)
+    }
+
+    /// Returns a copy of this tree with string values containing `${expr}` interpolations
+    /// replaced by their results. Expressions are evaluated against `root`, with the parent
+    /// of the string as the current node and variables from `scope`. If `root` is this node,
+    /// expressions see already substituted values, and substitution is repeated until the
+    /// tree stops changing, failing with `InterpolationDepthReached` for cyclic templates.
+    pub fn interpolate(&self, root: &NodeRef, scope: &Scope) -> TreeResult<NodeRef> {
+        struct ScopeStrategy<'a> {
+            root: Option<&'a NodeRef>,
+            scope: &'a Scope,
+        }
+
+        impl<'a> ResolveStrategy for ScopeStrategy<'a> {
+            fn resolve_interpolation(
+                &mut self,
+                interpolation: &Interpolation,
+                _node: &NodeRef,
+                parent: &NodeRef,
+                root: &NodeRef,
+            ) -> ExprResult<Option<NodeRef>> {
+                interpolation.resolve_ext(self.root.unwrap_or(root), parent, self.scope)
+            }
+        }
+
+        let mut resolver = TreeResolver::with_delims("${", "}");
+        if self.is_parent() {
+            let n = self.deep_copy();
+            let root = if root.is_ref_eq(self) { None } else { Some(root) };
+            resolver.resolve_custom(ScopeStrategy { root, scope }, &n)?;
+            Ok(n)
+        } else {
+            // resolver replaces children, so scalars are wrapped in a temporary array
+            let a = NodeRef::array(vec![self.deep_copy()]);
+            resolver.resolve_custom(ScopeStrategy { root: Some(root), scope }, &a)?;
+            let n = a.get_child_index(0).unwrap();
+            Ok(n.deep_copy())
+        }
+    }
+
+    /// Creates a detached copy of this subtree. File info and spans are preserved,
+    /// while the root of the copy has no parent, key or index.
     pub fn deep_copy(&self) -> NodeRef {
         NodeRef::new(self.data().deep_copy())
     }
 
+    /// Creates a new root node whose children are shared with this node rather than copied.
+    ///
+    /// Shared children keep their metadata, so they still report this node as their parent
+    /// (and their original key and index). Any modification of a shared child is visible
+    /// through both nodes. Adding or removing children of either node rebuilds the metadata
+    /// of all its children, which re-parents the shared ones to that node.
+    /// Use `deep_copy()` when the copy is going to be modified independently.
+    pub fn clone_shallow(&self) -> NodeRef {
+        NodeRef(Rc::new(RefCell::new(self.data().clone_shallow())))
+    }
+
+    /// Same as `deep_copy()`, but the root of the copy retains the key and index of this node,
+    /// so it can be re-inserted elsewhere identically.
+    pub fn deep_copy_preserving_path(&self) -> NodeRef {
+        let n = self.deep_copy();
+        {
+            let d = self.data();
+            let mut m = n.data_mut();
+            m.metadata_mut().set_key(d.key().into());
+            m.metadata_mut().set_index(d.index());
+        }
+        n
+    }
+
+    /// Returns the total heap memory used by this subtree, including the allocations
+    /// holding each descendant node.
+    pub fn deep_heap_size(&self) -> usize {
+        // `heap_size_of_children()` already recurses through child values,
+        // but it does not count the `Rc` allocations of the nodes themselves
+        let node_size = std::mem::size_of::<RefCell<Node>>() + 2 * std::mem::size_of::<usize>();
+        self.heap_size_of_children() + self.node_count() * node_size
+    }
+
+    /// Returns the number of nodes in this subtree, including this node.
+    pub fn node_count(&self) -> usize {
+        let mut count = 0;
+        self.visit_recursive(|_, _, _| {
+            count += 1;
+            true
+        });
+        count
+    }
+
+    /// Returns the length of the longest path from this node down to a descendant,
+    /// so scalars and empty containers have depth `0`.
+    pub fn max_depth(&self) -> usize {
+        let mut path: Vec<NodeRef> = Vec::new();
+        let mut depth = 0;
+        self.visit_recursive(|_, p, n| {
+            while path.last().map_or(false, |last| !last.is_ref_eq(p)) {
+                path.pop();
+            }
+            path.push(n.clone());
+            depth = depth.max(path.len() - 1);
+            true
+        });
+        depth
+    }
+
     pub fn visit_recursive<F>(&self, mut visitor: F)
     where
         F: FnMut(&NodeRef, &NodeRef, &NodeRef) -> bool,
@@ -816,6 +1419,79 @@ impl NodeRef {
         }
     }
 
+    /// Like `is_equal`, but compares arrays and objects by content instead of by reference.
+    /// Scalars follow `is_equal` coercion rules, so `{"a": 1}` is equal to `{"a": "1"}`.
+    /// Object properties are matched by key, regardless of their order.
+    pub fn is_equal_deep(&self, other: &NodeRef) -> bool {
+        if self.is_ref_eq(other) {
+            true
+        } else {
+            let a = self.data();
+            let b = other.data();
+            match (a.value(), b.value()) {
+                (&Value::Object(ref ap), &Value::Object(ref bp)) => {
+                    if ap.len() != bp.len() {
+                        false
+                    } else {
+                        for (k, va) in ap.iter() {
+                            match bp.get(k.as_ref()) {
+                                Some(vb) if va.is_equal_deep(vb) => {}
+                                _ => return false,
+                            }
+                        }
+                        true
+                    }
+                }
+                (&Value::Array(ref ae), &Value::Array(ref be)) => {
+                    if ae.len() != be.len() {
+                        false
+                    } else {
+                        for (va, vb) in ae.iter().zip(be.iter()) {
+                            if !va.is_equal_deep(vb) {
+                                return false;
+                            }
+                        }
+                        true
+                    }
+                }
+                _ => {
+                    drop(a);
+                    drop(b);
+                    self.is_equal(other)
+                }
+            }
+        }
+    }
+
+    pub fn diff_summary(&self, other: &NodeRef) -> DiffSummary {
+        DiffSummary::diff(self, other)
+    }
+
+    /// Renders differences between this node and `other` as text, one line per changed path:
+    /// `+ path: value` for added, `- path: value` for removed and `~ path: old -> new` for
+    /// changed values. Values are written as JSON.
+    pub fn diff_string(&self, other: &NodeRef) -> String {
+        fn value(root: &NodeRef, path: &Opath) -> String {
+            match path.apply_one(root, root) {
+                Ok(n) => n.to_json(),
+                Err(_) => String::new(),
+            }
+        }
+
+        let summary = self.diff_summary(other);
+        let mut out = String::new();
+        for p in summary.added() {
+            out.push_str(&format!("+ {}: {}\n", p, value(other, p)));
+        }
+        for p in summary.removed() {
+            out.push_str(&format!("- {}: {}\n", p, value(self, p)));
+        }
+        for p in summary.changed() {
+            out.push_str(&format!("~ {}: {} -> {}\n", p, value(self, p), value(other, p)));
+        }
+        out
+    }
+
     pub fn is_identical(&self, other: &NodeRef) -> bool {
         if self.is_ref_eq(other) {
             true
@@ -880,6 +1556,20 @@ impl NodeRef {
         self.data_mut().metadata_mut().set_span(Some(span));
         self
     }
+
+    /// Overrides file format of the file this node was loaded from, e.g. when file extension
+    /// is misleading. File info is shared by all nodes loaded from the same file, so the change
+    /// is visible on all of them. Returns `false` if this node has no file info.
+    pub fn set_file_format(&self, format: FileFormat) -> bool {
+        let file = self.data().file().cloned();
+        match file {
+            Some(mut file) => {
+                file.set_file_format(format);
+                true
+            }
+            None => false,
+        }
+    }
 }
 
 impl<'a> Clone for NodeRef {
@@ -935,7 +1625,7 @@ impl<'a> ser::Serialize for NodeRef {
             Value::Null => serializer.serialize_none(),
             Value::Boolean(b) => serializer.serialize_bool(b),
             Value::Integer(n) => serializer.serialize_i64(n),
-            Value::Float(n) => serializer.serialize_f64(n),
+            Value::Float(n) => serializer.serialize_f64(float_format().round(n)),
             Value::String(ref s) => serializer.serialize_str(s),
             Value::Binary(ref b) => serializer.serialize_bytes(b),
             Value::Array(ref elems) => {
@@ -956,24 +1646,74 @@ impl<'a> ser::Serialize for NodeRef {
     }
 }
 
-struct NodeVisitor;
-
-impl NodeVisitor {
-    fn new() -> NodeVisitor {
-        NodeVisitor
+fn serialization_err<E: std::fmt::Display>(format: FileFormat, err: E) -> TreeError {
+    TreeErrorDetail::SerializationErr {
+        format,
+        err: err.to_string(),
     }
+    .into()
 }
 
-impl<'de> de::Visitor<'de> for NodeVisitor {
-    type Value = NodeRef;
+/// Serializes node in an order accepted by TOML, where plain values must be written
+/// before any tables or arrays of tables.
+struct TomlNode<'a>(&'a NodeRef);
 
-    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "any data")
+impl<'a> TomlNode<'a> {
+    fn is_table(n: &NodeRef) -> bool {
+        match *n.data().value() {
+            Value::Object(_) => true,
+            Value::Array(ref elems) => !elems.is_empty() && elems.iter().all(|e| e.is_object()),
+            _ => false,
+        }
     }
+}
 
-    fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E>
+impl<'a> ser::Serialize for TomlNode<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
-        E: de::Error,
+        S: ser::Serializer,
+    {
+        match *self.0.data().value() {
+            Value::Array(ref elems) => {
+                let mut seq = serializer.serialize_seq(Some(elems.len()))?;
+                for e in elems.iter() {
+                    seq.serialize_element(&TomlNode(e))?;
+                }
+                seq.end()
+            }
+            Value::Object(ref props) => {
+                let mut map = serializer.serialize_map(Some(props.len()))?;
+                for (k, e) in props.iter().filter(|&(_, e)| !TomlNode::is_table(e)) {
+                    map.serialize_entry(k, &TomlNode(e))?;
+                }
+                for (k, e) in props.iter().filter(|&(_, e)| TomlNode::is_table(e)) {
+                    map.serialize_entry(k, &TomlNode(e))?;
+                }
+                map.end()
+            }
+            _ => self.0.serialize(serializer),
+        }
+    }
+}
+
+struct NodeVisitor;
+
+impl NodeVisitor {
+    fn new() -> NodeVisitor {
+        NodeVisitor
+    }
+}
+
+impl<'de> de::Visitor<'de> for NodeVisitor {
+    type Value = NodeRef;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "any data")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E>
+    where
+        E: de::Error,
     {
         Ok(NodeRef::boolean(v))
     }
@@ -1163,4 +1903,1007 @@ mod tests {
 
         assert_eq!(string_count, 3);
     }
+
+    mod is_equal_deep {
+        use super::*;
+
+        #[test]
+        fn separate_objects() {
+            let a = NodeRef::from_json(r#"{"a": 1, "b": [1, {"c": null}]}"#).unwrap();
+            let b = NodeRef::from_json(r#"{"b": [1, {"c": null}], "a": 1}"#).unwrap();
+
+            assert!(!a.is_equal(&b));
+            assert!(a.is_equal_deep(&b));
+            assert!(b.is_equal_deep(&a));
+        }
+
+        #[test]
+        fn coercion() {
+            let a = NodeRef::from_json(r#"{"a": 1}"#).unwrap();
+            let b = NodeRef::from_json(r#"{"a": "1"}"#).unwrap();
+
+            assert!(a.is_equal_deep(&b));
+            assert!(!a.is_identical_deep(&b));
+        }
+
+        #[test]
+        fn different() {
+            let a = NodeRef::from_json(r#"{"a": 1}"#).unwrap();
+
+            assert!(!a.is_equal_deep(&NodeRef::from_json(r#"{"a": 2}"#).unwrap()));
+            assert!(!a.is_equal_deep(&NodeRef::from_json(r#"{"b": 1}"#).unwrap()));
+            assert!(!a.is_equal_deep(&NodeRef::from_json(r#"{"a": 1, "b": 1}"#).unwrap()));
+            assert!(!a.is_equal_deep(&NodeRef::from_json(r#"[1]"#).unwrap()));
+        }
+    }
+
+    mod deep_copy {
+        use super::*;
+
+        fn with_metadata() -> NodeRef {
+            let n = NodeRef::from_json(r#"{"a": {"b": [1, 2]}}"#).unwrap();
+            n.data_mut().set_file(Some(FileInfo::new_file("/data/input.json", FileFormat::Json)));
+            let a = n.get_child_key("a").unwrap();
+            a.data_mut().metadata_mut().set_span(Some(Span::with(6, 1, 7, 20, 1, 21)));
+            n
+        }
+
+        #[test]
+        fn preserves_file_and_span() {
+            let n = with_metadata();
+            let a = n.get_child_key("a").unwrap();
+
+            let c = a.deep_copy();
+            assert!(c.data().parent().is_none());
+            assert_eq!(c.data().file(), a.data().file());
+            assert_eq!(c.data().metadata().span(), Some(Span::with(6, 1, 7, 20, 1, 21)));
+            let b = c.get_child_key("b").unwrap();
+            assert_eq!(b.data().file(), a.data().file());
+            assert!(b.data().parent().unwrap().is_ref_eq(&c));
+        }
+
+        #[test]
+        fn resets_root_key() {
+            let n = with_metadata();
+            let b = n.get_child_key("a").unwrap().get_child_key("b").unwrap();
+            let e = b.get_child_index(1).unwrap();
+
+            assert_eq!(b.deep_copy().data().key(), "");
+            assert_eq!(e.deep_copy().data().index(), 0);
+        }
+
+        #[test]
+        fn preserving_path_keeps_root_key() {
+            let n = with_metadata();
+            let b = n.get_child_key("a").unwrap().get_child_key("b").unwrap();
+            let e = b.get_child_index(1).unwrap();
+
+            let c = b.deep_copy_preserving_path();
+            assert!(c.data().parent().is_none());
+            assert_eq!(c.data().key(), "b");
+            assert_eq!(c.data().file(), b.data().file());
+
+            let c = e.deep_copy_preserving_path();
+            assert_eq!(c.data().index(), 1);
+        }
+    }
+
+    mod detach {
+        use super::*;
+
+        #[test]
+        fn array_element() {
+            let n = NodeRef::from_json(r#"[1, 2, 3]"#).unwrap();
+            let e = n.get_child_index(1).unwrap();
+            let last = n.get_child_index(2).unwrap();
+
+            assert!(e.detach());
+            assert!(e.data().parent().is_none());
+            assert_eq!(n.to_json(), "[1,3]");
+            assert_eq!(last.data().index(), 1);
+            assert_eq!(last.data().key(), "1");
+        }
+
+        #[test]
+        fn object_property() {
+            let n = NodeRef::from_json(r#"{"a": 1, "b": 2, "c": 3}"#).unwrap();
+            let b = n.get_child_key("b").unwrap();
+
+            assert!(b.detach());
+            assert!(b.data().parent().is_none());
+            assert_eq!(b.data().key(), "");
+            assert!(n.get_child_key("b").is_none());
+            assert_eq!(n.get_child_key("c").unwrap().data().index(), 1);
+        }
+
+        #[test]
+        fn root_node() {
+            let n = NodeRef::from_json(r#"{"a": 1}"#).unwrap();
+
+            assert!(!n.detach());
+            assert_eq!(n.to_json(), r#"{"a":1}"#);
+        }
+    }
+
+    mod replace_with {
+        use super::*;
+
+        #[test]
+        fn scalar_with_object() {
+            let n = NodeRef::from_json(r#"{"a": 1, "b": 2}"#).unwrap();
+            let a = n.get_child_key("a").unwrap();
+
+            a.replace_with(NodeRef::from_json(r#"{"x": [true]}"#).unwrap()).unwrap();
+            assert_eq!(n.to_json(), r#"{"a":{"x":[true]},"b":2}"#);
+            assert_eq!(a.data().key(), "a");
+
+            let x = a.get_child_key("x").unwrap();
+            assert!(x.data().parent().unwrap().is_ref_eq(&a));
+            assert_eq!(x.data().key(), "x");
+        }
+
+        #[test]
+        fn array_with_scalar() {
+            let n = NodeRef::from_json(r#"[[1, 2], 3]"#).unwrap();
+            let e = n.get_child_index(0).unwrap();
+
+            e.replace_with(NodeRef::string("text")).unwrap();
+            assert_eq!(n.to_json(), r#"["text",3]"#);
+            assert_eq!(e.data().index(), 0);
+        }
+
+        #[test]
+        fn parent_references_updated_node() {
+            let n = NodeRef::from_json(r#"{"a": {"b": 1}, "c": 2}"#).unwrap();
+            let a = n.get_child_key("a").unwrap();
+            let c = n.get_child_key("c").unwrap();
+
+            a.replace_with(c.clone()).unwrap();
+            assert!(n.get_child_key("a").unwrap().is_ref_eq(&a));
+            assert!(a.data().parent().unwrap().is_ref_eq(&n));
+            assert_eq!(a.as_integer(), Some(2));
+            assert_eq!(c.as_integer(), Some(2));
+        }
+    }
+
+    mod from_reader {
+        use super::*;
+        use std::io::Cursor;
+
+        #[test]
+        fn json() {
+            let s = r#"{"a": [1, 2.5, "x"], "b": {"c": null}}"#;
+            let mut r = Cursor::new(s.as_bytes().to_vec());
+
+            let n = NodeRef::from_reader(&mut r, FileFormat::Json).unwrap();
+            let e = NodeRef::from_str(s.into(), FileFormat::Json).unwrap();
+            assert!(n.is_identical_deep(&e));
+        }
+
+        #[test]
+        fn toml() {
+            let s = "a = [1, 2, 3]\n\n[b]\nc = \"text\"\n";
+            let mut r = Cursor::new(s.as_bytes().to_vec());
+
+            let n = NodeRef::from_reader(&mut r, FileFormat::Toml).unwrap();
+            let e = NodeRef::from_str(s.into(), FileFormat::Toml).unwrap();
+            assert!(n.is_identical_deep(&e));
+            assert_eq!(n.to_json(), r#"{"a":[1,2,3],"b":{"c":"text"}}"#);
+        }
+
+        #[test]
+        fn invalid_utf8() {
+            let mut r = Cursor::new(vec![b'"', 0xff, b'"']);
+
+            assert!(NodeRef::from_reader(&mut r, FileFormat::Json).is_err());
+        }
+    }
+
+    mod set_file_format {
+        use super::*;
+
+        fn file_format(n: &NodeRef, path: &str) -> String {
+            let expr = Opath::parse(&format!("{}.@file_format", path)).unwrap();
+            expr.apply(n, n).unwrap().into_one().unwrap().as_string()
+        }
+
+        #[test]
+        fn override_loaded_format() {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("config.txt");
+            std::fs::write(&path, r#"{"a": {"b": 1}}"#).unwrap();
+
+            let n = NodeRef::from_file(&path, Some(FileFormat::Json)).unwrap();
+            assert_eq!(file_format(&n, "$"), "json");
+
+            assert!(n.set_file_format(FileFormat::Yaml));
+            assert_eq!(file_format(&n, "$"), "yaml");
+            assert_eq!(file_format(&n, "$.a.b"), "yaml");
+        }
+
+        #[test]
+        fn no_file_info() {
+            let n = NodeRef::from_json(r#"{"a": 1}"#).unwrap();
+            assert!(!n.set_file_format(FileFormat::Yaml));
+            assert_eq!(file_format(&n, "$"), "");
+        }
+    }
+
+    mod from_json_bytes {
+        use super::*;
+
+        #[test]
+        fn same_as_from_json() {
+            let s = r#"{"a": [1, 2.5, "zażółć"], "b": {"c": null, "d": true}}"#;
+
+            let n = NodeRef::from_json_bytes(s.as_bytes()).unwrap();
+            let e = NodeRef::from_json(s).unwrap();
+            assert!(n.is_identical_deep(&e));
+        }
+
+        #[test]
+        fn invalid_utf8() {
+            assert!(NodeRef::from_json_bytes(&[b'"', 0xff, b'"']).is_err());
+        }
+    }
+
+    mod json_lines {
+        use super::*;
+
+        #[test]
+        fn round_trip() {
+            let s = "{\"id\":1,\"msg\":\"start\"}\n{\"id\":2,\"msg\":\"stop\"}\n[1,2]\n\"text\"\n";
+
+            let n = NodeRef::from_json_lines(s).unwrap();
+            assert_eq!(n.child_count(), 4);
+            assert_eq!(n.get_child_index(1).unwrap().to_json(), r#"{"id":2,"msg":"stop"}"#);
+            assert_eq!(n.to_json_lines(), s);
+
+            let f = NodeRef::from_str(s.into(), FileFormat::JsonLines).unwrap();
+            assert!(f.is_identical_deep(&n));
+            assert_eq!(f.to_format(FileFormat::JsonLines, false), s);
+        }
+
+        #[test]
+        fn blank_lines() {
+            let s = "\n{\"a\":1}\n   \n\r\n{\"a\":2}\r\n\n";
+
+            let n = NodeRef::from_json_lines(s).unwrap();
+            assert_eq!(n.to_json(), r#"[{"a":1},{"a":2}]"#);
+            assert_eq!(NodeRef::from_json_lines("").unwrap().to_json(), "[]");
+        }
+
+        #[test]
+        fn malformed_line() {
+            let err = NodeRef::from_json_lines("{\"a\":1}\n\n{\"a\":}\n").unwrap_err();
+            let detail: &TreeErrorDetail = err.detail().downcast_ref().unwrap();
+            match *detail {
+                TreeErrorDetail::JsonLineErr { line } => assert_eq!(line, 3),
+                _ => panic!("JsonLineErr expected"),
+            }
+        }
+
+        #[test]
+        fn file_format() {
+            assert_eq!(FileFormat::from("jsonl"), FileFormat::JsonLines);
+            assert_eq!(FileFormat::from("ndjson"), FileFormat::JsonLines);
+            assert_eq!(FileFormat::JsonLines.to_string(), "jsonl");
+        }
+    }
+
+    mod merge_at_path {
+        use super::*;
+
+        static BASE_JSON: &str = r#"
+            {
+                "services": {
+                    "web": {"image": "nginx", "ports": [80], "env": {"A": "1"}}
+                }
+            }
+        "#;
+
+        #[test]
+        fn existing_subtree() {
+            let n = NodeRef::from_json(BASE_JSON).unwrap();
+            let o = NodeRef::from_json(r#"{"image": "httpd", "ports": [443], "env": {"B": "2"}}"#).unwrap();
+
+            n.merge_at_path("services.web", o.clone(), MergeStrategy::Replace).unwrap();
+            assert_eq!(
+                n.get_child_key("services").unwrap().get_child_key("web").unwrap().to_json(),
+                r#"{"image":"httpd","ports":[443],"env":{"A":"1","B":"2"}}"#
+            );
+
+            let n = NodeRef::from_json(BASE_JSON).unwrap();
+            n.merge_at_path("services.web", o.clone(), MergeStrategy::Append).unwrap();
+            let web = n.get_child_key("services").unwrap().get_child_key("web").unwrap();
+            assert_eq!(web.get_child_key("ports").unwrap().to_json(), "[80,443]");
+            assert!(web.get_child_key("env").unwrap().data().parent().unwrap().is_ref_eq(&web));
+
+            // merged node is left untouched
+            assert_eq!(o.to_json(), r#"{"image":"httpd","ports":[443],"env":{"B":"2"}}"#);
+        }
+
+        #[test]
+        fn missing_path() {
+            let n = NodeRef::from_json(BASE_JSON).unwrap();
+            let o = NodeRef::from_json(r#"{"image": "redis"}"#).unwrap();
+
+            n.merge_at_path("services.cache.main", o, MergeStrategy::Replace).unwrap();
+            let main = n
+                .get_child_key("services")
+                .unwrap()
+                .get_child_key("cache")
+                .unwrap()
+                .get_child_key("main")
+                .unwrap();
+            assert_eq!(main.to_json(), r#"{"image":"redis"}"#);
+            assert_eq!(Opath::from(&main).to_string(), "$.services.cache.main");
+        }
+
+        #[test]
+        fn scalar_collision() {
+            let n = NodeRef::from_json(BASE_JSON).unwrap();
+            let o = NodeRef::from_json(r#"{"image": "httpd"}"#).unwrap();
+
+            let err = n.merge_at_path("services.web", o, MergeStrategy::NoOverwrite).unwrap_err();
+            let detail: &TreeErrorDetail = err.detail().downcast_ref().unwrap();
+            match *detail {
+                TreeErrorDetail::MergeConflict { target, source } => {
+                    assert_eq!(target, Kind::String);
+                    assert_eq!(source, Kind::String);
+                }
+                _ => panic!("Wrong error kind"),
+            }
+        }
+    }
+
+    mod clone_shallow {
+        use super::*;
+
+        #[test]
+        fn shares_children() {
+            let n = NodeRef::from_json(r#"{"a": {"b": 1}, "c": [1, 2]}"#).unwrap();
+            let a = n.get_child_key("a").unwrap();
+
+            let c = a.clone_shallow();
+            assert!(!c.is_ref_eq(&a));
+            assert!(c.data().parent().is_none());
+            assert!(c.get_child_key("b").unwrap().is_ref_eq(&a.get_child_key("b").unwrap()));
+
+            let c = n.clone_shallow();
+            assert!(!c.is_ref_eq(&n));
+            assert!(c.get_child_key("a").unwrap().is_ref_eq(&a));
+            assert!(c.get_child_key("c").unwrap().is_ref_eq(&n.get_child_key("c").unwrap()));
+            // shared children still point at the original parent
+            assert!(a.data().parent().unwrap().is_ref_eq(&n));
+        }
+
+        #[test]
+        fn root_is_distinct() {
+            let n = NodeRef::from_json(r#"{"a": 1, "b": [true]}"#).unwrap();
+
+            let c = n.clone_shallow();
+            c.add_child(None, Some("x".into()), NodeRef::null()).unwrap();
+            assert_eq!(c.to_json(), r#"{"a":1,"b":[true],"x":null}"#);
+            assert_eq!(n.to_json(), r#"{"a":1,"b":[true]}"#);
+
+            c.get_child_key("b").unwrap().add_child(None, None, NodeRef::integer(1)).unwrap();
+            assert_eq!(n.to_json(), r#"{"a":1,"b":[true,1]}"#);
+        }
+
+        #[test]
+        fn scalar() {
+            let n = NodeRef::string("text");
+
+            let c = n.clone_shallow();
+            assert!(!c.is_ref_eq(&n));
+            assert_eq!(c.as_string(), "text");
+        }
+    }
+
+    mod binary_strings {
+        use super::*;
+
+        #[test]
+        fn valid_utf8() {
+            let n = NodeRef::binary("zażółć".as_bytes());
+
+            assert_eq!(n.as_utf8_string(), Some("zażółć".to_string()));
+            assert_eq!(n.to_format(FileFormat::Text, false), "zażółć");
+        }
+
+        #[test]
+        fn invalid_utf8() {
+            let n = NodeRef::binary(vec![0x66, 0x6f, 0xff, 0xfe]);
+
+            assert_eq!(n.as_utf8_string(), None);
+            assert_eq!(n.to_format(FileFormat::Text, false), "666ffffe");
+        }
+
+        #[test]
+        fn hex() {
+            let n = NodeRef::binary(vec![0x00, 0x0a, 0xab, 0xff]);
+            assert_eq!(n.as_hex_string(), Some("000aabff".to_string()));
+
+            assert_eq!(NodeRef::binary(Vec::new()).as_hex_string(), Some(String::new()));
+            assert_eq!(NodeRef::string("ab").as_hex_string(), None);
+        }
+    }
+
+    mod float_format {
+        use super::*;
+
+        #[test]
+        fn serialize_whole_float() {
+            let n = NodeRef::float(1.0);
+            assert_eq!(n.to_json(), "1.0");
+            assert_eq!(n.to_string(), "1.0");
+
+            let e = Opath::parse(&n.to_string()).unwrap();
+            let res = e.apply_one(&n, &n).unwrap();
+            assert!(res.data().is_float());
+            assert_eq!(res.as_float(), 1.0);
+        }
+
+        #[test]
+        fn significant_digits() {
+            set_float_format(FloatFormat::Significant(3));
+
+            let n = NodeRef::from_json(r#"{"pi": 3.14159265, "big": 1234567.0, "one": 1.0}"#).unwrap();
+            assert_eq!(n.to_json(), r#"{"pi":3.14,"big":1230000.0,"one":1.0}"#);
+
+            let e = Opath::parse("3.14159265").unwrap();
+            assert_eq!(e.to_string(), "3.14");
+
+            set_float_format(FloatFormat::Shortest);
+            assert_eq!(n.to_json(), r#"{"pi":3.14159265,"big":1234567.0,"one":1.0}"#);
+        }
+    }
+
+    mod deep_heap_size {
+        use super::*;
+
+        #[test]
+        fn grows_with_children() {
+            let n = NodeRef::from_json(r#"{"a": 1, "b": [true, false]}"#).unwrap();
+            let before = n.deep_heap_size();
+            assert!(before > n.heap_size_of_children());
+
+            let b = n.get_child_key("b").unwrap();
+            b.add_child(None, None, NodeRef::string("hello world")).unwrap();
+            let after = n.deep_heap_size();
+            assert!(after >= before + std::mem::size_of::<RefCell<Node>>() + "hello world".len());
+        }
+
+        #[test]
+        fn scalar() {
+            let n = NodeRef::integer(1);
+            assert_eq!(
+                n.deep_heap_size(),
+                std::mem::size_of::<RefCell<Node>>() + 2 * std::mem::size_of::<usize>()
+            );
+        }
+    }
+
+    mod leaf_container {
+        use super::*;
+
+        #[test]
+        fn scalars() {
+            let nodes = vec![
+                NodeRef::null(),
+                NodeRef::boolean(true),
+                NodeRef::integer(1),
+                NodeRef::float(1.5),
+                NodeRef::string("s"),
+                NodeRef::binary(vec![1u8, 2]),
+            ];
+            for n in nodes.iter() {
+                assert!(n.is_leaf());
+                assert!(!n.is_container());
+                assert_eq!(n.child_count(), 0);
+            }
+        }
+
+        #[test]
+        fn containers() {
+            let n = NodeRef::from_json(r#"{"a": [1, 2, 3], "b": {}, "c": []}"#).unwrap();
+            assert!(n.is_container());
+            assert!(!n.is_leaf());
+            assert_eq!(n.child_count(), 3);
+
+            let a = n.get_child_key("a").unwrap();
+            assert!(a.is_container());
+            assert_eq!(a.child_count(), 3);
+
+            for key in &["b", "c"] {
+                let e = n.get_child_key(key).unwrap();
+                assert!(e.is_container());
+                assert!(!e.is_leaf());
+                assert_eq!(e.child_count(), 0);
+            }
+        }
+    }
+
+    mod typed_views {
+        use super::*;
+
+        #[test]
+        fn as_vec() {
+            let n = NodeRef::from_json(r#"[1, 2, 3]"#).unwrap();
+            let elems = n.as_vec().unwrap();
+            assert_eq!(elems.len(), 3);
+            let sum: i64 = elems.iter().map(|e| e.as_integer().unwrap()).sum();
+            assert_eq!(sum, 6);
+            assert!(n.as_map().is_none());
+        }
+
+        #[test]
+        fn as_map() {
+            let n = NodeRef::from_json(r#"{"a": 1, "b": "x"}"#).unwrap();
+            {
+                let props = n.as_map().unwrap();
+                let keys: Vec<&str> = props.iter().map(|(k, _)| k.as_ref()).collect();
+                assert_eq!(keys, vec!["a", "b"]);
+                assert_eq!(props.get("b").unwrap().as_string(), "x");
+            }
+            assert!(n.as_vec().is_none());
+            // borrow is released, node can be modified again
+            n.add_child(None, Some("c".into()), NodeRef::null()).unwrap();
+            assert_eq!(n.as_map().unwrap().len(), 3);
+        }
+
+        #[test]
+        fn scalars() {
+            for n in &[NodeRef::null(), NodeRef::integer(1), NodeRef::string("s")] {
+                assert!(n.as_vec().is_none());
+                assert!(n.as_map().is_none());
+            }
+        }
+    }
+
+    mod interpolate {
+        use super::*;
+        use crate::opath::{ExprErrorDetail, NodeSet, ScopeMut};
+
+        #[test]
+        fn scalars() {
+            let n = NodeRef::from_json(
+                r#"{"host": "example.com", "url": "${$.host}", "port": "${8000 + 80}", "env": "${$env}"}"#,
+            )
+            .unwrap();
+            let scope = ScopeMut::new();
+            scope.set_var("env".into(), NodeSet::One(NodeRef::string("prod")));
+
+            let res = n.interpolate(&n, &scope).unwrap();
+            assert_eq!(
+                res.to_json(),
+                r#"{"host":"example.com","url":"example.com","port":8080,"env":"prod"}"#
+            );
+            // source tree is not modified
+            assert_eq!(n.get_child_key("url").unwrap().as_string(), "${$.host}");
+
+            let s = NodeRef::string("${$.host}").interpolate(&n, &scope).unwrap();
+            assert_eq!(s.as_string(), "example.com");
+        }
+
+        #[test]
+        fn nested() {
+            let n = NodeRef::from_json(
+                r#"{"name": "x", "a": {"b": ["${$.name}", {"c": "${@.d}", "d": 1}]}, "ref": "${$.a.b[0]}"}"#,
+            )
+            .unwrap();
+            let res = n.interpolate(&n, &ScopeMut::new()).unwrap();
+            assert_eq!(
+                res.to_json(),
+                r#"{"name":"x","a":{"b":["x",{"c":1,"d":1}]},"ref":"x"}"#
+            );
+        }
+
+        #[test]
+        fn depth_limit() {
+            let n = NodeRef::from_json(r#"{"x": "${$.x + 'x'}"}"#).unwrap();
+            let err = n.interpolate(&n, &ScopeMut::new()).unwrap_err();
+            let detail: &ExprErrorDetail = err.detail().downcast_ref().unwrap();
+            match *detail {
+                ExprErrorDetail::InterpolationDepthReached { .. } => {}
+                _ => panic!("InterpolationDepthReached expected"),
+            }
+        }
+    }
+
+    mod metrics {
+        use super::*;
+
+        static NESTED_JSON: &str = r#"
+            {
+                "a": 1,
+                "b": {
+                    "c": [1, 2, {"d": null}],
+                    "e": {}
+                },
+                "f": []
+            }
+        "#;
+
+        #[test]
+        fn node_count() {
+            let n = NodeRef::from_json(NESTED_JSON).unwrap();
+            assert_eq!(n.node_count(), 10);
+            assert_eq!(n.get_child_key("b").unwrap().node_count(), 7);
+            assert_eq!(NodeRef::null().node_count(), 1);
+        }
+
+        #[test]
+        fn max_depth() {
+            let n = NodeRef::from_json(NESTED_JSON).unwrap();
+            assert_eq!(n.max_depth(), 4);
+            assert_eq!(n.get_child_key("b").unwrap().max_depth(), 3);
+            assert_eq!(n.get_child_key("f").unwrap().max_depth(), 0);
+            assert_eq!(NodeRef::null().max_depth(), 0);
+        }
+    }
+
+    mod reorder_keys {
+        use super::*;
+
+        #[test]
+        fn move_key() {
+            let n = NodeRef::from_json(r#"{"a": 1, "b": 2, "c": 3}"#).unwrap();
+            assert!(n.move_key("c", 0).unwrap());
+            assert_eq!(n.to_json(), r#"{"c":3,"a":1,"b":2}"#);
+            assert!(n.move_key("c", 10).unwrap());
+            assert_eq!(n.to_json(), r#"{"a":1,"b":2,"c":3}"#);
+            assert!(!n.move_key("x", 0).unwrap());
+
+            let c = n.get_child_key("c").unwrap();
+            assert_eq!(c.data().index(), 2);
+        }
+
+        #[test]
+        fn sort_keys() {
+            let n = NodeRef::from_json(r#"{"b": 2, "c": 3, "a": 1}"#).unwrap();
+            n.sort_keys(|a, b| a.cmp(b)).unwrap();
+            assert_eq!(n.to_json(), r#"{"a":1,"b":2,"c":3}"#);
+            assert_eq!(n.get_child_key("a").unwrap().data().index(), 0);
+
+            n.sort_keys(|a, b| b.cmp(a)).unwrap();
+            assert_eq!(n.to_json(), r#"{"c":3,"b":2,"a":1}"#);
+        }
+
+        #[test]
+        fn non_object() {
+            let n = NodeRef::from_json("[1, 2]").unwrap();
+            let err = n.sort_keys(|a, b| a.cmp(b)).unwrap_err();
+            let detail: &TreeErrorDetail = err.detail().downcast_ref().unwrap();
+            match *detail {
+                TreeErrorDetail::ReorderKeysInvalidType { kind } => assert_eq!(kind, Kind::Array),
+                _ => panic!("ReorderKeysInvalidType expected"),
+            }
+        }
+    }
+
+    mod to_toml {
+        use super::*;
+
+        #[test]
+        fn array_of_tables() {
+            let n = NodeRef::from_json(r#"{
+                "name": "x",
+                "servers": [{"host": "a", "port": 1}, {"host": "b", "port": 2}],
+                "owner": "me"
+            }"#)
+            .unwrap();
+
+            let s = n.try_to_toml().unwrap();
+            assert_eq!(s.matches("[[servers]]").count(), 2);
+            assert!(s.find("owner").unwrap() < s.find("[[servers]]").unwrap());
+
+            let t = NodeRef::from_toml(&s).unwrap();
+            assert_eq!(t.get_child_key("owner").unwrap().as_string(), "me");
+            let servers = t.get_child_key("servers").unwrap();
+            assert_eq!(servers.get_child_index(1).unwrap().to_json(), r#"{"host":"b","port":2}"#);
+        }
+
+        #[test]
+        fn nested_tables() {
+            let n = NodeRef::from_json(r#"{"a": {"b": {"c": 1}, "d": [1, 2]}, "e": true}"#).unwrap();
+
+            let s = n.try_to_toml().unwrap();
+            let t = NodeRef::from_toml(&s).unwrap();
+            assert_eq!(t.to_json(), r#"{"e":true,"a":{"d":[1,2],"b":{"c":1}}}"#);
+        }
+
+        #[test]
+        fn top_level_array() {
+            let n = NodeRef::from_json(r#"[{"a": 1}]"#).unwrap();
+            let err = n.try_to_toml().unwrap_err();
+            let detail: &TreeErrorDetail = err.detail().downcast_ref().unwrap();
+            match *detail {
+                TreeErrorDetail::SerializationErr { format, .. } => assert_eq!(format, FileFormat::Toml),
+                _ => panic!("SerializationErr expected"),
+            }
+        }
+
+        #[test]
+        fn top_level_scalar() {
+            assert!(NodeRef::integer(1).try_to_toml().is_err());
+        }
+    }
+
+    mod try_to {
+        use super::*;
+
+        fn assert_serialization_err(res: TreeResult<String>, expected: FileFormat) {
+            let err = res.unwrap_err();
+            let detail: &TreeErrorDetail = err.detail().downcast_ref().unwrap();
+            match *detail {
+                TreeErrorDetail::SerializationErr { format, .. } => assert_eq!(format, expected),
+                _ => panic!("SerializationErr expected"),
+            }
+        }
+
+        #[test]
+        fn valid_tree() {
+            let n = NodeRef::from_json(r#"{"a": [1, 2.5, "x"], "b": {"c": true}}"#).unwrap();
+            assert_eq!(n.try_to_json().unwrap(), n.to_json());
+            assert_eq!(n.try_to_yaml().unwrap(), n.to_yaml());
+            assert_eq!(n.try_to_toml().unwrap(), n.to_toml());
+        }
+
+        #[test]
+        fn binary() {
+            let n = NodeRef::from_json(r#"{"a": {}}"#).unwrap();
+            n.get_child_key("a")
+                .unwrap()
+                .add_child(None, Some("b".into()), NodeRef::binary(vec![0u8, 1, 2]))
+                .unwrap();
+            assert_serialization_err(n.try_to_json(), FileFormat::Json);
+            assert_serialization_err(n.try_to_yaml(), FileFormat::Yaml);
+            assert_serialization_err(n.try_to_toml(), FileFormat::Toml);
+        }
+
+        #[test]
+        fn binary_infallible() {
+            let n = NodeRef::from_json(r#"{"a": 1}"#).unwrap();
+            n.add_child(None, Some("b".into()), NodeRef::binary(vec![0u8, 1, 2])).unwrap();
+            assert_eq!(n.to_toml(), "a = 1\nb = [0, 1, 2]\n");
+            assert_eq!(n.to_format(FileFormat::Toml, false), n.to_toml());
+        }
+
+        #[test]
+        fn nan() {
+            let n = NodeRef::array(vec![NodeRef::float(1.0), NodeRef::float(std::f64::NAN)]);
+            assert_serialization_err(n.try_to_json(), FileFormat::Json);
+            assert!(n.try_to_yaml().is_ok());
+        }
+    }
+
+    mod symbol {
+        use super::*;
+
+        #[test]
+        fn interned_keys() {
+            let keys = ["id", "name", "value"];
+            let objects: Vec<NodeRef> = (0..1000)
+                .map(|i| {
+                    let mut props = Properties::with_capacity(keys.len());
+                    for k in keys.iter() {
+                        props.insert(Symbol::from(*k), NodeRef::integer(i));
+                    }
+                    NodeRef::object(props)
+                })
+                .collect();
+
+            fn object_keys(n: &NodeRef) -> Vec<Symbol> {
+                match *n.data().value() {
+                    Value::Object(ref props) => props.iter().map(|(k, _)| k.clone()).collect(),
+                    _ => unreachable!(),
+                }
+            }
+
+            let first_keys = object_keys(&objects[0]);
+            for o in objects.iter().skip(1) {
+                assert_eq!(object_keys(o), first_keys);
+            }
+            assert_eq!(first_keys[1].as_str(), "name");
+            assert_eq!(Symbol::from("name"), Symbol::from(String::from("name")));
+            assert_ne!(Symbol::from("name"), Symbol::from("id"));
+        }
+    }
+
+    mod to_tree_string {
+        use super::*;
+
+        #[test]
+        fn nested_document() {
+            let n = NodeRef::from_json(
+                r#"{"name": "app", "ports": [80, 443], "db": {"host": "localhost", "opts": {}}}"#,
+            )
+            .unwrap();
+            assert_eq!(
+                n.to_tree_string(),
+                "name: \"app\"\n\
+                 ports:\n\
+                 \x20 [0]: 80\n\
+                 \x20 [1]: 443\n\
+                 db:\n\
+                 \x20 host: \"localhost\"\n\
+                 \x20 opts: {}\n"
+            );
+        }
+
+        #[test]
+        fn array_indices() {
+            let n = NodeRef::from_json(r#"[{"a": [true, null]}, []]"#).unwrap();
+            let lines: Vec<&str> = n.to_tree_string().lines().collect();
+            assert_eq!(lines, vec!["[0]:", "  a:", "    [0]: true", "    [1]: null", "[1]: []"]);
+        }
+
+        #[test]
+        fn scalar() {
+            assert_eq!(NodeRef::string("text").to_tree_string(), "\"text\"\n");
+        }
+    }
+
+    mod trivia {
+        use super::*;
+
+        static COMMENTED_TOML: &str = r#"# application name
+name = "app"
+
+# listen port
+# (must be > 1024)
+port = 8080
+
+# not attached, followed by a blank line
+
+debug = false
+
+# database settings
+[db]
+# database host
+host = "localhost"
+
+# first server
+[[servers]]
+addr = "10.0.0.1"
+
+# second server
+[[servers]]
+addr = "10.0.0.2"
+"#;
+
+        #[test]
+        fn parse_comments() {
+            let n = NodeRef::from_toml_with_trivia(COMMENTED_TOML).unwrap();
+            let trivia = |n: NodeRef| n.data().metadata().trivia().map(|t| t.to_string());
+
+            assert_eq!(trivia(n.get_child_key("name").unwrap()).unwrap(), "# application name\n");
+            assert_eq!(
+                trivia(n.get_child_key("port").unwrap()).unwrap(),
+                "# listen port\n# (must be > 1024)\n"
+            );
+            assert_eq!(trivia(n.get_child_key("debug").unwrap()), None);
+            assert_eq!(trivia(n.get_child_key("db").unwrap()).unwrap(), "# database settings\n");
+
+            let plain = NodeRef::from_toml(COMMENTED_TOML).unwrap();
+            assert_eq!(trivia(plain.get_child_key("name").unwrap()), None);
+        }
+
+        #[test]
+        fn comments_survive_modification() {
+            let n = NodeRef::from_toml_with_trivia(COMMENTED_TOML).unwrap();
+            n.get_child_key("port").unwrap().replace_with(NodeRef::integer(9090)).unwrap();
+
+            let s = n.to_toml_with_trivia().unwrap();
+            assert!(s.contains("# application name\nname = \"app\"\n"));
+            assert!(s.contains("# listen port\n# (must be > 1024)\nport = 9090\n"));
+            assert!(s.contains("# database settings\n[db]\n# database host\nhost = \"localhost\"\n"));
+            assert!(s.contains("# first server\n[[servers]]\naddr = \"10.0.0.1\"\n"));
+            assert!(s.contains("# second server\n[[servers]]\naddr = \"10.0.0.2\"\n"));
+            assert!(!s.contains("not attached"));
+
+            assert!(NodeRef::from_toml(&s).unwrap().is_equal_deep(&n));
+        }
+
+        #[test]
+        fn nested_arrays_of_tables() {
+            let toml = r#"# first a
+[[a]]
+x = 1
+
+# b of first a
+[[a.b]]
+y = 1
+
+# second a
+[[a]]
+x = 2
+
+# first b of second a
+[[a.b]]
+y = 2
+
+# second b of second a
+[[a.b]]
+y = 3
+"#;
+            let n = NodeRef::from_toml_with_trivia(toml).unwrap();
+            let s = n.to_toml_with_trivia().unwrap();
+            assert!(s.contains("# first a\n[[a]]\nx = 1\n"));
+            assert!(s.contains("# b of first a\n[[a.b]]\ny = 1\n"));
+            assert!(s.contains("# second a\n[[a]]\nx = 2\n"));
+            assert!(s.contains("# first b of second a\n[[a.b]]\ny = 2\n"));
+            assert!(s.contains("# second b of second a\n[[a.b]]\ny = 3\n"));
+        }
+
+        #[test]
+        fn multiline_string_untouched() {
+            let toml = r#"# description
+text = """
+[db]
+host = "x"
+"""
+
+# database settings
+[db]
+# database host
+host = "localhost"
+"#;
+            let n = NodeRef::from_toml_with_trivia(toml).unwrap();
+            let stripped: String = toml
+                .lines()
+                .filter(|l| !l.starts_with('#'))
+                .map(|l| format!("{}\n", l))
+                .collect();
+            assert_eq!(serial::toml::insert_trivia(&n, &stripped), toml);
+        }
+    }
+
+    mod ancestry {
+        use super::*;
+
+        static TREE_JSON: &str = r#"{"a": {"b": {"c": 1, "d": [2, 3]}, "e": 4}, "f": 5}"#;
+
+        fn get(n: &NodeRef, path: &str) -> NodeRef {
+            Opath::parse(path).unwrap().apply_one(n, n).unwrap()
+        }
+
+        #[test]
+        fn is_descendant_of() {
+            let n = NodeRef::from_json(TREE_JSON).unwrap();
+            let a = get(&n, "$.a");
+            let d1 = get(&n, "$.a.b.d[1]");
+
+            assert!(d1.is_descendant_of(&a));
+            assert!(d1.is_descendant_of(&n));
+            assert!(!a.is_descendant_of(&d1));
+            assert!(!a.is_descendant_of(&a));
+            assert!(!get(&n, "$.f").is_descendant_of(&a));
+        }
+
+        #[test]
+        fn common_ancestor() {
+            let n = NodeRef::from_json(TREE_JSON).unwrap();
+            let c = get(&n, "$.a.b.c");
+            let d0 = get(&n, "$.a.b.d[0]");
+            let e = get(&n, "$.a.e");
+            let f = get(&n, "$.f");
+
+            let b = NodeRef::common_ancestor(&c, &d0).unwrap();
+            assert!(b.is_ref_eq(&get(&n, "$.a.b")));
+            let a = NodeRef::common_ancestor(&d0, &e).unwrap();
+            assert!(a.is_ref_eq(&get(&n, "$.a")));
+            assert!(NodeRef::common_ancestor(&c, &f).unwrap().is_ref_eq(&n));
+            assert!(NodeRef::common_ancestor(&a, &c).unwrap().is_ref_eq(&a));
+        }
+
+        #[test]
+        fn separate_trees() {
+            let n = NodeRef::from_json(TREE_JSON).unwrap();
+            let o = NodeRef::from_json(TREE_JSON).unwrap();
+            assert!(NodeRef::common_ancestor(&get(&n, "$.a"), &get(&o, "$.a")).is_none());
+        }
+    }
 }
@@ -39,6 +39,49 @@ impl Kind {
             Kind::Object => "object",
         }
     }
+
+    pub fn all() -> &'static [Kind] {
+        &[
+            Kind::Null,
+            Kind::Boolean,
+            Kind::Integer,
+            Kind::Float,
+            Kind::String,
+            Kind::Binary,
+            Kind::Array,
+            Kind::Object,
+        ]
+    }
+
+    /// Parses a kind name as returned by `as_str()`.
+    pub fn from_str_opt(s: &str) -> Option<Kind> {
+        Kind::all().iter().find(|k| k.as_str() == s).cloned()
+    }
+
+    /// Parses a type name as returned by `as_type_str()`. Since both integers and floats
+    /// share the `"number"` type name, the result is a mask of matching kinds.
+    pub fn from_type_str(s: &str) -> Option<KindMask> {
+        let mask = Kind::all()
+            .iter()
+            .filter(|k| k.as_type_str() == s)
+            .fold(KindMask::none(), |m, k| m.with(*k));
+        if mask == KindMask::none() {
+            None
+        } else {
+            Some(mask)
+        }
+    }
+}
+
+impl std::str::FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match Kind::from_str_opt(s) {
+            Some(k) => Ok(k),
+            None => Err(format!("unknown kind '{}'", s)),
+        }
+    }
 }
 
 impl std::fmt::Display for Kind {
@@ -144,6 +187,14 @@ impl Value {
         }
     }
 
+    fn clone_shallow(&self) -> Value {
+        match *self {
+            Value::Array(ref elems) => Value::Array(elems.clone()),
+            Value::Object(ref props) => Value::Object(props.clone()),
+            _ => self.deep_copy(),
+        }
+    }
+
     fn shrink_to_fit(&mut self) {
         match *self {
             Value::String(ref mut s) => {
@@ -266,6 +317,32 @@ impl Node {
         }
     }
 
+    /// Returns the content of a binary node decoded as UTF-8, or `None` if the bytes are not
+    /// valid UTF-8. String nodes are returned unchanged.
+    pub fn as_utf8_string(&self) -> Option<String> {
+        match self.value {
+            Value::Binary(ref bytes) => std::str::from_utf8(bytes).ok().map(|s| s.to_string()),
+            Value::String(ref s) => Some(s.clone()),
+            _ => None,
+        }
+    }
+
+    /// Returns the content of a binary node as lowercase hex digits.
+    pub fn as_hex_string(&self) -> Option<String> {
+        use std::fmt::Write;
+
+        match self.value {
+            Value::Binary(ref bytes) => {
+                let mut s = String::with_capacity(bytes.len() * 2);
+                for b in bytes.iter() {
+                    write!(s, "{:02x}", b).unwrap();
+                }
+                Some(s)
+            }
+            _ => None,
+        }
+    }
+
     pub fn as_boolean(&self) -> bool {
         match self.value {
             Value::Null => false,
@@ -584,6 +661,10 @@ impl Node {
         Node::new(self.metadata.deep_copy(), self.value.deep_copy())
     }
 
+    pub fn clone_shallow(&self) -> Node {
+        Node::new(self.metadata.deep_copy(), self.value.clone_shallow())
+    }
+
     pub fn shrink_to_fit(&mut self) {
         self.value.shrink_to_fit();
     }
@@ -625,7 +706,7 @@ impl std::fmt::Display for Node {
             Value::Null => write!(f, "null"),
             Value::Boolean(b) => write!(f, "{}", b),
             Value::Integer(n) => write!(f, "{}", n),
-            Value::Float(n) => write!(f, "{}", n),
+            Value::Float(n) => write!(f, "{}", float_format().format(n)),
             Value::String(ref s) => write!(f, "{:?}", s),
             Value::Binary(ref b) => write!(f, "{:?}", b),
             Value::Array(ref elems) => {
@@ -745,4 +826,32 @@ mod tests {
         assert!(!m.has(Kind::Array));
         assert!(!m.has(Kind::Object));
     }
+
+    #[test]
+    fn kind_from_str() {
+        for k in Kind::all() {
+            assert_eq!(k.as_str().parse::<Kind>(), Ok(*k));
+        }
+        assert!("number".parse::<Kind>().is_err());
+        assert!("unknown".parse::<Kind>().is_err());
+    }
+
+    #[test]
+    fn kind_from_type_str() {
+        for k in Kind::all() {
+            assert!(Kind::from_type_str(k.as_type_str()).unwrap().has(*k));
+        }
+        assert_eq!(Kind::from_type_str("number"), Some(KindMask::number()));
+        assert_eq!(Kind::from_type_str("string"), Some(KindMask::string()));
+        assert_eq!(Kind::from_type_str("integer"), None);
+    }
+
+    #[test]
+    fn display_float() {
+        let n = Node::new(Metadata::new(), Value::Float(1.0));
+        assert_eq!(n.to_string(), "1.0");
+
+        let n = Node::new(Metadata::new(), Value::Float(2.5e-7));
+        assert_eq!(n.to_string(), "2.5e-7");
+    }
 }
@@ -0,0 +1,230 @@
+use super::*;
+
+#[derive(Debug, Display, Detail, PartialEq)]
+#[diag(code_offset = 800)]
+pub enum ValidationErrorDetail {
+    #[display(fmt = "expected type '{expected}', found '{found}'")]
+    TypeMismatch { expected: String, found: Kind },
+
+    #[display(fmt = "missing required property '{key}'")]
+    MissingProperty { key: String },
+
+    #[display(fmt = "value is not one of the allowed values")]
+    NotInEnum,
+
+    #[display(fmt = "value {value} is less than minimum {minimum}")]
+    BelowMinimum { value: f64, minimum: f64 },
+
+    #[display(fmt = "value {value} is greater than maximum {maximum}")]
+    AboveMaximum { value: f64, maximum: f64 },
+}
+
+#[derive(Debug)]
+pub struct ValidationError {
+    path: Opath,
+    detail: ValidationErrorDetail,
+}
+
+impl ValidationError {
+    fn new(node: &NodeRef, detail: ValidationErrorDetail) -> ValidationError {
+        ValidationError {
+            path: Opath::from(node),
+            detail,
+        }
+    }
+
+    pub fn path(&self) -> &Opath {
+        &self.path
+    }
+
+    pub fn detail(&self) -> &ValidationErrorDetail {
+        &self.detail
+    }
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.detail)
+    }
+}
+
+/// Validates `instance` against a JSON-Schema-like `schema`. Supported keywords are
+/// `type`, `required`, `properties`, `items`, `enum`, `minimum` and `maximum`.
+///
+/// Type names can be either kind names (e.g. `"integer"`) or type names (e.g. `"number"`).
+pub fn validate(instance: &NodeRef, schema: &NodeRef) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    validate_node(instance, schema, &mut errors);
+    errors
+}
+
+fn type_mask(name: &str) -> KindMask {
+    match Kind::from_str_opt(name) {
+        Some(kind) => KindMask::none().with(kind),
+        None => Kind::from_type_str(name).unwrap_or_else(KindMask::none),
+    }
+}
+
+fn validate_node(n: &NodeRef, schema: &NodeRef, errors: &mut Vec<ValidationError>) {
+    let kind = n.data().kind();
+
+    if let Some(t) = schema.get_child_key("type") {
+        let names: Vec<String> = match *t.data().value() {
+            Value::Array(ref elems) => elems.iter().map(|e| e.as_string()).collect(),
+            _ => vec![t.as_string()],
+        };
+        if !names.iter().any(|name| type_mask(name).has(kind)) {
+            errors.push(ValidationError::new(
+                n,
+                ValidationErrorDetail::TypeMismatch {
+                    expected: names.join("|"),
+                    found: kind,
+                },
+            ));
+            return;
+        }
+    }
+
+    if let Some(e) = schema.get_child_key("enum") {
+        if let Value::Array(ref elems) = *e.data().value() {
+            if !elems.iter().any(|e| e.is_equal_deep(n)) {
+                errors.push(ValidationError::new(n, ValidationErrorDetail::NotInEnum));
+            }
+        }
+    }
+
+    match kind {
+        Kind::Integer | Kind::Float => {
+            let value = n.as_float();
+            if let Some(minimum) = schema.get_child_key("minimum") {
+                let minimum = minimum.as_float();
+                if value < minimum {
+                    errors.push(ValidationError::new(
+                        n,
+                        ValidationErrorDetail::BelowMinimum { value, minimum },
+                    ));
+                }
+            }
+            if let Some(maximum) = schema.get_child_key("maximum") {
+                let maximum = maximum.as_float();
+                if value > maximum {
+                    errors.push(ValidationError::new(
+                        n,
+                        ValidationErrorDetail::AboveMaximum { value, maximum },
+                    ));
+                }
+            }
+        }
+        Kind::Object => {
+            if let Some(required) = schema.get_child_key("required") {
+                if let Value::Array(ref keys) = *required.data().value() {
+                    for key in keys.iter() {
+                        let key = key.as_string();
+                        if n.get_child_key(&key).is_none() {
+                            errors.push(ValidationError::new(
+                                n,
+                                ValidationErrorDetail::MissingProperty { key },
+                            ));
+                        }
+                    }
+                }
+            }
+            if let Some(properties) = schema.get_child_key("properties") {
+                if let Value::Object(ref props) = *properties.data().value() {
+                    for (key, s) in props.iter() {
+                        if let Some(c) = n.get_child_key(key.as_ref()) {
+                            validate_node(&c, s, errors);
+                        }
+                    }
+                }
+            }
+        }
+        Kind::Array => {
+            if let Some(items) = schema.get_child_key("items") {
+                if let Value::Array(ref elems) = *n.data().value() {
+                    for e in elems.iter() {
+                        validate_node(e, &items, errors);
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static SCHEMA_JSON: &str = r#"
+        {
+            "type": "object",
+            "required": ["name", "port"],
+            "properties": {
+                "name": {"type": "string"},
+                "port": {"type": "integer", "minimum": 1, "maximum": 65535},
+                "mode": {"enum": ["dev", "prod"]},
+                "tags": {"type": "array", "items": {"type": "string"}}
+            }
+        }
+    "#;
+
+    fn validate_json(json: &str) -> Vec<ValidationError> {
+        let schema = NodeRef::from_json(SCHEMA_JSON).unwrap();
+        let n = NodeRef::from_json(json).unwrap();
+        validate(&n, &schema)
+    }
+
+    #[test]
+    fn valid_document() {
+        let errors = validate_json(r#"{"name": "web", "port": 80, "mode": "prod", "tags": ["a", "b"]}"#);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn type_mismatch() {
+        let errors = validate_json(r#"{"name": "web", "port": 80, "tags": ["a", 2]}"#);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path().to_string(), "$.tags[1]");
+        assert_eq!(
+            errors[0].detail(),
+            &ValidationErrorDetail::TypeMismatch {
+                expected: "string".into(),
+                found: Kind::Integer,
+            }
+        );
+    }
+
+    #[test]
+    fn missing_required_key() {
+        let errors = validate_json(r#"{"name": "web"}"#);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path().to_string(), "$");
+        assert_eq!(
+            errors[0].detail(),
+            &ValidationErrorDetail::MissingProperty { key: "port".into() }
+        );
+    }
+
+    #[test]
+    fn enum_violation() {
+        let errors = validate_json(r#"{"name": "web", "port": 80, "mode": "test"}"#);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path().to_string(), "$.mode");
+        assert_eq!(errors[0].detail(), &ValidationErrorDetail::NotInEnum);
+    }
+
+    #[test]
+    fn range_violation() {
+        let errors = validate_json(r#"{"name": "web", "port": 70000}"#);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path().to_string(), "$.port");
+        assert_eq!(
+            errors[0].detail(),
+            &ValidationErrorDetail::AboveMaximum {
+                value: 70000.0,
+                maximum: 65535.0,
+            }
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use super::*;
+
+/// Maps node spans back to the source text they were parsed from.
+#[derive(Debug, Clone, Copy)]
+pub struct SourceMap<'a> {
+    source: &'a str,
+}
+
+impl<'a> SourceMap<'a> {
+    pub fn new(source: &'a str) -> SourceMap<'a> {
+        SourceMap { source }
+    }
+
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    /// Returns source text covered by `span`. Spans reaching past the end of source
+    /// are truncated, and spans not aligned to character boundaries yield an empty string.
+    pub fn snippet(&self, span: Span) -> &'a str {
+        let end = span.to.offset.min(self.source.len());
+        let start = span.from.offset.min(end);
+        self.source.get(start..end).unwrap_or("")
+    }
+
+    /// Returns source text of node `n`, if it has a span.
+    pub fn node_snippet(&self, n: &NodeRef) -> Option<&'a str> {
+        n.source_span().map(|span| self.snippet(span))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static TEST_JSON: &str = r#"{
+    "a": {
+        "b": [1, {"c": "text"}, 42]
+    }
+}"#;
+
+    #[test]
+    fn nested_value_snippet() {
+        let n = NodeRef::from_json(TEST_JSON).unwrap();
+        let b = n.get_child_key("a").unwrap().get_child_key("b").unwrap();
+        let map = SourceMap::new(TEST_JSON);
+
+        assert_eq!(map.node_snippet(&b).unwrap(), r#"[1, {"c": "text"}, 42]"#);
+        assert_eq!(
+            map.node_snippet(&b.get_child_index(1).unwrap()).unwrap(),
+            r#"{"c": "text"}"#
+        );
+        assert_eq!(map.node_snippet(&b.get_child_index(2).unwrap()).unwrap(), "42");
+    }
+
+    #[test]
+    fn node_without_span() {
+        let map = SourceMap::new(TEST_JSON);
+        assert_eq!(map.node_snippet(&NodeRef::integer(1)), None);
+    }
+
+    #[test]
+    fn span_out_of_bounds() {
+        let map = SourceMap::new("abc");
+        assert_eq!(map.snippet(Span::with(1, 0, 1, 10, 0, 10)), "bc");
+        assert_eq!(map.snippet(Span::with(5, 0, 5, 10, 0, 10)), "");
+    }
+}
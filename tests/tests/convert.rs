@@ -0,0 +1,63 @@
+use super::*;
+
+static CONFIG_JSON: &str = r#"{
+    "name": "app",
+    "port": 8080,
+    "debug": false,
+    "tags": ["a", "b"],
+    "db": {"host": "localhost", "pool": 4.5}
+}"#;
+
+#[test]
+fn json_to_yaml_and_back() {
+    let (_tmp, dir) = get_tmp_dir();
+    write_file!(dir.join("config.json"), CONFIG_JSON);
+
+    convert_file(&dir.join("config.json"), &dir.join("config.yaml")).unwrap();
+    convert_file(&dir.join("config.yaml"), &dir.join("back.json")).unwrap();
+
+    let orig = NodeRef::from_json(CONFIG_JSON).unwrap();
+    let yaml = NodeRef::from_file(&dir.join("config.yaml"), None).unwrap();
+    let back = NodeRef::from_file(&dir.join("back.json"), None).unwrap();
+    assert!(orig.is_equal_deep(&yaml));
+    assert!(orig.is_equal_deep(&back));
+}
+
+#[test]
+fn json_to_toml_and_back() {
+    let (_tmp, dir) = get_tmp_dir();
+    write_file!(dir.join("config.json"), CONFIG_JSON);
+
+    convert_file(&dir.join("config.json"), &dir.join("config.toml")).unwrap();
+    convert_file(&dir.join("config.toml"), &dir.join("back.json")).unwrap();
+
+    let orig = NodeRef::from_json(CONFIG_JSON).unwrap();
+    let toml = NodeRef::from_file(&dir.join("config.toml"), None).unwrap();
+    let back = NodeRef::from_file(&dir.join("back.json"), None).unwrap();
+    assert!(orig.is_equal_deep(&toml));
+    assert!(orig.is_equal_deep(&back));
+}
+
+#[test]
+fn text_is_copied_unchanged() {
+    let (_tmp, dir) = get_tmp_dir();
+    let content = "{ not: valid json\n";
+    write_file!(dir.join("notes.txt"), content);
+
+    convert_file(&dir.join("notes.txt"), &dir.join("notes.json")).unwrap();
+
+    let copied = std::fs::read_to_string(dir.join("notes.json")).unwrap();
+    assert_eq!(copied, content);
+}
+
+#[test]
+fn reformat() {
+    let n = NodeRef::from_json(CONFIG_JSON).unwrap();
+
+    let yaml = n.reformat(FileFormat::Yaml).unwrap();
+    assert!(n.is_equal_deep(&NodeRef::from_yaml(&yaml).unwrap()));
+
+    let json = n.reformat(FileFormat::Json).unwrap();
+    assert!(json.contains('\n'));
+    assert!(n.is_equal_deep(&NodeRef::from_json(&json).unwrap()));
+}
@@ -124,5 +124,6 @@ impl NodeRefExt for NodeRef {
     }
 }
 
+mod convert;
 mod opath;
 mod serial;
@@ -112,6 +112,56 @@ fn level_root() {
     assert_eq!(res.as_integer().unwrap(), 0)
 }
 
+#[test]
+fn depth_relative_to_current() {
+    let json: &str = r#"
+                        {
+                            "child0": {
+                                "child0_0": {
+                                    "value": 1
+                                }
+                            }
+                        }"#;
+
+    let n = NodeRef::from_json(json).unwrap();
+    let child0 = n.get_child_key("child0").unwrap();
+    let child0_0 = child0.get_child_key("child0_0").unwrap();
+    let opath = opath::Opath::parse("$.child0.child0_0.value.@depth").unwrap();
+
+    let depth = |current: &NodeRef| opath.apply_one(&n, current).unwrap().as_integer().unwrap();
+
+    assert_eq!(depth(&n), 3);
+    assert_eq!(depth(&child0), 2);
+    assert_eq!(depth(&child0_0), 1);
+
+    let value = child0_0.get_child_key("value").unwrap();
+    assert_eq!(depth(&value), 0);
+}
+
+#[test]
+fn depth_dynamic_property() {
+    let json: &str = r#"{"a": {"b": 1}}"#;
+
+    let n = NodeRef::from_json(json).unwrap();
+    let a = n.get_child_key("a").unwrap();
+    let res = opath::Opath::parse("$.a.b.('@depth')").unwrap().apply_one(&n, &a).unwrap();
+    assert_eq!(res.as_integer().unwrap(), 1);
+
+    let res = opath::Opath::parse("$.a.b.('@dep' + 'th')").unwrap().apply_one(&n, &a).unwrap();
+    assert_eq!(res.as_integer().unwrap(), 1);
+}
+
+#[test]
+fn depth_not_descendant() {
+    let json: &str = r#"{"a": {"b": 1}, "c": {}}"#;
+
+    let n = NodeRef::from_json(json).unwrap();
+    let c = n.get_child_key("c").unwrap();
+    let res = opath::Opath::parse("$.a.b.@depth").unwrap().apply_one(&n, &c).unwrap();
+
+    assert_eq!(res.as_integer().unwrap(), 2)
+}
+
 #[test]
 fn type_null() {
     let results = query("null_value.@type", EXAMPLE_JSON);
@@ -569,4 +569,101 @@ mod wildcards {
         let exp_root = NodeRef::from_json(json).unwrap();
         assert!(root.is_identical_deep(&exp_root));
     }
+
+    static LEAVES_JSON: &str = r#"
+                            {
+                                "type": "root",
+                                "a": {
+                                    "type": "branch",
+                                    "x": {"type": "leaf", "v": 1},
+                                    "y": {
+                                        "type": "branch",
+                                        "z": {"type": "leaf", "v": 2}
+                                    }
+                                },
+                                "b": {"type": "leaf", "v": 3}
+                            }"#;
+
+    fn leaf_values(query_str: &str) -> Vec<i64> {
+        let mut values: Vec<i64> = query(query_str, LEAVES_JSON)
+            .iter()
+            .map(|n| n.get_child_key("v").unwrap().as_integer().unwrap())
+            .collect();
+        values.sort();
+        values
+    }
+
+    #[test]
+    fn double_star_depth_open_max() {
+        let results = query("@.**{2,}", LEAVES_JSON);
+        assert_eq!(results.len(), 11);
+    }
+
+    #[test]
+    fn double_star_depth_bounds() {
+        let results = query("@.**{2,3}", LEAVES_JSON);
+        assert_eq!(results.len(), 9);
+    }
+
+    #[test]
+    fn double_star_predicate() {
+        assert_eq!(leaf_values("@.**{1,}[@.type=='leaf']"), vec![1, 2, 3]);
+        assert_eq!(leaf_values("@.**[@.type=='leaf']"), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn double_star_depth_bounds_predicate() {
+        assert_eq!(leaf_values("@.**{1,1}[@.type=='leaf']"), vec![3]);
+        assert_eq!(leaf_values("@.**{2,3}[@.type=='leaf']"), vec![1, 2]);
+        assert_eq!(leaf_values("@.**{3}[@.type=='leaf']"), vec![2]);
+
+        let results = query("@.**{,2}[@.type=='branch']", LEAVES_JSON);
+        assert_eq!(results.len(), 2);
+    }
+
+    #[test]
+    fn double_star_predicate_then_property() {
+        let mut values: Vec<i64> = query("@.**{1,}[@.type=='leaf'].v", LEAVES_JSON)
+            .iter()
+            .map(|n| n.as_integer().unwrap())
+            .collect();
+        values.sort();
+        assert_eq!(values, vec![1, 2, 3]);
+    }
+}
+
+mod safe_navigation {
+    use super::*;
+
+    static JSON: &str = r#"{"a": null, "b": {"c": 1}}"#;
+
+    #[test]
+    fn null_left_side() {
+        assert!(query("@.a?.c", JSON).is_empty());
+        assert!(query("@.a?.@kind", JSON).is_empty());
+
+        let results = query("@.a.@kind", JSON);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].as_string(), "null");
+    }
+
+    #[test]
+    fn missing_left_side() {
+        assert!(query("@.x?.c", JSON).is_empty());
+        assert!(query("@.x?.@kind", JSON).is_empty());
+        assert!(query("@.x.@kind", JSON).is_empty());
+    }
+
+    #[test]
+    fn existing_left_side() {
+        let results = query("@.b?.c", JSON);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].as_integer(), Some(1));
+    }
+
+    #[test]
+    fn short_circuits_rest_of_path() {
+        assert!(query("@.a?.c.length()", JSON).is_empty());
+        assert_eq!(query("@.b?.c?.@kind", JSON)[0].as_string(), "integer");
+    }
 }
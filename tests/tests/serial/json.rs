@@ -490,6 +490,42 @@ fn duplicated_keys() {
     assert_err!(err, JsonParseErrorDetail::RedefinedKey {..});
 }
 
+macro_rules! parse_node_with_policy {
+    ($input: expr, $policy: expr) => {{
+        let mut r = kg_diag::MemCharReader::new($input.as_bytes());
+        let mut parser = crate::serial::json::JsonParser::new().with_duplicate_key_policy($policy);
+        parser.parse(&mut r)
+    }};
+}
+
+#[test]
+fn duplicated_keys_policy_error() {
+    let err = parse_node_with_policy!(r#"{"a":1,"a":2}"#, DuplicateKeyPolicy::Error).unwrap_err();
+
+    assert_err!(err, JsonParseErrorDetail::RedefinedKey {..});
+}
+
+#[test]
+fn duplicated_keys_policy_last_wins() {
+    let node = parse_node_with_policy!(r#"{"a":1,"a":2}"#, DuplicateKeyPolicy::LastWins).unwrap();
+
+    assert_eq!(node.to_json(), r#"{"a":2}"#);
+}
+
+#[test]
+fn duplicated_keys_policy_first() {
+    let node = parse_node_with_policy!(r#"{"a":1,"a":2}"#, DuplicateKeyPolicy::First).unwrap();
+
+    assert_eq!(node.to_json(), r#"{"a":1}"#);
+}
+
+#[test]
+fn duplicated_keys_policy_array() {
+    let node = parse_node_with_policy!(r#"{"a":1,"b":true,"a":2,"a":3}"#, DuplicateKeyPolicy::Array).unwrap();
+
+    assert_eq!(node.to_json(), r#"{"a":[1,2,3],"b":true}"#);
+}
+
 #[test]
 fn arrays() {
     let input = r#"{
@@ -1382,3 +1382,102 @@ fn redefined_static_array_as_array_of_tables_nested() {
 
     assert_err!(err, TomlParseErrDetail::RedefinedKey {..});
 }
+
+macro_rules! assert_round_trip {
+    ($input: expr) => {{
+        let node: NodeRef = parse_node!($input);
+        let output = node.to_toml();
+        let parsed: NodeRef = parse_node!(output);
+        assert!(
+            node.is_equal_deep(&parsed),
+            "round trip changed shape:\n{}\n{}",
+            node.to_json(),
+            parsed.to_json()
+        );
+        node
+    }};
+}
+
+#[test]
+fn round_trip_dotted_keys() {
+    let input = r#"
+        name = "Orange"
+        physical.color = "orange"
+        physical.shape.kind = "round"
+        site."google.com" = true
+    "#;
+    let node = assert_round_trip!(input);
+
+    assert_eq!(
+        r#"{"name":"Orange","physical":{"color":"orange","shape":{"kind":"round"}},"site":{"google.com":true}}"#,
+        node.to_json()
+    );
+}
+
+#[test]
+fn round_trip_dotted_keys_in_table() {
+    let input = r#"
+        [server]
+        host.name = "localhost"
+        host.port = 8080
+    "#;
+    let node = assert_round_trip!(input);
+
+    assert_eq!(
+        r#"{"server":{"host":{"name":"localhost","port":8080}}}"#,
+        node.to_json()
+    );
+}
+
+#[test]
+fn round_trip_inline_tables() {
+    let input = r#"
+        name = { first = "Tom", last = "Preston-Werner" }
+        point = { x = 1, y = { z = 2 } }
+        points = [ { x = 1, y = 2 }, { x = 7, y = 8 } ]
+        nested = [ [1, 2], ["a"] ]
+    "#;
+    let node = assert_round_trip!(input);
+
+    assert_eq!(
+        r#"{"name":{"first":"Tom","last":"Preston-Werner"},"point":{"x":1,"y":{"z":2}},"points":[{"x":1,"y":2},{"x":7,"y":8}],"nested":[[1,2],["a"]]}"#,
+        node.to_json()
+    );
+}
+
+#[test]
+fn round_trip_arrays_of_tables() {
+    let input = r#"
+        title = "fruits"
+
+        [[fruit]]
+          name = "apple"
+
+          [fruit.physical]
+            color = "red"
+
+          [[fruit.variety]]
+            name = "red delicious"
+
+        [[fruit]]
+          name = "banana"
+          tags = []
+    "#;
+    let node = assert_round_trip!(input);
+
+    assert_eq!(
+        r#"{"title":"fruits","fruit":[{"name":"apple","physical":{"color":"red"},"variety":[{"name":"red delicious"}]},{"name":"banana","tags":[]}]}"#,
+        node.to_json()
+    );
+}
+
+#[test]
+fn mixed_array_types_nested() {
+    let input = r#"
+        [table]
+        arr = [ "a", { k = 1 } ]
+    "#;
+    let err: ParseDiag = parse_node_err!(input);
+
+    assert_err!(err, TomlParseErrDetail::MixedArrayType {..});
+}